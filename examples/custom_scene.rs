@@ -0,0 +1,73 @@
+//! Builds a scene from scratch with the public API: a glass and a copper
+//! sphere on a checkered floor under a daylight sky, written as a PPM.
+//!
+//! `cargo run --example custom_scene [output.ppm]`
+
+use std::f64::consts::PI;
+use std::path::Path;
+
+use ray_tracer::bsdf::{conductor::Conductor, dielectric::Dielectric};
+use ray_tracer::geometry::{planes::PlaneXZ, sphere::Sphere};
+use ray_tracer::image::ppm;
+use ray_tracer::texture::procedural::Checker;
+use ray_tracer::{render, Camera, Material, PointLight, Scene, Sky, Tracer, Vector};
+
+fn main() {
+    let out = std::env::args().nth(1).unwrap_or("custom.ppm".to_string());
+
+    let mut scene = Scene::empty();
+    scene.shapes.push(Box::new(Sphere::new(
+        Vector::new(-0.8, 0.0, 4.0),
+        1.0,
+        Material {
+            bsdf: Some(Box::new(Dielectric::new(1.5, 0.0))),
+            ..Default::default()
+        },
+    )));
+    scene.shapes.push(Box::new(Sphere::new(
+        Vector::new(1.3, -0.3, 5.0),
+        0.7,
+        Material {
+            bsdf: Some(Box::new(Conductor::copper(0.2))),
+            ..Default::default()
+        },
+    )));
+    scene.shapes.push(Box::new(PlaneXZ::new(
+        -1.0,
+        false,
+        (-10.0, 10.0),
+        (0.0, 20.0),
+        Material {
+            texture: Some(Box::new(Checker::new(
+                Vector::one(),
+                Vector::new(0.2, 0.25, 0.3),
+                1.0,
+            ))),
+            ..Default::default()
+        },
+    )));
+    scene.point_lights.push(PointLight::new(
+        Vector::new(0.0, 3.0, 2.0),
+        Vector::one(),
+        4.0,
+    ));
+    scene.sky = Some(Sky::new(PI / 5.0, PI * 0.75, 3.0));
+
+    let (width, height) = (480, 320);
+    let camera = Camera::new(
+        Vector::new(0.0, 0.5, 0.0),
+        Vector::new(0.0, -0.15, 1.0),
+        Vector::one_y(),
+        PI / 3.0,
+        width as f64 / height as f64,
+    );
+    let mut tracer = Tracer::new();
+    tracer.samples_per_pixel = 4;
+
+    let frame = render(&tracer, &camera, &scene, (width, height), 0.0);
+    ppm::write(Path::new(&out), width, height, &frame.to_rgb()).unwrap_or_else(|err| {
+        println!("Cannot write {}! {}", out, err);
+        std::process::exit(1);
+    });
+    println!("Wrote {}", out);
+}
@@ -0,0 +1,37 @@
+//! Renders the demo scene without a window and saves it as a PNG.
+//!
+//! `cargo run --example render_demo [output.png] [samples per pixel]`
+
+use std::f64::consts::PI;
+use std::path::Path;
+
+use ray_tracer::image::png;
+use ray_tracer::{demo, render, Camera, Tracer, Vector};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let out = args.get(1).map_or("demo.png", |s| s.as_str());
+    let samples = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(4);
+
+    let (width, height) = (640, 480);
+    let camera = Camera::new(
+        Vector::zero(),
+        Vector::one_z(),
+        Vector::one_y(),
+        PI / 3.0,
+        width as f64 / height as f64,
+    );
+    let mut tracer = Tracer::new();
+    tracer.samples_per_pixel = samples;
+
+    let scene = demo::scene().unwrap_or_else(|err| {
+        println!("Cannot load environment map! {}", err);
+        std::process::exit(1);
+    });
+    let frame = render(&tracer, &camera, &scene, (width, height), 0.0);
+    png::write(Path::new(out), width, height, &frame.to_rgb()).unwrap_or_else(|err| {
+        println!("Cannot write {}! {}", out, err);
+        std::process::exit(1);
+    });
+    println!("Wrote {}", out);
+}
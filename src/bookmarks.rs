@@ -0,0 +1,237 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::camera::Camera;
+use crate::geometry::vector::Vector;
+
+pub const SLOT_COUNT: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bookmark {
+    pub pos: Vector,
+    pub forward: Vector,
+    pub up: Vector,
+    pub vfov: f64,
+    pub aperture: f64,
+    pub focus_distance: f64,
+}
+
+impl Bookmark {
+    pub fn from_camera(camera: &Camera) -> Bookmark {
+        Bookmark {
+            pos: camera.pos,
+            forward: camera.forward,
+            up: camera.up,
+            vfov: camera.vfov,
+            aperture: camera.aperture,
+            focus_distance: camera.focus_distance,
+        }
+    }
+
+    /// Moves `camera` to the viewpoint and lens of the bookmark. The aspect
+    /// ratio belongs to the output and the shutter to the render, both stay.
+    pub fn apply(&self, camera: &mut Camera) {
+        let view = Camera::new(self.pos, self.forward, self.up, self.vfov, camera.ar);
+        camera.pos = view.pos;
+        camera.forward = view.forward;
+        camera.up = view.up;
+        camera.right = view.right;
+        camera.vfov = view.vfov;
+        camera.vfov2_tg = view.vfov2_tg;
+        camera.aperture = self.aperture;
+        camera.focus_distance = self.focus_distance;
+    }
+}
+
+pub struct Bookmarks {
+    slots: [Option<Bookmark>; SLOT_COUNT],
+}
+
+impl Default for Bookmarks {
+    fn default() -> Bookmarks {
+        Bookmarks::new()
+    }
+}
+
+impl Bookmarks {
+    pub fn new() -> Bookmarks {
+        Bookmarks {
+            slots: [None; SLOT_COUNT],
+        }
+    }
+
+    pub fn store(&mut self, slot: usize, camera: &Camera) {
+        self.slots[slot] = Some(Bookmark::from_camera(camera));
+    }
+
+    pub fn recall(&self, slot: usize, camera: &mut Camera) -> bool {
+        match self.slots[slot] {
+            Some(bookmark) => {
+                bookmark.apply(camera);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&Bookmark> {
+        self.slots[slot].as_ref()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Bookmarks> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+
+    /// One line per occupied slot:
+    /// `slot px py pz fx fy fz ux uy uz vfov aperture focus_distance`
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        for (slot, bookmark) in self.slots.iter().enumerate() {
+            if let Some(b) = bookmark {
+                out += &format!(
+                    "{} {} {} {} {} {} {} {} {} {} {} {} {}\n",
+                    slot,
+                    b.pos.x,
+                    b.pos.y,
+                    b.pos.z,
+                    b.forward.x,
+                    b.forward.y,
+                    b.forward.z,
+                    b.up.x,
+                    b.up.y,
+                    b.up.z,
+                    b.vfov,
+                    b.aperture,
+                    b.focus_distance
+                );
+            }
+        }
+        out
+    }
+
+    pub fn parse(text: &str) -> io::Result<Bookmarks> {
+        let mut bookmarks = Bookmarks::new();
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid bookmark on line {}", line_no + 1),
+                )
+            };
+
+            // files from before the lens was saved have a pinhole camera
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 11 && fields.len() != 13 {
+                return Err(invalid());
+            }
+            let slot: usize = fields[0].parse().map_err(|_| invalid())?;
+            if slot >= SLOT_COUNT {
+                return Err(invalid());
+            }
+            let mut v = [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+            for (i, field) in fields[1..].iter().enumerate() {
+                v[i] = field.parse().map_err(|_| invalid())?;
+            }
+
+            bookmarks.slots[slot] = Some(Bookmark {
+                pos: Vector::new(v[0], v[1], v[2]),
+                forward: Vector::new(v[3], v[4], v[5]),
+                up: Vector::new(v[6], v[7], v[8]),
+                vfov: v[9],
+                aperture: v[10],
+                focus_distance: v[11],
+            });
+        }
+
+        Ok(bookmarks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::Camera;
+    use crate::geometry::vector::Vector;
+
+    use super::Bookmarks;
+
+    fn camera() -> Camera {
+        Camera::new(Vector::zero(), Vector::one_z(), Vector::one_y(), 1.0, 1.5)
+    }
+
+    #[test]
+    fn store_recall() {
+        let mut camera = camera();
+        let mut bookmarks = Bookmarks::new();
+
+        camera.shift_lateral(1.0);
+        camera.rotate_yaw(0.3);
+        bookmarks.store(3, &camera);
+        let (pos, forward) = (camera.pos, camera.forward);
+
+        camera.shift_longitudinal(2.0);
+        camera.rotate_pitch(0.2);
+        assert!(bookmarks.recall(3, &mut camera));
+        assert_eq!(camera.pos, pos);
+        assert_eq!(camera.forward, forward);
+        assert_eq!(camera.ar, 1.5);
+
+        assert!(!bookmarks.recall(4, &mut camera));
+    }
+
+    #[test]
+    fn recall_keeps_lens_and_shutter() {
+        let mut camera = camera();
+        camera.aperture = 0.1;
+        camera.focus_distance = 4.0;
+        let mut bookmarks = Bookmarks::new();
+        bookmarks.store(1, &camera);
+
+        camera.aperture = 0.0;
+        camera.shutter = 0.02;
+        camera.end_pose = Some((Vector::one(), camera.orientation()));
+        assert!(bookmarks.recall(1, &mut camera));
+        assert_eq!(camera.aperture, 0.1);
+        assert_eq!(camera.focus_distance, 4.0);
+        // motion blur set up for the render stays on
+        assert_eq!(camera.shutter, 0.02);
+        assert!(camera.end_pose.is_some());
+    }
+
+    #[test]
+    fn serialize_parse() {
+        let mut camera = camera();
+        let mut bookmarks = Bookmarks::new();
+        bookmarks.store(0, &camera);
+        camera.shift_vertical(0.25);
+        camera.rotate_roll(0.15);
+        camera.aperture = 0.05;
+        camera.focus_distance = 2.5;
+        bookmarks.store(9, &camera);
+
+        let parsed = Bookmarks::parse(&bookmarks.serialize()).unwrap();
+        assert_eq!(parsed.get(0), bookmarks.get(0));
+        assert_eq!(parsed.get(9), bookmarks.get(9));
+        assert!(parsed.get(5).is_none());
+
+        // older files without the lens load as pinhole cameras
+        let old = Bookmarks::parse("2 0 0 0 0 0 1 0 1 0 1").unwrap();
+        assert_eq!(old.get(2).unwrap().aperture, 0.0);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(Bookmarks::parse("1 0 0 0").is_err());
+        assert!(Bookmarks::parse("12 0 0 0 0 0 1 0 1 0 1").is_err());
+    }
+}
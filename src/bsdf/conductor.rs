@@ -0,0 +1,179 @@
+use crate::geometry::vector::Vector;
+use crate::random::Rng;
+
+use super::{microfacet::Ggx, same_hemisphere, Bsdf, BsdfSample};
+
+/// Rough metal: GGX microfacets reflecting by the Fresnel equations of a
+/// conductor with complex index of refraction `eta + i k` (per RGB channel).
+pub struct Conductor {
+    pub eta: Vector,
+    pub k: Vector,
+    pub distribution: Ggx,
+}
+
+impl Conductor {
+    pub fn new(eta: Vector, k: Vector, roughness: f64) -> Conductor {
+        Conductor {
+            eta,
+            k,
+            distribution: Ggx::new(roughness, roughness),
+        }
+    }
+
+    pub fn gold(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vector::new(0.143, 0.374, 1.442),
+            Vector::new(3.983, 2.385, 1.603),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vector::new(0.200, 0.924, 1.102),
+            Vector::new(3.912, 2.452, 2.142),
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vector::new(1.657, 0.880, 0.521),
+            Vector::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+
+    /// Different roughness along the surface tangent and bitangent, as on brushed metal
+    pub fn with_roughness(mut self, along_tangent: f64, along_bitangent: f64) -> Conductor {
+        self.distribution = Ggx::new(along_tangent, along_bitangent);
+        self
+    }
+
+    fn fresnel(&self, cos_i: f64) -> Vector {
+        Vector::new(
+            fresnel_conductor(cos_i, self.eta.x, self.k.x),
+            fresnel_conductor(cos_i, self.eta.y, self.k.y),
+            fresnel_conductor(cos_i, self.eta.z, self.k.z),
+        )
+    }
+}
+
+/// Unpolarized reflectance of a conductor with index `eta + i k` seen from air
+pub fn fresnel_conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_i.clamp(-1.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let (eta2, k2) = (eta * eta, k * k);
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i.abs() * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    (rs + rp) / 2.0
+}
+
+impl Bsdf for Conductor {
+    fn eval(&self, wo: Vector, wi: Vector, color: Vector) -> Vector {
+        if !same_hemisphere(wo, wi) {
+            return Vector::zero();
+        }
+        let wh = wo + wi;
+        if wh.len_sq() == 0.0 {
+            return Vector::zero();
+        }
+        let wh = wh.normalized();
+        let d = self.distribution.d(wh);
+        let g = self.distribution.g(wo, wi);
+        self.fresnel(wo.dot(&wh)).scale(&color) * (d * g / (4.0 * wo.z * wi.z).abs())
+    }
+
+    fn sample(&self, wo: Vector, color: Vector, rng: &mut Rng) -> Option<BsdfSample> {
+        if wo.z == 0.0 {
+            return None;
+        }
+        let mut wh = self
+            .distribution
+            .sample_wh(wo, rng.next_f64(), rng.next_f64());
+        if wo.z < 0.0 {
+            wh = -wh;
+        }
+        let wi = (-wo).reflect(&wh);
+        if !same_hemisphere(wo, wi) {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, color),
+            pdf: self.pdf(wo, wi),
+        })
+    }
+
+    fn pdf(&self, wo: Vector, wi: Vector) -> f64 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        let wh = (wo + wi).normalized();
+        self.distribution.pdf_wh(wo, wh) / (4.0 * wo.dot(&wh).abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::bsdf::tests::{albedo, check_sampling};
+    use crate::bsdf::Bsdf;
+    use crate::geometry::vector::Vector;
+
+    use super::{fresnel_conductor, Conductor};
+
+    #[test]
+    fn normal_incidence() {
+        // ((n - 1)^2 + k^2) / ((n + 1)^2 + k^2)
+        let (n, k) = (0.143, 3.983);
+        let expected = ((n - 1.0) * (n - 1.0) + k * k) / ((n + 1.0) * (n + 1.0) + k * k);
+        assert_delta!(fresnel_conductor(1.0, n, k), expected, 1e-9);
+        // everything is reflected at grazing angles
+        assert_delta!(fresnel_conductor(1e-6, n, k), 1.0, 1e-4);
+    }
+
+    #[test]
+    fn gold_is_yellow() {
+        let gold = Conductor::gold(0.0);
+        let f = gold.fresnel(1.0);
+        assert!(f.x > f.y && f.y > f.z);
+    }
+
+    #[test]
+    fn sampling() {
+        let wo = Vector::new(0.3, -0.4, 0.866).normalized();
+        for roughness in [0.05, 0.3, 0.8] {
+            let copper = Conductor::copper(roughness);
+            check_sampling(&copper, wo);
+            check_sampling(&copper, -wo);
+
+            // single scattering loses energy to masking on rough metal, never gains any
+            let a = albedo(&copper, wo);
+            let f = copper.fresnel(1.0);
+            assert!(a.x <= f.x * 1.02 && a.x > f.x * 0.5);
+        }
+    }
+
+    #[test]
+    fn brushed() {
+        let brushed = Conductor::aluminium(0.1).with_roughness(0.6, 0.05);
+        check_sampling(&brushed, Vector::new(0.0, 0.6, 0.8));
+
+        // highlights stretch across the brushing direction
+        let wo = Vector::one_z();
+        let along = brushed.eval(wo, Vector::new(0.3, 0.0, 1.0).normalized(), Vector::one());
+        let across = brushed.eval(wo, Vector::new(0.0, 0.3, 1.0).normalized(), Vector::one());
+        assert!(along.x > across.x * 10.0);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::geometry::vector::Vector;
+use crate::random::Rng;
+
+use super::{microfacet::Ggx, same_hemisphere, Bsdf, BsdfSample};
+
+/// Rough glass-like interface (Walter et al. 2007): GGX microfacets that
+/// reflect or refract by the Fresnel equations. `eta` is the index of
+/// refraction of the inside (-Z side) relative to the outside.
+pub struct Dielectric {
+    pub eta: f64,
+    pub distribution: Ggx,
+}
+
+impl Dielectric {
+    pub fn new(eta: f64, roughness: f64) -> Dielectric {
+        Dielectric {
+            eta,
+            distribution: Ggx::new(roughness, roughness),
+        }
+    }
+
+    /// Microfacet normal (facing +Z) that scatters `wo` into `wi`, with the
+    /// relative index of refraction across it when `wi` is transmitted.
+    fn half_vector(&self, wo: Vector, wi: Vector) -> Option<(Vector, f64)> {
+        if wo.z == 0.0 || wi.z == 0.0 {
+            return None;
+        }
+        let etap = if same_hemisphere(wo, wi) {
+            1.0
+        } else if wo.z > 0.0 {
+            self.eta
+        } else {
+            1.0 / self.eta
+        };
+        let wh = wi * etap + wo;
+        if wh.len_sq() == 0.0 {
+            return None;
+        }
+        let wh = wh.normalized();
+        let wh = if wh.z < 0.0 { -wh } else { wh };
+        // microfacets seen from behind can't scatter
+        if wh.dot(&wi) * wi.z < 0.0 || wh.dot(&wo) * wo.z < 0.0 {
+            return None;
+        }
+        Some((wh, etap))
+    }
+}
+
+/// Unpolarized reflectance of an interface with relative index `eta`; a
+/// negative `cos_i` means the light arrives from the inside.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let (cos_i, eta) = if cos_i < 0.0 {
+        (-cos_i, 1.0 / eta)
+    } else {
+        (cos_i, eta)
+    };
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        // total internal reflection
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+/// Refracts `wi` through the interface with normal `n`, `None` on total internal reflection
+fn refract(wi: Vector, n: Vector, eta: f64) -> Option<Vector> {
+    let mut cos_i = n.dot(&wi);
+    let (mut n, mut eta) = (n, eta);
+    if cos_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_i = -cos_i;
+        n = -n;
+    }
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-wi / eta + n * (cos_i / eta - cos_t))
+}
+
+impl Bsdf for Dielectric {
+    fn eval(&self, wo: Vector, wi: Vector, color: Vector) -> Vector {
+        let (wh, etap) = match self.half_vector(wo, wi) {
+            Some(h) => h,
+            None => return Vector::zero(),
+        };
+        let d = self.distribution.d(wh);
+        let g = self.distribution.g(wo, wi);
+        let fresnel = fresnel_dielectric(wo.dot(&wh), self.eta);
+
+        if same_hemisphere(wo, wi) {
+            return color * (d * g * fresnel / (4.0 * wo.z * wi.z).abs());
+        }
+        let denom = (wi.dot(&wh) + wo.dot(&wh) / etap).powi(2) * wo.z * wi.z;
+        // radiance is compressed into the smaller solid angle on the denser side
+        let ft = d * g * (1.0 - fresnel) * (wi.dot(&wh) * wo.dot(&wh) / denom).abs();
+        color * (ft / (etap * etap))
+    }
+
+    fn sample(&self, wo: Vector, color: Vector, rng: &mut Rng) -> Option<BsdfSample> {
+        if wo.z == 0.0 {
+            return None;
+        }
+        let wh = self
+            .distribution
+            .sample_wh(wo, rng.next_f64(), rng.next_f64());
+        let reflectance = fresnel_dielectric(wo.dot(&wh), self.eta);
+
+        let wi = if rng.next_f64() < reflectance {
+            let wi = (-wo).reflect(&wh);
+            if !same_hemisphere(wo, wi) {
+                return None;
+            }
+            wi
+        } else {
+            let wi = refract(wo, wh, self.eta)?;
+            if same_hemisphere(wo, wi) || wi.z == 0.0 {
+                return None;
+            }
+            wi
+        };
+
+        let pdf = self.pdf(wo, wi);
+        if pdf == 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, color),
+            pdf,
+        })
+    }
+
+    fn pdf(&self, wo: Vector, wi: Vector) -> f64 {
+        let (wh, etap) = match self.half_vector(wo, wi) {
+            Some(h) => h,
+            None => return 0.0,
+        };
+        let reflectance = fresnel_dielectric(wo.dot(&wh), self.eta);
+        let pdf_wh = self.distribution.pdf_wh(wo, wh);
+
+        if same_hemisphere(wo, wi) {
+            return pdf_wh / (4.0 * wo.dot(&wh).abs()) * reflectance;
+        }
+        let denom = (wi.dot(&wh) + wo.dot(&wh) / etap).powi(2);
+        pdf_wh * wi.dot(&wh).abs() / denom * (1.0 - reflectance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::bsdf::tests::{albedo, check_sampling};
+    use crate::geometry::vector::Vector;
+
+    use super::{fresnel_dielectric, refract, Dielectric};
+
+    #[test]
+    fn fresnel() {
+        assert_delta!(fresnel_dielectric(1.0, 1.5), 0.04, 1e-9);
+        assert_delta!(fresnel_dielectric(-1.0, 1.5), 0.04, 1e-9);
+        // past the critical angle from inside
+        assert_eq!(fresnel_dielectric(-0.2, 1.5), 1.0);
+    }
+
+    #[test]
+    fn snell() {
+        let wi = Vector::new(0.6, 0.0, 0.8);
+        let wt = refract(wi, Vector::one_z(), 1.5).unwrap();
+        assert_delta!(wt.len(), 1.0, 1e-12);
+        assert_delta!(wt.x, -0.4, 1e-12);
+        assert!(wt.z < 0.0);
+        assert!(refract(Vector::new(0.9, 0.0, -0.436), Vector::one_z(), 1.5).is_none());
+    }
+
+    #[test]
+    fn sampling() {
+        let wo = Vector::new(0.5, 0.1, 0.8).normalized();
+        for roughness in [0.1, 0.5] {
+            let glass = Dielectric::new(1.5, roughness);
+            check_sampling(&glass, wo);
+            check_sampling(&glass, -wo);
+
+            let a = albedo(&glass, wo);
+            assert!(a.x > 0.3 && a.x < 1.0);
+        }
+    }
+}
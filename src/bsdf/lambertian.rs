@@ -0,0 +1,72 @@
+use std::f64::consts::PI;
+
+use crate::geometry::vector::Vector;
+use crate::random::Rng;
+
+use super::{same_hemisphere, Bsdf, BsdfSample};
+
+/// Ideal diffuse reflector, scatters `albedo` times the surface colour evenly in all directions.
+pub struct Lambertian {
+    pub albedo: Vector,
+}
+
+impl Lambertian {
+    pub fn new(albedo: Vector) -> Lambertian {
+        Lambertian { albedo }
+    }
+}
+
+impl Bsdf for Lambertian {
+    fn eval(&self, wo: Vector, wi: Vector, color: Vector) -> Vector {
+        if !same_hemisphere(wo, wi) {
+            return Vector::zero();
+        }
+        self.albedo.scale(&color) / PI
+    }
+
+    fn sample(&self, wo: Vector, color: Vector, rng: &mut Rng) -> Option<BsdfSample> {
+        // cosine weighted: uniform disk lifted onto the hemisphere
+        let r = rng.next_f64().sqrt();
+        let phi = 2.0 * PI * rng.next_f64();
+        let z = (1.0 - r * r).max(0.0).sqrt();
+        let wi = Vector::new(r * phi.cos(), r * phi.sin(), z.copysign(wo.z));
+        if wi.z == 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, color),
+            pdf: self.pdf(wo, wi),
+        })
+    }
+
+    fn pdf(&self, wo: Vector, wi: Vector) -> f64 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        wi.z.abs() / PI
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::bsdf::tests::{albedo, check_sampling};
+    use crate::bsdf::Bsdf;
+    use crate::geometry::vector::Vector;
+
+    use super::Lambertian;
+
+    #[test]
+    fn sampling() {
+        let bsdf = Lambertian::new(Vector::new(0.2, 0.5, 0.8));
+        let wo = Vector::new(0.0, 0.6, 0.8);
+        check_sampling(&bsdf, wo);
+        check_sampling(&bsdf, -wo);
+
+        let a = albedo(&bsdf, wo);
+        assert_delta!(a.x, 0.2, 1e-9);
+        assert_delta!(a.z, 0.8, 1e-9);
+        assert_eq!(bsdf.eval(wo, -wo, Vector::one()), Vector::zero());
+    }
+}
@@ -0,0 +1,133 @@
+use std::f64::consts::PI;
+
+use crate::geometry::vector::Vector;
+
+/// Lowest `alpha`, smoother surfaces are numerically indistinguishable from mirrors
+const MIN_ALPHA: f64 = 1e-4;
+
+/// GGX / Trowbridge-Reitz distribution of microfacet normals, optionally
+/// anisotropic with separate widths along the tangent (`alpha_x`) and bitangent.
+#[derive(Clone, Copy, Debug)]
+pub struct Ggx {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl Ggx {
+    /// Perceptually linear roughness in 0..1 along the tangent and bitangent
+    pub fn new(roughness_x: f64, roughness_y: f64) -> Ggx {
+        Ggx {
+            alpha_x: (roughness_x * roughness_x).max(MIN_ALPHA),
+            alpha_y: (roughness_y * roughness_y).max(MIN_ALPHA),
+        }
+    }
+
+    /// Density of microfacets facing `wh`
+    pub fn d(&self, wh: Vector) -> f64 {
+        let cos2 = wh.z * wh.z;
+        if cos2 == 0.0 {
+            return 0.0;
+        }
+        let e = (wh.x * wh.x / (self.alpha_x * self.alpha_x)
+            + wh.y * wh.y / (self.alpha_y * self.alpha_y))
+            / cos2;
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos2 * cos2 * (1.0 + e) * (1.0 + e))
+    }
+
+    fn lambda(&self, w: Vector) -> f64 {
+        let cos2 = w.z * w.z;
+        if cos2 == 0.0 {
+            return f64::INFINITY;
+        }
+        let alpha2_tan2 = (w.x * w.x * self.alpha_x * self.alpha_x
+            + w.y * w.y * self.alpha_y * self.alpha_y)
+            / cos2;
+        ((1.0 + alpha2_tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// Share of microfacets visible from `w`
+    pub fn g1(&self, w: Vector) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Share of microfacets visible from both `wo` and `wi`
+    pub fn g(&self, wo: Vector, wi: Vector) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of the normals `sample_wh` returns: visible from `wo`, weighted by their projected area
+    pub fn pdf_wh(&self, wo: Vector, wh: Vector) -> f64 {
+        if wo.z == 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * wo.dot(&wh).abs() * self.d(wh) / wo.z.abs()
+    }
+
+    /// Samples a microfacet normal visible from `wo` (Heitz 2018), always facing +Z.
+    pub fn sample_wh(&self, wo: Vector, u1: f64, u2: f64) -> Vector {
+        let wo = if wo.z < 0.0 { -wo } else { wo };
+        // stretch to the unit roughness configuration
+        let vh = Vector::new(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z).normalized();
+
+        let lensq = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if lensq > 0.0 {
+            Vector::new(-vh.y, vh.x, 0.0) / lensq.sqrt()
+        } else {
+            Vector::one_x()
+        };
+        let t2 = vh.cross(&t1);
+
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        Vector::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-9)).normalized()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::assert_delta;
+    use crate::geometry::vector::Vector;
+    use crate::random::Rng;
+
+    use super::Ggx;
+
+    #[test]
+    fn normalized() {
+        // projected microfacet area integrates to one over the hemisphere
+        let ggx = Ggx::new(0.5, 0.7);
+        let mut rng = Rng::new(3);
+        let count = 200000;
+        let mut sum = 0.0;
+        for _ in 0..count {
+            // uniform hemisphere, pdf 1 / 2pi
+            let z = rng.next_f64();
+            let phi = 2.0 * PI * rng.next_f64();
+            let r = (1.0 - z * z).sqrt();
+            let wh = Vector::new(r * phi.cos(), r * phi.sin(), z);
+            sum += ggx.d(wh) * wh.z * 2.0 * PI;
+        }
+        assert_delta!(sum / count as f64, 1.0, 0.02);
+    }
+
+    #[test]
+    fn visible_normals() {
+        let ggx = Ggx::new(0.4, 0.4);
+        let wo = Vector::new(0.6, 0.0, 0.8);
+        let mut rng = Rng::new(9);
+        for _ in 0..100 {
+            let wh = ggx.sample_wh(wo, rng.next_f64(), rng.next_f64());
+            assert_delta!(wh.len(), 1.0, 1e-9);
+            assert!(wh.z > 0.0);
+            assert!(wh.dot(&wo) > -1e-9);
+        }
+        assert!(ggx.g1(wo) < 1.0);
+        assert_eq!(ggx.g1(Vector::one_z()), 1.0);
+    }
+}
@@ -0,0 +1,134 @@
+pub mod conductor;
+pub mod dielectric;
+pub mod lambertian;
+pub mod microfacet;
+pub mod principled;
+
+use crate::geometry::{shape::tangent_frame, vector::Vector};
+use crate::random::Rng;
+
+/// Direction picked by `Bsdf::sample` with the BSDF value and the probability
+/// density of picking it (per unit solid angle).
+#[derive(Clone, Copy, Debug)]
+pub struct BsdfSample {
+    pub wi: Vector,
+    pub f: Vector,
+    pub pdf: f64,
+}
+
+impl BsdfSample {
+    /// Throughput of the sample, `f * |cos| / pdf`
+    pub fn weight(&self) -> Vector {
+        self.f * (self.wi.z.abs() / self.pdf)
+    }
+}
+
+/// Surface scattering model. Directions are in the local shading frame (see
+/// `Frame`) with the normal along +Z, both `wo` and `wi` point away from the surface.
+/// `color` is the surface colour at the hit (`Material::color_at`): simple models
+/// are tinted by it, the principled one takes it as its base colour.
+pub trait Bsdf: Send + Sync {
+    /// Fraction of the light arriving from `wi` that scatters towards `wo`
+    fn eval(&self, wo: Vector, wi: Vector, color: Vector) -> Vector;
+    /// Picks an incoming direction for `wo`, roughly in proportion to `eval` times the cosine
+    fn sample(&self, wo: Vector, color: Vector, rng: &mut Rng) -> Option<BsdfSample>;
+    /// Density `sample` picks `wi` with
+    fn pdf(&self, wo: Vector, wi: Vector) -> f64;
+}
+
+/// Orthonormal shading frame the BSDFs work in.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame {
+    pub tangent: Vector,
+    pub bitangent: Vector,
+    pub normal: Vector,
+}
+
+impl Frame {
+    /// `tangent` is made perpendicular to `normal`, anisotropic roughness runs along it.
+    pub fn new(normal: Vector, tangent: Vector) -> Frame {
+        let tangent = tangent - normal * normal.dot(&tangent);
+        if tangent.len_sq() < 1e-12 {
+            let (tangent, bitangent) = tangent_frame(normal);
+            return Frame {
+                tangent,
+                bitangent,
+                normal,
+            };
+        }
+        let tangent = tangent.normalized();
+        Frame {
+            tangent,
+            bitangent: normal.cross(&tangent),
+            normal,
+        }
+    }
+
+    #[inline]
+    pub fn to_local(self, v: Vector) -> Vector {
+        Vector::new(
+            v.dot(&self.tangent),
+            v.dot(&self.bitangent),
+            v.dot(&self.normal),
+        )
+    }
+
+    #[inline]
+    pub fn to_world(self, v: Vector) -> Vector {
+        self.tangent * v.x + self.bitangent * v.y + self.normal * v.z
+    }
+}
+
+#[inline]
+fn same_hemisphere(a: Vector, b: Vector) -> bool {
+    a.z * b.z > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::geometry::vector::Vector;
+    use crate::random::Rng;
+
+    use super::{Bsdf, Frame};
+
+    #[test]
+    fn frame_round_trip() {
+        let frame = Frame::new(Vector::one_y(), Vector::new(1.0, 0.5, 0.0));
+        assert_eq!(frame.tangent, Vector::one_x());
+        assert_eq!(frame.bitangent, Vector::new(0.0, 0.0, -1.0));
+
+        let v = Vector::new(0.3, -0.2, 0.9);
+        let back = frame.to_world(frame.to_local(v));
+        assert_delta!(back.x, v.x, 1e-12);
+        assert_delta!(back.y, v.y, 1e-12);
+        assert_delta!(back.z, v.z, 1e-12);
+    }
+
+    /// Samples must be drawn with the density `pdf` reports and weighted by `eval`.
+    pub fn check_sampling(bsdf: &dyn Bsdf, wo: Vector) {
+        let mut rng = Rng::new(11);
+        let color = Vector::new(0.9, 0.6, 0.3);
+        for _ in 0..200 {
+            if let Some(sample) = bsdf.sample(wo, color, &mut rng) {
+                let pdf = bsdf.pdf(wo, sample.wi);
+                assert!((pdf - sample.pdf).abs() <= 1e-6 * pdf.max(1.0));
+                let f = bsdf.eval(wo, sample.wi, color);
+                assert!((f - sample.f).len() <= 1e-6 * f.len().max(1.0));
+            }
+        }
+    }
+
+    /// Monte Carlo estimate of the reflected and transmitted energy for `wo`
+    pub fn albedo(bsdf: &dyn Bsdf, wo: Vector) -> Vector {
+        let mut rng = Rng::new(5);
+        let count = 20000;
+        let mut sum = Vector::zero();
+        for _ in 0..count {
+            if let Some(sample) = bsdf.sample(wo, Vector::one(), &mut rng) {
+                sum += sample.weight();
+            }
+        }
+        sum / count as f64
+    }
+}
@@ -0,0 +1,335 @@
+use std::f64::consts::PI;
+
+use crate::geometry::{animation::Lerp, vector::Vector};
+use crate::random::Rng;
+
+use super::{dielectric::Dielectric, microfacet::Ggx, same_hemisphere, Bsdf, BsdfSample};
+
+/// Roughness of the clear coat at zero and full `clearcoat_gloss`
+const CLEARCOAT_ROUGH: f64 = 0.1;
+const CLEARCOAT_GLOSSY: f64 = 0.001;
+
+/// Disney "principled" BSDF (Burley 2012, transmission as in 2015): one
+/// material blending diffuse, sheen, specular, clear coat and glass lobes,
+/// driven by the parameters DCC tools export. All parameters are in 0..1.
+/// `base_color` is multiplied by the surface colour at the hit, so colour
+/// textures on the `Material` feed the base colour.
+#[derive(Clone, Copy, Debug)]
+pub struct Principled {
+    pub base_color: Vector,
+    pub metallic: f64,
+    pub roughness: f64,
+    /// Dielectric reflectance, 0.5 is 4% at normal incidence (IOR 1.5)
+    pub specular: f64,
+    /// Tints dielectric reflections towards the base colour
+    pub specular_tint: f64,
+    /// Soft rim reflection for cloth
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    /// Second, always white specular layer on top
+    pub clearcoat: f64,
+    pub clearcoat_gloss: f64,
+    /// Share of the dielectric base that is transparent glass
+    pub transmission: f64,
+    /// Stretches highlights along the surface tangent
+    pub anisotropic: f64,
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Principled {
+            base_color: Vector::one(),
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            anisotropic: 0.0,
+        }
+    }
+}
+
+/// How much of each lobe the material has
+struct Weights {
+    diffuse: f64,
+    specular: f64,
+    clearcoat: f64,
+    glass: f64,
+}
+
+impl Weights {
+    /// Chances of sampling each lobe, in the same order as the fields
+    fn probabilities(&self) -> [f64; 4] {
+        let w = [self.diffuse, self.specular, self.clearcoat, self.glass];
+        let total: f64 = w.iter().sum();
+        w.map(|w| w / total)
+    }
+}
+
+#[inline]
+fn schlick_weight(cos: f64) -> f64 {
+    (1.0 - cos.abs().min(1.0)).powi(5)
+}
+
+impl Principled {
+    fn weights(&self) -> Weights {
+        let dielectric = 1.0 - self.metallic;
+        Weights {
+            diffuse: dielectric * (1.0 - self.transmission),
+            specular: 1.0 - dielectric * self.transmission,
+            clearcoat: 0.25 * self.clearcoat,
+            glass: dielectric * self.transmission,
+        }
+    }
+
+    /// Relative index of refraction implied by `specular`
+    fn eta(&self) -> f64 {
+        let r0 = (0.08 * self.specular).clamp(0.0, 0.99).sqrt();
+        ((1.0 + r0) / (1.0 - r0)).max(1.0 + 1e-4)
+    }
+
+    fn specular_distribution(&self) -> Ggx {
+        let aspect = (1.0 - 0.9 * self.anisotropic).sqrt();
+        let alpha = self.roughness * self.roughness;
+        Ggx {
+            alpha_x: (alpha / aspect).max(1e-4),
+            alpha_y: (alpha * aspect).max(1e-4),
+        }
+    }
+
+    fn clearcoat_alpha(&self) -> f64 {
+        CLEARCOAT_ROUGH.lerp(&CLEARCOAT_GLOSSY, self.clearcoat_gloss)
+    }
+
+    fn glass(&self) -> Dielectric {
+        Dielectric::new(self.eta(), self.roughness)
+    }
+
+    /// Generalized Trowbridge-Reitz with exponent 1, the long tailed clear coat distribution
+    fn gtr1(cos_h: f64, alpha: f64) -> f64 {
+        let a2 = alpha * alpha;
+        (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_h * cos_h))
+    }
+
+    fn clearcoat_pdf(&self, wo: Vector, wi: Vector) -> f64 {
+        let wh = (wo + wi).normalized();
+        Self::gtr1(wh.z, self.clearcoat_alpha()) * wh.z.abs() / (4.0 * wo.dot(&wh).abs())
+    }
+
+    fn specular_pdf(&self, wo: Vector, wi: Vector) -> f64 {
+        let wh = (wo + wi).normalized();
+        self.specular_distribution().pdf_wh(wo, wh) / (4.0 * wo.dot(&wh).abs())
+    }
+
+    /// All the reflection lobes, for `wo` and `wi` on the same side
+    fn eval_reflection(&self, wo: Vector, wi: Vector, base: Vector, weights: &Weights) -> Vector {
+        let wh = wo + wi;
+        if wh.len_sq() == 0.0 {
+            return Vector::zero();
+        }
+        let wh = wh.normalized();
+        let (cos_o, cos_i) = (wo.z.abs(), wi.z.abs());
+        let cos_d = wi.dot(&wh);
+        let (fo, fi, fd) = (
+            schlick_weight(cos_o),
+            schlick_weight(cos_i),
+            schlick_weight(cos_d),
+        );
+
+        let lum = base.luminance();
+        let tint = if lum > 0.0 { base / lum } else { Vector::one() };
+
+        let mut f = Vector::zero();
+        if weights.diffuse > 0.0 {
+            // Lambert softened at grazing angles, plus retro-reflection on rough surfaces
+            let rr = 2.0 * self.roughness * cos_d * cos_d;
+            let lambert = (1.0 - fo / 2.0) * (1.0 - fi / 2.0);
+            let retro = rr * (fo + fi + fo * fi * (rr - 1.0));
+            let sheen = Vector::one().lerp(&tint, self.sheen_tint) * (self.sheen * fd);
+            f += (base * ((lambert + retro) / PI) + sheen) * weights.diffuse;
+        }
+
+        if weights.specular > 0.0 {
+            let spec0 = (Vector::one().lerp(&tint, self.specular_tint) * (0.08 * self.specular))
+                .lerp(&base, self.metallic);
+            let fresnel = spec0 + (Vector::one() - spec0) * schlick_weight(wo.dot(&wh));
+            let ggx = self.specular_distribution();
+            let spec = ggx.d(wh) * ggx.g(wo, wi) / (4.0 * cos_o * cos_i);
+            f += fresnel * (spec * weights.specular);
+        }
+
+        if weights.clearcoat > 0.0 {
+            let d = Self::gtr1(wh.z, self.clearcoat_alpha());
+            let g = Ggx {
+                alpha_x: 0.25,
+                alpha_y: 0.25,
+            }
+            .g(wo, wi);
+            let fresnel = 0.04 + 0.96 * schlick_weight(wo.dot(&wh));
+            f += Vector::one() * (weights.clearcoat * d * g * fresnel / (4.0 * cos_o * cos_i));
+        }
+
+        f
+    }
+}
+
+impl Bsdf for Principled {
+    fn eval(&self, wo: Vector, wi: Vector, color: Vector) -> Vector {
+        if wo.z == 0.0 || wi.z == 0.0 {
+            return Vector::zero();
+        }
+        let base = self.base_color.scale(&color);
+        let weights = self.weights();
+
+        let mut f = Vector::zero();
+        if same_hemisphere(wo, wi) {
+            f += self.eval_reflection(wo, wi, base, &weights);
+        }
+        if weights.glass > 0.0 {
+            // light passing through is tinted by the colour once on the way in and once out
+            let tint = if same_hemisphere(wo, wi) {
+                Vector::one()
+            } else {
+                Vector::new(base.x.sqrt(), base.y.sqrt(), base.z.sqrt())
+            };
+            f += self.glass().eval(wo, wi, tint) * weights.glass;
+        }
+        f
+    }
+
+    fn sample(&self, wo: Vector, color: Vector, rng: &mut Rng) -> Option<BsdfSample> {
+        if wo.z == 0.0 {
+            return None;
+        }
+        let [p_diffuse, p_specular, p_clearcoat, _] = self.weights().probabilities();
+        let pick = rng.next_f64();
+
+        let wi = if pick < p_diffuse {
+            let r = rng.next_f64().sqrt();
+            let phi = 2.0 * PI * rng.next_f64();
+            let z = (1.0 - r * r).max(0.0).sqrt();
+            Vector::new(r * phi.cos(), r * phi.sin(), z.copysign(wo.z))
+        } else if pick < p_diffuse + p_specular {
+            let wh = self
+                .specular_distribution()
+                .sample_wh(wo, rng.next_f64(), rng.next_f64());
+            let wh = if wo.z < 0.0 { -wh } else { wh };
+            (-wo).reflect(&wh)
+        } else if pick < p_diffuse + p_specular + p_clearcoat {
+            let alpha = self.clearcoat_alpha();
+            let a2 = alpha * alpha;
+            let cos = ((1.0 - a2.powf(1.0 - rng.next_f64())) / (1.0 - a2))
+                .clamp(0.0, 1.0)
+                .sqrt();
+            let sin = (1.0 - cos * cos).sqrt();
+            let phi = 2.0 * PI * rng.next_f64();
+            let wh = Vector::new(sin * phi.cos(), sin * phi.sin(), cos.copysign(wo.z));
+            (-wo).reflect(&wh)
+        } else {
+            self.glass().sample(wo, Vector::one(), rng)?.wi
+        };
+
+        let pdf = self.pdf(wo, wi);
+        if pdf == 0.0 || wi.z == 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, color),
+            pdf,
+        })
+    }
+
+    fn pdf(&self, wo: Vector, wi: Vector) -> f64 {
+        if wo.z == 0.0 || wi.z == 0.0 {
+            return 0.0;
+        }
+        let [p_diffuse, p_specular, p_clearcoat, p_glass] = self.weights().probabilities();
+
+        let mut pdf = 0.0;
+        if same_hemisphere(wo, wi) {
+            pdf += p_diffuse * wi.z.abs() / PI;
+            pdf += p_specular * self.specular_pdf(wo, wi);
+            pdf += p_clearcoat * self.clearcoat_pdf(wo, wi);
+        }
+        if p_glass > 0.0 {
+            pdf += p_glass * self.glass().pdf(wo, wi);
+        }
+        pdf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bsdf::tests::{albedo, check_sampling};
+    use crate::bsdf::Bsdf;
+    use crate::geometry::vector::Vector;
+
+    use super::Principled;
+
+    fn materials() -> Vec<Principled> {
+        vec![
+            Principled::default(),
+            Principled {
+                metallic: 1.0,
+                roughness: 0.3,
+                anisotropic: 0.8,
+                ..Default::default()
+            },
+            Principled {
+                sheen: 1.0,
+                clearcoat: 1.0,
+                clearcoat_gloss: 0.7,
+                specular_tint: 0.5,
+                ..Default::default()
+            },
+            Principled {
+                transmission: 1.0,
+                roughness: 0.2,
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn sampling() {
+        let wo = Vector::new(0.4, 0.2, 0.8).normalized();
+        for material in materials() {
+            check_sampling(&material, wo);
+            check_sampling(&material, -wo);
+
+            // the retro-reflective diffuse is allowed to slightly exceed one
+            let a = albedo(&material, wo);
+            assert!(a.x > 0.3 && a.x < 1.1, "{:?}", a);
+        }
+    }
+
+    #[test]
+    fn base_color() {
+        let wo = Vector::new(0.0, 0.6, 0.8);
+        let wi = Vector::new(0.0, -0.6, 0.8);
+        let red = Vector::new(1.0, 0.0, 0.0);
+
+        // diffuse takes the colour, the dielectric highlight stays white
+        let plastic = Principled {
+            roughness: 0.05,
+            ..Default::default()
+        };
+        let f = plastic.eval(wo, wi, red);
+        assert!(f.y > 0.0 && f.x > f.y);
+        let off_specular = plastic.eval(wo, Vector::new(0.6, 0.0, 0.8), red);
+        assert!(off_specular.y < f.y * 1e-3);
+
+        // metal reflects in its base colour, whitening only towards grazing angles
+        let metal = Principled {
+            metallic: 1.0,
+            ..Default::default()
+        };
+        let f = metal.eval(wo, wi, red);
+        assert!(f.x > 0.0 && f.y < f.x * 1e-3);
+    }
+}
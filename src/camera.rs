@@ -1,5 +1,8 @@
-use crate::geometry::vector::Vector;
+use std::f64::consts::PI;
 
+use crate::geometry::{quaternion::Quaternion, vector::Vector};
+
+#[derive(Clone, Debug)]
 pub struct Camera {
     pub pos: Vector,
     pub forward: Vector,
@@ -8,6 +11,14 @@ pub struct Camera {
     pub vfov: f64,
     pub ar: f64,
     pub vfov2_tg: f64,
+    /// Time the shutter stays open, starting at the frame time
+    pub shutter: f64,
+    /// Position and orientation reached when the shutter closes, for camera motion blur
+    pub end_pose: Option<(Vector, Quaternion)>,
+    /// Radius of the lens, zero for a pinhole with everything in focus
+    pub aperture: f64,
+    /// Distance along `forward` to the plane in focus
+    pub focus_distance: f64,
 }
 
 impl Camera {
@@ -24,9 +35,54 @@ impl Camera {
             vfov,
             ar,
             vfov2_tg: (vfov / 2.0).tan(),
+            shutter: 0.0,
+            end_pose: None,
+            aperture: 0.0,
+            focus_distance: 1.0,
         }
     }
 
+    pub fn from_orientation(pos: Vector, orientation: Quaternion, vfov: f64, ar: f64) -> Camera {
+        Camera::new(
+            pos,
+            orientation.rotate(&Vector::one_z()),
+            orientation.rotate(&Vector::one_y()),
+            vfov,
+            ar,
+        )
+    }
+
+    pub fn orientation(&self) -> Quaternion {
+        Quaternion::from_basis(&self.right, &self.up, &self.forward)
+    }
+
+    /// Camera as seen at `fraction` of the shutter interval (0 - open, 1 - closed).
+    pub fn at_shutter(&self, fraction: f64) -> Camera {
+        match self.end_pose {
+            None => self.clone(),
+            Some((end_pos, end_orientation)) => Camera {
+                aperture: self.aperture,
+                focus_distance: self.focus_distance,
+                ..Camera::from_orientation(
+                    self.pos + (end_pos - self.pos) * fraction,
+                    self.orientation().slerp(&end_orientation, fraction),
+                    self.vfov,
+                    self.ar,
+                )
+            },
+        }
+    }
+
+    /// Ray from the point `u`, `v` (both in 0..1) of the lens through the point
+    /// in focus that `direction` leads to from the lens centre
+    pub fn lens_ray(&self, direction: Vector, u: f64, v: f64) -> (Vector, Vector) {
+        let r = self.aperture * u.sqrt();
+        let phi = 2.0 * PI * v;
+        let origin = self.pos + self.right * (r * phi.cos()) + self.up * (r * phi.sin());
+        let focus = self.pos + direction * (self.focus_distance / direction.dot(&self.forward));
+        (origin, (focus - origin).normalized())
+    }
+
     pub fn shift_vertical(&mut self, up: f64) {
         self.pos += self.up * up;
     }
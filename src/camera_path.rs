@@ -0,0 +1,333 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::camera::Camera;
+use crate::geometry::{quaternion::Quaternion, vector::Vector};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraKeyframe {
+    pub time: f64,
+    pub pos: Vector,
+    pub orientation: Quaternion,
+    pub vfov: f64,
+}
+
+impl CameraKeyframe {
+    pub fn from_camera(time: f64, camera: &Camera) -> CameraKeyframe {
+        CameraKeyframe {
+            time,
+            pos: camera.pos,
+            orientation: camera.orientation(),
+            vfov: camera.vfov,
+        }
+    }
+}
+
+/// Camera fly-through defined by keyframes sorted by time. Positions follow a
+/// Catmull-Rom spline through the keyframes, orientation is slerped and field
+/// of view is interpolated linearly.
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+}
+
+impl Default for CameraPath {
+    fn default() -> CameraPath {
+        CameraPath::new()
+    }
+}
+
+impl CameraPath {
+    pub fn new() -> CameraPath {
+        CameraPath {
+            keyframes: Vec::new(),
+        }
+    }
+
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        &self.keyframes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn add_keyframe(&mut self, keyframe: CameraKeyframe) {
+        let idx = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(idx, keyframe);
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+    }
+
+    pub fn duration(&self) -> f64 {
+        match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+
+    pub fn end_time(&self) -> f64 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    pub fn sample(&self, time: f64, ar: f64) -> Option<Camera> {
+        let keys = &self.keyframes;
+        let first = keys.first()?;
+        let last = keys.last()?;
+
+        if keys.len() == 1 || time <= first.time {
+            return Some(Camera::from_orientation(
+                first.pos,
+                first.orientation,
+                first.vfov,
+                ar,
+            ));
+        }
+        if time >= last.time {
+            return Some(Camera::from_orientation(
+                last.pos,
+                last.orientation,
+                last.vfov,
+                ar,
+            ));
+        }
+
+        let i = keys.partition_point(|k| k.time <= time) - 1;
+        let (k1, k2) = (&keys[i], &keys[i + 1]);
+        let k0 = if i > 0 { &keys[i - 1] } else { k1 };
+        let k3 = if i + 2 < keys.len() { &keys[i + 2] } else { k2 };
+
+        let span = k2.time - k1.time;
+        let u = if span > 0.0 {
+            (time - k1.time) / span
+        } else {
+            0.0
+        };
+
+        let pos = catmull_rom(k0.pos, k1.pos, k2.pos, k3.pos, u);
+        let orientation = k1.orientation.slerp(&k2.orientation, u);
+        let vfov = k1.vfov + (k2.vfov - k1.vfov) * u;
+
+        Some(Camera::from_orientation(pos, orientation, vfov, ar))
+    }
+
+    pub fn load(path: &Path) -> io::Result<CameraPath> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+
+    /// One line per keyframe: `time px py pz fx fy fz ux uy uz vfov`
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        for k in &self.keyframes {
+            let forward = k.orientation.rotate(&Vector::one_z());
+            let up = k.orientation.rotate(&Vector::one_y());
+            out += &format!(
+                "{} {} {} {} {} {} {} {} {} {} {}\n",
+                k.time,
+                k.pos.x,
+                k.pos.y,
+                k.pos.z,
+                forward.x,
+                forward.y,
+                forward.z,
+                up.x,
+                up.y,
+                up.z,
+                k.vfov
+            );
+        }
+        out
+    }
+
+    pub fn parse(text: &str) -> io::Result<CameraPath> {
+        let mut path = CameraPath::new();
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid camera keyframe on line {}", line_no + 1),
+                )
+            };
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 11 {
+                return Err(invalid());
+            }
+            let mut v = [0.0; 11];
+            for (i, field) in fields.iter().enumerate() {
+                v[i] = field.parse().map_err(|_| invalid())?;
+            }
+
+            let camera = Camera::new(
+                Vector::new(v[1], v[2], v[3]),
+                Vector::new(v[4], v[5], v[6]),
+                Vector::new(v[7], v[8], v[9]),
+                v[10],
+                1.0,
+            );
+            path.add_keyframe(CameraKeyframe::from_camera(v[0], &camera));
+        }
+
+        Ok(path)
+    }
+}
+
+/// Real-time playback of a camera path in the interactive window.
+pub struct PathPreview {
+    started: Option<Instant>,
+}
+
+impl Default for PathPreview {
+    fn default() -> PathPreview {
+        PathPreview::new()
+    }
+}
+
+impl PathPreview {
+    pub fn new() -> PathPreview {
+        PathPreview { started: None }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn toggle(&mut self, path: &CameraPath) {
+        self.started = match self.started {
+            None if !path.is_empty() => Some(Instant::now()),
+            _ => None,
+        };
+    }
+
+    /// Moves the camera along the path; playback stops after the last keyframe.
+    pub fn update(&mut self, path: &CameraPath, camera: &mut Camera) {
+        let started = match self.started {
+            Some(started) => started,
+            None => return,
+        };
+
+        let elapsed = started.elapsed().as_secs_f64();
+        if elapsed > path.duration() {
+            self.started = None;
+        }
+
+        let start = path.keyframes().first().map_or(0.0, |k| k.time);
+        if let Some(sampled) = path.sample(start + elapsed, camera.ar) {
+            *camera = sampled;
+        }
+    }
+}
+
+fn catmull_rom(p0: Vector, p1: Vector, p2: Vector, p3: Vector, u: f64) -> Vector {
+    let u2 = u * u;
+    let u3 = u2 * u;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * u
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * u2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * u3)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::camera::Camera;
+    use crate::geometry::vector::Vector;
+
+    use super::{CameraKeyframe, CameraPath};
+
+    fn keyframe(time: f64, pos: Vector, dir: Vector, vfov: f64) -> CameraKeyframe {
+        let camera = Camera::new(pos, dir, Vector::one_y(), vfov, 1.0);
+        CameraKeyframe::from_camera(time, &camera)
+    }
+
+    fn path() -> CameraPath {
+        let mut path = CameraPath::new();
+        path.add_keyframe(keyframe(
+            2.0,
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::one_x(),
+            1.0,
+        ));
+        path.add_keyframe(keyframe(0.0, Vector::zero(), Vector::one_z(), 0.5));
+        path.add_keyframe(keyframe(
+            1.0,
+            Vector::new(1.0, 1.0, 0.0),
+            Vector::one_z(),
+            0.5,
+        ));
+        path
+    }
+
+    #[test]
+    fn keyframes_sorted() {
+        let path = path();
+        let times: Vec<f64> = path.keyframes().iter().map(|k| k.time).collect();
+        assert_eq!(times, vec![0.0, 1.0, 2.0]);
+        assert_eq!(path.duration(), 2.0);
+    }
+
+    #[test]
+    fn sample_passes_through_keyframes() {
+        let path = path();
+        for k in path.keyframes() {
+            let camera = path.sample(k.time, 1.0).unwrap();
+            assert_delta!(camera.pos.x, k.pos.x, 1e-12);
+            assert_delta!(camera.pos.y, k.pos.y, 1e-12);
+            assert_delta!(camera.pos.z, k.pos.z, 1e-12);
+            assert_delta!(camera.vfov, k.vfov, 1e-12);
+        }
+    }
+
+    #[test]
+    fn sample_interpolates() {
+        let path = path();
+        let camera = path.sample(1.5, 2.0).unwrap();
+
+        assert_delta!(camera.vfov, 0.75, 1e-12);
+        assert_eq!(camera.ar, 2.0);
+        // halfway between +Z and +X when turning around +Y
+        let expected = (Vector::one_x() + Vector::one_z()).normalized();
+        assert_delta!(camera.forward.x, expected.x, 1e-12);
+        assert_delta!(camera.forward.z, expected.z, 1e-12);
+        // Catmull-Rom keeps a smooth curve, it does not cut the corner linearly
+        assert!(camera.pos.y > 0.5);
+    }
+
+    #[test]
+    fn sample_clamps() {
+        let path = path();
+        assert_eq!(path.sample(-1.0, 1.0).unwrap().pos, Vector::zero());
+        assert_eq!(
+            path.sample(5.0, 1.0).unwrap().pos,
+            Vector::new(2.0, 0.0, 0.0)
+        );
+        assert!(CameraPath::new().sample(0.0, 1.0).is_none());
+    }
+
+    #[test]
+    fn serialize_parse() {
+        let path = path();
+        let parsed = CameraPath::parse(&path.serialize()).unwrap();
+
+        assert_eq!(parsed.keyframes().len(), 3);
+        for (a, b) in path.keyframes().iter().zip(parsed.keyframes()) {
+            assert_eq!(a.time, b.time);
+            assert_eq!(a.pos, b.pos);
+            assert_delta!(a.orientation.dot(&b.orientation).abs(), 1.0, 1e-12);
+        }
+        assert!(CameraPath::parse("0 1 2").is_err());
+    }
+}
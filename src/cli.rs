@@ -0,0 +1,585 @@
+use std::path::{Path, PathBuf};
+
+use ray_tracer::headless::{ExrOptions, FrameFormat, Layer};
+use ray_tracer::image::{
+    exr::{Compression, PixelType},
+    y4m::{Matrix, Range, Y4mOptions},
+};
+use ray_tracer::recording::SequenceFormat;
+use ray_tracer::tracer::{Integrator, Tracer, MAX_DEPTH};
+
+pub const USAGE: &str = "\
+Usage:
+  ray_tracer [view] [SCENE] [OPTIONS]
+  ray_tracer render [SCENE] --output FILE [OPTIONS]
+  ray_tracer render-path CAMERA_PATH OUTPUT [SCENE] [OPTIONS]
+  ray_tracer render-shot SETTINGS OUTPUT [--threads N]
+  ray_tracer help
+
+Commands:
+  view          Explore the scene in a window (the default)
+  render        Render a single frame to a PNG, PPM or EXR file
+  render-path   Render the frames of a camera path to images or video
+  render-shot   Render a screenshot again from the settings saved next to it
+
+SCENE is a scene file; without one the built-in demo scene is shown.
+
+Options:
+  -o, --output FILE     Image to write, the format follows the extension
+  --size WxH            Resolution traced [default: 640x480]
+  --scale N             Window size as a multiple of the resolution [default: 3]
+  --fov DEGREES         Vertical field of view [default: 60, or the scene camera]
+  --samples N           Samples per pixel, the fewest with --noise [default: 1]
+  --noise X             Add samples to each pixel until its noise is below this share of its
+                        brightness, or it reaches --max-samples [default: off]
+  --max-samples N       Most samples --noise spends on a pixel [default: 256]
+  --depth N             Reflections and bounces followed after the first hit, at most 256
+                        [default: 5]
+  --cutoff X            Stop paths once less than this share of their light shows [default: 0.02]
+  --threads N           Rows traced at once [default: one per core]
+  --seed N              Seed of the sample pattern [default: 0]
+  --integrator NAME     path, direct or ao (ambient occlusion) [default: path]
+  --time SECONDS        Animation time of a single frame [default: 0]
+  --fps N               Frame rate of a camera path render [default: 30]
+  -h, --help            Show this help
+
+Camera path output (render-path):
+  --ppm, --png          Numbered images in the OUTPUT directory [default: --ppm]
+  --y4m                 YUV4MPEG2 video written to the OUTPUT file, BT.709 limited range
+  --bt601, --full-range Change the video colours, implying --y4m
+
+EXR output (render with a .exr file, render-path with --exr):
+  --exr                 Linear OpenEXR frames, 32-bit floats with ZIP compression
+  --half                16-bit floats
+  --uncompressed        No compression
+  --aovs=LIST           Extra layers: depth, normal, albedo, id, direct, indirect or all
+
+With more than one sample per pixel camera path frames get motion blur.";
+
+/// Default resolution traced
+const SIZE: (u32, u32) = (640, 480);
+/// Default window size as a multiple of the resolution
+const WINDOW_SCALE: u32 = 3;
+const VFOV_DEGREES: f64 = 60.0;
+const PATH_FPS: f64 = 30.0;
+
+/// How frames are traced, shared by the commands that render
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderSettings {
+    /// Scene file, the demo scene when absent
+    pub scene: Option<PathBuf>,
+    pub size: (u32, u32),
+    /// Vertical field of view in radians, when it overrides the default or the scene camera
+    pub vfov: Option<f64>,
+    pub samples_per_pixel: u32,
+    /// Noise threshold of adaptive sampling, when it is on
+    pub noise_threshold: Option<f64>,
+    pub max_samples_per_pixel: Option<u32>,
+    pub max_depth: Option<u32>,
+    pub min_throughput: Option<f64>,
+    pub threads: Option<usize>,
+    pub seed: u64,
+    pub integrator: Integrator,
+}
+
+impl RenderSettings {
+    pub fn tracer(&self) -> Tracer {
+        let mut tracer = Tracer::new();
+        tracer.samples_per_pixel = self.samples_per_pixel;
+        if let Some(threshold) = self.noise_threshold {
+            tracer.noise_threshold = threshold;
+        }
+        if let Some(max_samples) = self.max_samples_per_pixel {
+            tracer.max_samples_per_pixel = max_samples;
+        }
+        if let Some(max_depth) = self.max_depth {
+            tracer.max_depth = max_depth;
+        }
+        if let Some(min_throughput) = self.min_throughput {
+            tracer.min_throughput = min_throughput;
+        }
+        tracer.seed = self.seed;
+        tracer.integrator = self.integrator;
+        if let Some(threads) = self.threads {
+            tracer.threads = threads;
+        }
+        tracer
+    }
+
+    pub fn vfov_or_default(&self) -> f64 {
+        self.vfov.unwrap_or(VFOV_DEGREES.to_radians())
+    }
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Help,
+    View {
+        settings: RenderSettings,
+        scale: u32,
+    },
+    Render {
+        settings: RenderSettings,
+        output: PathBuf,
+        time: f64,
+        /// EXR settings when the output is an `.exr` file
+        exr: Option<ExrOptions>,
+    },
+    RenderPath {
+        settings: RenderSettings,
+        camera_path: PathBuf,
+        output: PathBuf,
+        fps: f64,
+        format: FrameFormat,
+    },
+    RenderShot {
+        settings_file: PathBuf,
+        output: PathBuf,
+        threads: Option<usize>,
+    },
+}
+
+/// Options taking a value, and the commands they apply to
+const VALUE_OPTIONS: [(&str, &[&str]); 14] = [
+    ("--output", &["render"]),
+    ("--size", &["view", "render", "render-path"]),
+    ("--scale", &["view"]),
+    ("--fov", &["view", "render", "render-path"]),
+    ("--samples", &["view", "render", "render-path"]),
+    ("--noise", &["view", "render", "render-path"]),
+    ("--max-samples", &["view", "render", "render-path"]),
+    ("--depth", &["view", "render", "render-path"]),
+    ("--cutoff", &["view", "render", "render-path"]),
+    (
+        "--threads",
+        &["view", "render", "render-path", "render-shot"],
+    ),
+    ("--seed", &["view", "render", "render-path"]),
+    ("--integrator", &["view", "render", "render-path"]),
+    ("--time", &["render"]),
+    ("--fps", &["render-path"]),
+];
+
+/// Switches of the sequence output, EXR switches are handled by `exr_flag`
+const SEQUENCE_FLAGS: [&str; 5] = ["--ppm", "--png", "--y4m", "--bt601", "--full-range"];
+
+/// Reads the command line after the program name. Errors describe the first
+/// problem found, for printing above the usage.
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let (command, args) = match args.first().map(String::as_str) {
+        Some(command @ ("view" | "render" | "render-path" | "render-shot")) => {
+            (command, &args[1..])
+        }
+        Some("help") => return Ok(Command::Help),
+        _ => ("view", args),
+    };
+
+    let mut positional: Vec<&str> = Vec::new();
+    let mut values: Vec<(&str, &str)> = Vec::new();
+    let mut flags: Vec<&str> = Vec::new();
+    let mut rest = args.iter().map(String::as_str);
+    while let Some(arg) = rest.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Command::Help);
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            positional.push(arg);
+            continue;
+        }
+
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg, None),
+        };
+        let name = if name == "-o" { "--output" } else { name };
+        if let Some((name, commands)) = VALUE_OPTIONS.iter().find(|(option, _)| *option == name) {
+            if !commands.contains(&command) {
+                return Err(format!("{} does not take {}", command, name));
+            }
+            let value = match inline {
+                Some(value) => value,
+                None => rest
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", name))?,
+            };
+            values.push((name, value));
+        } else if SEQUENCE_FLAGS.contains(&arg) || exr_flag(arg) {
+            if command != "render-path" && !(command == "render" && exr_flag(arg)) {
+                return Err(format!("{} does not take {}", command, arg));
+            }
+            flags.push(arg);
+        } else {
+            return Err(format!("Unknown option {}", arg));
+        }
+    }
+    let value = |name: &str| {
+        values
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+    };
+
+    if command == "render-shot" {
+        let (settings_file, output) = match positional[..] {
+            [settings_file, output] => (settings_file, output),
+            _ => return Err("render-shot needs a settings file and an output file".to_string()),
+        };
+        return Ok(Command::RenderShot {
+            settings_file: PathBuf::from(settings_file),
+            output: PathBuf::from(output),
+            threads: value("--threads").map(positive).transpose()?,
+        });
+    }
+
+    // the scene comes after the arguments the command needs
+    let needed = if command == "render-path" { 2 } else { 0 };
+    if positional.len() < needed {
+        return Err("render-path needs a camera path file and an output".to_string());
+    }
+    if positional.len() > needed + 1 {
+        return Err(format!("Unexpected argument {}", positional[needed + 1]));
+    }
+    let settings = RenderSettings {
+        scene: positional.get(needed).map(PathBuf::from),
+        size: value("--size").map_or(Ok(SIZE), parse_size)?,
+        vfov: value("--fov")
+            .map(|fov| match fov.parse::<f64>() {
+                Ok(fov) if fov > 0.0 && fov < 180.0 => Ok(fov.to_radians()),
+                _ => Err(format!(
+                    "Field of view must be between 0 and 180 degrees, not {}",
+                    fov
+                )),
+            })
+            .transpose()?,
+        samples_per_pixel: value("--samples").map_or(Ok(1), positive)?,
+        noise_threshold: value("--noise")
+            .map(|noise| match noise.parse::<f64>() {
+                Ok(noise) if noise > 0.0 && noise.is_finite() => Ok(noise),
+                _ => Err(format!("Noise threshold must be above 0, not {}", noise)),
+            })
+            .transpose()?,
+        max_samples_per_pixel: value("--max-samples").map(positive).transpose()?,
+        max_depth: value("--depth")
+            .map(|depth| match number(depth)? {
+                depth if depth > MAX_DEPTH => Err(format!(
+                    "Depth must be at most {}, not {}",
+                    MAX_DEPTH, depth
+                )),
+                depth => Ok(depth),
+            })
+            .transpose()?,
+        min_throughput: value("--cutoff")
+            .map(|cutoff| match cutoff.parse::<f64>() {
+                Ok(cutoff) if (0.0..=1.0).contains(&cutoff) => Ok(cutoff),
+                _ => Err(format!("Cutoff must be between 0 and 1, not {}", cutoff)),
+            })
+            .transpose()?,
+        threads: value("--threads").map(positive).transpose()?,
+        seed: value("--seed").map_or(Ok(0), number)?,
+        integrator: value("--integrator").map_or(Ok(Integrator::Path), |name| {
+            Integrator::ALL
+                .into_iter()
+                .find(|integrator| integrator.name() == name)
+                .ok_or_else(|| format!("Unknown integrator {}", name))
+        })?,
+    };
+
+    match command {
+        "view" => {
+            let scale = value("--scale").map_or(Ok(WINDOW_SCALE), positive)?;
+            // window sides are signed 32-bit numbers to SDL
+            let (width, height) = settings.size;
+            let fits = |side: u32| {
+                side.checked_mul(scale)
+                    .is_some_and(|s| s <= i32::MAX as u32)
+            };
+            if !fits(width) || !fits(height) {
+                return Err(format!(
+                    "Window of {}x{} scaled {} times is too large",
+                    width, height, scale
+                ));
+            }
+            Ok(Command::View { settings, scale })
+        }
+        "render" => {
+            let output = value("--output").ok_or("render needs an --output file")?;
+            let exr = match Path::new(output).extension().and_then(|e| e.to_str()) {
+                Some("exr") => match frame_format(&flags, true)? {
+                    FrameFormat::Exr(options) => Some(options),
+                    FrameFormat::Sequence(_) => unreachable!(),
+                },
+                Some("png" | "ppm") if flags.is_empty() => None,
+                Some("png" | "ppm") => {
+                    return Err(format!("{} only applies to .exr output", flags[0]))
+                }
+                _ => return Err(format!("Cannot tell the image format of {}", output)),
+            };
+            Ok(Command::Render {
+                settings,
+                output: PathBuf::from(output),
+                time: value("--time").map_or(Ok(0.0), |time| match time.parse::<f64>() {
+                    Ok(time) if time.is_finite() => Ok(time),
+                    _ => Err(format!("Time must be a number, not {}", time)),
+                })?,
+                exr,
+            })
+        }
+        _ => Ok(Command::RenderPath {
+            settings,
+            camera_path: PathBuf::from(positional[0]),
+            output: PathBuf::from(positional[1]),
+            fps: value("--fps").map_or(Ok(PATH_FPS), |fps| match fps.parse::<f64>() {
+                Ok(fps) if fps.is_finite() && fps > 0.0 => Ok(fps),
+                _ => Err(format!("Frame rate must be a positive number, not {}", fps)),
+            })?,
+            format: frame_format(&flags, false)?,
+        }),
+    }
+}
+
+fn exr_flag(flag: &str) -> bool {
+    matches!(flag, "--exr" | "--half" | "--uncompressed") || flag.starts_with("--aovs=")
+}
+
+/// Output of the flags given; `exr` starts from EXR output, otherwise the
+/// EXR flags switch to it
+fn frame_format(flags: &[&str], mut exr: bool) -> Result<FrameFormat, String> {
+    let mut options = ExrOptions {
+        pixel_type: PixelType::Float,
+        compression: Compression::Zip,
+        layers: Vec::new(),
+    };
+    let mut sequence = None;
+    let mut y4m = Y4mOptions::default();
+    for flag in flags {
+        match *flag {
+            "--ppm" => sequence = Some(SequenceFormat::Ppm),
+            "--png" => sequence = Some(SequenceFormat::Png),
+            "--y4m" => sequence = Some(SequenceFormat::Y4m(y4m)),
+            "--bt601" => {
+                y4m.matrix = Matrix::Bt601;
+                sequence = Some(SequenceFormat::Y4m(y4m));
+            }
+            "--full-range" => {
+                y4m.range = Range::Full;
+                sequence = Some(SequenceFormat::Y4m(y4m));
+            }
+            "--exr" => exr = true,
+            "--half" => {
+                exr = true;
+                options.pixel_type = PixelType::Half;
+            }
+            "--uncompressed" => {
+                exr = true;
+                options.compression = Compression::None;
+            }
+            "--aovs=all" => {
+                exr = true;
+                options.layers = Layer::ALL.to_vec();
+            }
+            _ => {
+                exr = true;
+                let names = flag.trim_start_matches("--aovs=");
+                for name in names.split(',') {
+                    let layer = Layer::parse(name).ok_or(format!("Unknown AOV layer {}", name))?;
+                    options.layers.push(layer);
+                }
+            }
+        }
+    }
+    match (exr, sequence) {
+        (true, Some(_)) => {
+            Err("EXR frames cannot also be written as 8-bit images or video".to_string())
+        }
+        (true, None) => Ok(FrameFormat::Exr(options)),
+        (false, sequence) => Ok(FrameFormat::Sequence(
+            sequence.unwrap_or(SequenceFormat::Ppm),
+        )),
+    }
+}
+
+/// `WxH`, both at least one
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Size must look like 640x480, not {}", size);
+    let (w, h) = size.split_once('x').ok_or_else(invalid)?;
+    match (w.parse::<u32>(), h.parse::<u32>()) {
+        // frames take four bytes a pixel, more than 4 GiB of them won't be allocated
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => match w.checked_mul(h).and_then(|n| n.checked_mul(4)) {
+            Some(_) => Ok((w, h)),
+            None => Err(format!("Size {} is too large", size)),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Expected a whole number, not {}", value))
+}
+
+fn positive<T: std::str::FromStr + Default + PartialEq>(value: &str) -> Result<T, String> {
+    match number(value)? {
+        n if n == T::default() => Err(format!("Expected a positive number, not {}", value)),
+        n => Ok(n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use ray_tracer::headless::FrameFormat;
+    use ray_tracer::image::exr::PixelType;
+    use ray_tracer::recording::SequenceFormat;
+    use ray_tracer::tracer::Integrator;
+
+    use super::{parse, Command};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn view_by_default() {
+        let settings = match parse(&args("")).unwrap() {
+            Command::View { settings, scale } => {
+                assert_eq!(scale, 3);
+                settings
+            }
+            command => panic!("{:?}", command),
+        };
+        assert_eq!(settings.scene, None);
+        assert_eq!(settings.size, (640, 480));
+        assert_eq!(settings.vfov, None);
+        assert_eq!(settings.samples_per_pixel, 1);
+        assert_eq!(settings.max_depth, None);
+        assert_eq!(settings.integrator, Integrator::Path);
+
+        match parse(&args(
+            "room.txt --scale 2 --size=320x240 --depth 4 --cutoff 0.1",
+        ))
+        .unwrap()
+        {
+            Command::View { settings, scale } => {
+                assert_eq!(scale, 2);
+                assert_eq!(settings.scene, Some(PathBuf::from("room.txt")));
+                assert_eq!(settings.size, (320, 240));
+                assert_eq!(settings.max_depth, Some(4));
+                assert_eq!(settings.tracer().min_throughput, 0.1);
+            }
+            command => panic!("{:?}", command),
+        }
+        assert!(matches!(parse(&args("view --help")), Ok(Command::Help)));
+        assert!(matches!(parse(&args("help")), Ok(Command::Help)));
+    }
+
+    #[test]
+    fn render_options() {
+        let line = "render scene.txt -o out.exr --samples 16 --threads 2 --seed 7 \
+                    --noise 0.05 --max-samples 128 --integrator ao --fov 45 --time 1.5 --half --aovs=depth,id";
+        match parse(&args(line)).unwrap() {
+            Command::Render {
+                settings,
+                output,
+                time,
+                exr,
+            } => {
+                assert_eq!(output, PathBuf::from("out.exr"));
+                assert_eq!(time, 1.5);
+                let exr = exr.unwrap();
+                assert_eq!(exr.pixel_type, PixelType::Half);
+                assert_eq!(exr.layers.len(), 2);
+
+                assert_eq!(settings.samples_per_pixel, 16);
+                assert_eq!(settings.threads, Some(2));
+                assert_eq!(settings.seed, 7);
+                assert_eq!(settings.integrator, Integrator::AmbientOcclusion);
+                assert_eq!(settings.vfov, Some(45f64.to_radians()));
+                let tracer = settings.tracer();
+                assert_eq!(tracer.threads, 2);
+                assert_eq!(tracer.samples_per_pixel, 16);
+                assert_eq!(tracer.noise_threshold, 0.05);
+                assert_eq!(tracer.max_samples_per_pixel, 128);
+            }
+            command => panic!("{:?}", command),
+        }
+
+        match parse(&args("render-path path.txt out.y4m --fps 24 --bt601")).unwrap() {
+            Command::RenderPath { fps, format, .. } => {
+                assert_eq!(fps, 24.0);
+                assert!(matches!(
+                    format,
+                    FrameFormat::Sequence(SequenceFormat::Y4m(_))
+                ));
+            }
+            command => panic!("{:?}", command),
+        }
+    }
+
+    #[test]
+    fn validation() {
+        let error = |line: &str| parse(&args(line)).unwrap_err();
+        assert_eq!(error("--frobnicate"), "Unknown option --frobnicate");
+        assert_eq!(error("render"), "render needs an --output file");
+        assert_eq!(
+            error("render -o out.gif"),
+            "Cannot tell the image format of out.gif"
+        );
+        assert_eq!(
+            error("render -o out.png --half"),
+            "--half only applies to .exr output"
+        );
+        assert_eq!(
+            error("render -o out.png --scale 2"),
+            "render does not take --scale"
+        );
+        assert_eq!(error("view --png"), "view does not take --png");
+        assert_eq!(error("view --samples"), "--samples needs a value");
+        assert_eq!(
+            error("view --samples 0"),
+            "Expected a positive number, not 0"
+        );
+        assert_eq!(error("view --depth -1"), "Expected a whole number, not -1");
+        assert_eq!(
+            error("view --depth 100000"),
+            "Depth must be at most 256, not 100000"
+        );
+        assert_eq!(
+            error("view --size 640"),
+            "Size must look like 640x480, not 640"
+        );
+        assert_eq!(error("view --integrator bdpt"), "Unknown integrator bdpt");
+        assert_eq!(
+            error("render -o a.png --size 70000x70000"),
+            "Size 70000x70000 is too large"
+        );
+        assert_eq!(
+            error("--size 640x480 --scale 4000000"),
+            "Window of 640x480 scaled 4000000 times is too large"
+        );
+        assert_eq!(
+            error("render -o a.png --time nan"),
+            "Time must be a number, not nan"
+        );
+        assert_eq!(
+            error("render-path path.txt out --fps inf"),
+            "Frame rate must be a positive number, not inf"
+        );
+        assert_eq!(
+            error("render -o a.png --noise 0"),
+            "Noise threshold must be above 0, not 0"
+        );
+        assert_eq!(error("view a.txt b.txt"), "Unexpected argument b.txt");
+        assert_eq!(
+            error("render-path path.txt"),
+            "render-path needs a camera path file and an output"
+        );
+        assert_eq!(
+            error("render-path path.txt out --png --exr"),
+            "EXR frames cannot also be written as 8-bit images or video"
+        );
+        assert!(parse(&args("render-shot shot.txt out.png --samples 4")).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+use std::time::Instant;
+
+/// Scene time advancing in real time, with pause support for interactive playback.
+pub struct Clock {
+    time: f64,
+    last_tick: Instant,
+    paused: bool,
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::new()
+    }
+}
+
+impl Clock {
+    pub fn new() -> Clock {
+        Clock {
+            time: 0.0,
+            last_tick: Instant::now(),
+            paused: false,
+        }
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn tick(&mut self) -> f64 {
+        let now = Instant::now();
+        if !self.paused {
+            self.time += (now - self.last_tick).as_secs_f64();
+        }
+        self.last_tick = now;
+        self.time
+    }
+}
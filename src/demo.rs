@@ -0,0 +1,58 @@
+use std::io;
+use std::path::Path;
+
+use crate::geometry::{environment::Environment, scene::Scene, sphere::Sphere, vector::Vector};
+use crate::material::Material;
+use crate::medium::{grid::VoxelGrid, Medium, Volume};
+
+/// Lights the scene when present, an equirectangular `.hdr` image
+pub const ENVIRONMENT_PATH: &str = "environment.hdr";
+/// Density of the smoke toggled into the demo when present, a raw voxel grid
+pub const VOLUME_PATH: &str = "volume.vox";
+/// Scattering of the fog toggled in, per unit distance
+pub const FOG_DENSITY: f64 = 0.04;
+
+/// Demo scene of `Scene::new`, lit by the environment map in the working
+/// directory if there is one. Fails when that map can't be read.
+pub fn scene() -> io::Result<Scene> {
+    let mut scene = Scene::new();
+    let environment_path = Path::new(ENVIRONMENT_PATH);
+    if environment_path.exists() {
+        scene.environment = Some(Environment::load(environment_path)?);
+    }
+    Ok(scene)
+}
+
+/// Light haze filling the demo room, bounded so the sky still shows through it
+pub fn fog() -> Volume {
+    Volume::new(
+        Medium::new(
+            Vector::one() * (FOG_DENSITY / 8.0),
+            Vector::one() * FOG_DENSITY,
+            0.3,
+        ),
+        Some(Box::new(Sphere::new(
+            Vector::new(0.5, 0.5, 2.5),
+            10.0,
+            Material::default(),
+        ))),
+    )
+}
+
+/// Cloud of smoke above the big sphere, from the voxel grid in the working
+/// directory if there is one, otherwise from noise. Fails when that grid can't
+/// be read.
+pub fn smoke() -> io::Result<Volume> {
+    let (min, max) = (Vector::new(-3.0, 0.3, 4.0), Vector::new(-0.6, 2.7, 6.4));
+    let path = Path::new(VOLUME_PATH);
+    let grid = if path.exists() {
+        VoxelGrid::load(path, min, max)?
+    } else {
+        VoxelGrid::cloud(min, max, 48, 7)
+    };
+    Ok(Volume::new(
+        Medium::new(Vector::one() * 0.3, Vector::one() * 3.0, 0.2),
+        None,
+    )
+    .with_density(grid))
+}
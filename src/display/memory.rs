@@ -0,0 +1,33 @@
+use super::Display;
+use crate::framebuffer::Framebuffer;
+
+/// Keeps the last frame presented instead of showing it.
+pub struct MemoryDisplay {
+    pub last_frame: Option<Framebuffer>,
+    pub frames_presented: usize,
+}
+
+impl Default for MemoryDisplay {
+    fn default() -> MemoryDisplay {
+        MemoryDisplay::new()
+    }
+}
+
+impl MemoryDisplay {
+    pub fn new() -> MemoryDisplay {
+        MemoryDisplay {
+            last_frame: None,
+            frames_presented: 0,
+        }
+    }
+}
+
+impl Display for MemoryDisplay {
+    fn present(&mut self, frame: &Framebuffer) {
+        match &mut self.last_frame {
+            Some(last) => last.clone_from(frame),
+            None => self.last_frame = Some(frame.clone()),
+        }
+        self.frames_presented += 1;
+    }
+}
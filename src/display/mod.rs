@@ -0,0 +1,12 @@
+#[cfg(feature = "sdl")]
+pub mod sdl;
+
+pub mod memory;
+
+use crate::framebuffer::Framebuffer;
+
+/// Where finished frames go to be seen: a window, or memory for tests and
+/// servers that have no screen.
+pub trait Display {
+    fn present(&mut self, frame: &Framebuffer);
+}
@@ -0,0 +1,77 @@
+use sdl2::render::Canvas;
+
+use super::Display;
+use crate::framebuffer::Framebuffer;
+
+/// Window that shows frames scaled up to fill it, and the keyboard and
+/// window events that come with it.
+pub struct SdlDisplay {
+    canvas: Canvas<sdl2::video::Window>,
+    pub event_pump: sdl2::EventPump,
+}
+
+impl SdlDisplay {
+    pub fn initialize(
+        window_width: u32,
+        window_height: u32,
+        render_width: u32,
+        render_height: u32,
+    ) -> SdlDisplay {
+        let sdl_context = sdl2::init().unwrap_or_else(|err| {
+            println!("Cannot initialize SDL! {}", err);
+            std::process::exit(1);
+        });
+
+        let video_subsystem = sdl_context
+            .video()
+            .expect("Cannot initialize video for SDL!");
+
+        let mut window = video_subsystem
+            .window("ray tracer", window_width, window_height)
+            .resizable()
+            .position_centered()
+            .build()
+            .expect("Cannot initialize video mode for SDL! {}");
+
+        window
+            .set_title("Ray Tracer")
+            .expect("Failed to set window title!");
+
+        let event_pump = sdl_context
+            .event_pump()
+            .expect("Cannot initialize event pump for SDL!");
+
+        let mut canvas = window
+            .into_canvas()
+            .present_vsync()
+            .build()
+            .expect("Failed to get canvas from window!");
+
+        canvas
+            .set_integer_scale(true)
+            .expect("Failed to set integer scale!");
+        canvas
+            .set_logical_size(render_width, render_height)
+            .expect("Failed to set logical size for the canvas!");
+
+        SdlDisplay { canvas, event_pump }
+    }
+}
+
+impl Display for SdlDisplay {
+    fn present(&mut self, frame: &Framebuffer) {
+        let (w, h) = frame.size();
+
+        let tex_creator = self.canvas.texture_creator();
+        let mut tex = tex_creator
+            .create_texture_streaming(None, w, h)
+            .expect("Cannot create texture for rendering!");
+        tex.update(None, frame.pixels(), frame.stride())
+            .expect("Cannot copy frame into texture!");
+
+        self.canvas
+            .copy(&tex, None, None)
+            .expect("Cannot copy texture to framebuffer!");
+        self.canvas.present();
+    }
+}
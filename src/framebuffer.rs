@@ -0,0 +1,268 @@
+use std::ops::Range;
+use std::sync::Mutex;
+use std::thread;
+
+use crate::camera::Camera;
+use crate::geometry::scene::Scene;
+use crate::tracer::{PixelAovs, Tracer};
+
+/// Traced frame in memory, four bytes per pixel in BGRA order and rows
+/// packed without padding - the layout streaming display textures take.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Bytes from one row to the next
+    #[inline]
+    pub fn stride(&self) -> usize {
+        self.width as usize * 4
+    }
+
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Tightly packed 8-bit RGB, as the image writers take it
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.pixels
+            .chunks_exact(4)
+            .flat_map(|p| [p[2], p[1], p[0]])
+            .collect()
+    }
+}
+
+/// Everything a frame is traced from.
+pub struct RenderJob<'a> {
+    pub tracer: &'a Tracer,
+    pub camera: &'a Camera,
+    pub scene: &'a Scene,
+    pub time: f64,
+}
+
+impl RenderJob<'_> {
+    /// Traces the whole frame into `frame`, at the frame's resolution.
+    pub fn render(&self, frame: &mut Framebuffer) {
+        self.render_rows(frame, 0..frame.height);
+    }
+
+    /// Traces only rows `rows` of the frame, leaving the others as they are,
+    /// so a frame can be split into bands rendered separately. The rows are
+    /// shared out between `Tracer::threads` threads.
+    pub fn render_rows(&self, frame: &mut Framebuffer, rows: Range<u32>) {
+        let (w, h) = frame.size();
+        let stride = frame.stride();
+        let rows = rows.start.min(h)..rows.end.min(h);
+        let band = &mut frame.pixels[rows.start as usize * stride..rows.end as usize * stride];
+        share_rows(band, stride, rows, self.tracer.threads, |row, y| {
+            self.render_row(row, y, (w, h))
+        });
+    }
+
+    /// Traces a `width` x `height` frame with the layers compositing works
+    /// with, row after row from the top, sharing the rows out like `render_rows`.
+    pub fn render_aovs(&self, (width, height): (u32, u32)) -> Vec<PixelAovs> {
+        let mut pixels = vec![None; width as usize * height as usize];
+        share_rows(
+            &mut pixels,
+            width as usize,
+            0..height,
+            self.tracer.threads,
+            |row, y| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    *pixel = Some(self.tracer.trace_pixel_aovs(
+                        x as u32,
+                        y,
+                        (width, height),
+                        self.camera,
+                        self.scene,
+                        self.time,
+                    ));
+                }
+            },
+        );
+        pixels.into_iter().flatten().collect()
+    }
+
+    fn render_row(&self, row: &mut [u8], y: u32, (w, h): (u32, u32)) {
+        for x in 0..w {
+            let pos = x as usize * 4;
+            let (r, g, b) = self
+                .tracer
+                .trace_pixel(x, y, (w, h), self.camera, self.scene, self.time)
+                .spread();
+
+            let r = (r * 256.0).clamp(0.0, 255.0) as u8; // TODO: Fix hue shifting issue
+            let g = (g * 256.0).clamp(0.0, 255.0) as u8;
+            let b = (b * 256.0).clamp(0.0, 255.0) as u8;
+
+            row[pos] = b; // b
+            row[pos + 1] = g; // g
+            row[pos + 2] = r; // r
+            row[pos + 3] = 255; // a?
+        }
+    }
+}
+
+/// Calls `trace_row` for the rows `rows` of `band`, `row_len` items each, on
+/// `threads` threads. Rows are handed out one at a time, so slow rows don't
+/// hold up a whole share of them.
+fn share_rows<T: Send>(
+    band: &mut [T],
+    row_len: usize,
+    rows: Range<u32>,
+    threads: usize,
+    trace_row: impl Fn(&mut [T], u32) + Sync,
+) {
+    let threads = threads.clamp(1, rows.len().max(1));
+    let mut queue = band.chunks_exact_mut(row_len).zip(rows);
+    if threads == 1 {
+        for (row, y) in queue {
+            trace_row(row, y);
+        }
+        return;
+    }
+
+    let queue = Mutex::new(&mut queue);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                match next {
+                    Some((row, y)) => trace_row(row, y),
+                    None => break,
+                }
+            });
+        }
+    });
+}
+
+/// Traces a `width` x `height` frame of `scene` as seen by `camera` at `time`.
+pub fn render(
+    tracer: &Tracer,
+    camera: &Camera,
+    scene: &Scene,
+    (width, height): (u32, u32),
+    time: f64,
+) -> Framebuffer {
+    let mut frame = Framebuffer::new(width, height);
+    RenderJob {
+        tracer,
+        camera,
+        scene,
+        time,
+    }
+    .render(&mut frame);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::camera::Camera;
+    use crate::geometry::{scene::Scene, vector::Vector};
+    use crate::tracer::Tracer;
+
+    use super::{Framebuffer, RenderJob};
+
+    #[test]
+    fn rows_render_independently() {
+        let tracer = Tracer::new();
+        let camera = Camera::new(
+            Vector::zero(),
+            Vector::one_z(),
+            Vector::one_y(),
+            PI / 3.0,
+            1.5,
+        );
+        let scene = Scene::new();
+        let job = RenderJob {
+            tracer: &tracer,
+            camera: &camera,
+            scene: &scene,
+            time: 0.0,
+        };
+
+        let mut whole = Framebuffer::new(12, 8);
+        job.render(&mut whole);
+        assert!(whole.pixels().chunks_exact(4).all(|p| p[3] == 255));
+
+        let mut bands = Framebuffer::new(12, 8);
+        job.render_rows(&mut bands, 0..3);
+        // rows past the band are still untouched
+        assert!(bands.pixels()[3 * 12 * 4..].iter().all(|&b| b == 0));
+        job.render_rows(&mut bands, 3..8);
+        assert_eq!(bands, whole);
+
+        // threads share out the rows without changing them
+        let mut tracer = Tracer::new();
+        tracer.threads = 1;
+        let mut single = Framebuffer::new(12, 8);
+        RenderJob {
+            tracer: &tracer,
+            ..job
+        }
+        .render(&mut single);
+        tracer.threads = 3;
+        let mut threaded = Framebuffer::new(12, 8);
+        RenderJob {
+            tracer: &tracer,
+            ..job
+        }
+        .render(&mut threaded);
+        assert_eq!(single, whole);
+        assert_eq!(threaded, whole);
+
+        let rgb = whole.to_rgb();
+        assert_eq!(rgb.len(), 12 * 8 * 3);
+        let bgra = &whole.pixels()[(4 * 12 + 5) * 4..];
+        assert_eq!(&rgb[(4 * 12 + 5) * 3..][..3], &[bgra[2], bgra[1], bgra[0]]);
+    }
+
+    #[test]
+    fn aovs_in_row_order() {
+        let mut tracer = Tracer::new();
+        tracer.threads = 3;
+        let camera = Camera::new(
+            Vector::zero(),
+            Vector::one_z(),
+            Vector::one_y(),
+            PI / 3.0,
+            1.5,
+        );
+        let scene = Scene::new();
+        let job = RenderJob {
+            tracer: &tracer,
+            camera: &camera,
+            scene: &scene,
+            time: 0.0,
+        };
+
+        let pixels = job.render_aovs((6, 4));
+        assert_eq!(pixels.len(), 6 * 4);
+        for (i, pixel) in pixels.iter().enumerate() {
+            let (x, y) = ((i % 6) as u32, (i / 6) as u32);
+            let single = tracer.trace_pixel_aovs(x, y, (6, 4), &camera, &scene, 0.0);
+            assert_eq!(pixel.color, single.color);
+            assert_eq!(pixel.object_id, single.object_id);
+        }
+    }
+}
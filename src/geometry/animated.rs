@@ -0,0 +1,141 @@
+use crate::material::Material;
+
+use super::{animation::Track, quaternion::Quaternion, shape::Shape, vector::Vector};
+
+/// Wraps a shape with a time-dependent rigid transform: the wrapped shape is
+/// rotated about its local origin, then translated.
+pub struct Animated {
+    pub shape: Box<dyn Shape>,
+    pub translation: Track<Vector>,
+    pub rotation: Track<Quaternion>,
+}
+
+impl Animated {
+    pub fn new(shape: Box<dyn Shape>, translation: Track<Vector>) -> Animated {
+        Animated {
+            shape,
+            translation,
+            rotation: Track::Constant(Quaternion::identity()),
+        }
+    }
+
+    #[inline]
+    fn to_local(&self, point: Vector, time: f64) -> (Vector, Quaternion) {
+        let inv_rotation = self.rotation.at(time).conjugate();
+        (
+            inv_rotation.rotate(&(point - self.translation.at(time))),
+            inv_rotation,
+        )
+    }
+}
+
+impl Shape for Animated {
+    fn intersect(&self, source: Vector, direction: Vector, t_min: f64, time: f64) -> Option<f64> {
+        // rigid transforms keep distances, so t is the same in both spaces
+        let (src, inv_rotation) = self.to_local(source, time);
+        self.shape
+            .intersect(src, inv_rotation.rotate(&direction), t_min, time)
+    }
+
+    fn normal(&self, intersect_point: Vector, time: f64) -> Vector {
+        let (ip, inv_rotation) = self.to_local(intersect_point, time);
+        inv_rotation
+            .conjugate()
+            .rotate(&self.shape.normal(ip, time))
+    }
+
+    #[inline]
+    fn get_material(&self) -> &Material {
+        self.shape.get_material()
+    }
+
+    fn uv(&self, intersect_point: Vector, time: f64) -> Option<(f64, f64)> {
+        // UVs come from the untransformed shape so textures move along with it
+        let (ip, _) = self.to_local(intersect_point, time);
+        self.shape.uv(ip, time)
+    }
+
+    fn area(&self) -> f64 {
+        self.shape.area()
+    }
+
+    fn sample_point(&self, u: f64, v: f64, time: f64) -> Option<(Vector, Vector)> {
+        let (point, normal) = self.shape.sample_point(u, v, time)?;
+        let rotation = self.rotation.at(time);
+        Some((
+            rotation.rotate(&point) + self.translation.at(time),
+            rotation.rotate(&normal),
+        ))
+    }
+
+    fn uv_scale(&self, intersect_point: Vector, time: f64) -> (f64, f64) {
+        let (ip, _) = self.to_local(intersect_point, time);
+        self.shape.uv_scale(ip, time)
+    }
+
+    fn tangents(&self, intersect_point: Vector, time: f64) -> (Vector, Vector) {
+        let (ip, inv_rotation) = self.to_local(intersect_point, time);
+        let rotation = inv_rotation.conjugate();
+        let (tangent, bitangent) = self.shape.tangents(ip, time);
+        (rotation.rotate(&tangent), rotation.rotate(&bitangent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::geometry::animation::Track;
+    use crate::geometry::quaternion::Quaternion;
+    use crate::geometry::shape::Shape;
+    use crate::geometry::sphere::Sphere;
+    use crate::geometry::vector::Vector;
+    use crate::material::Material;
+    use std::f64::consts::PI;
+
+    use super::Animated;
+
+    fn sphere() -> Box<dyn Shape> {
+        Box::new(Sphere::new(
+            Vector::new(1.0, 0.0, 0.0),
+            0.5,
+            Material {
+                color: Vector::one(),
+                refletivity_index: 0.0,
+                ..Default::default()
+            },
+        ))
+    }
+
+    #[test]
+    fn translation() {
+        let a = Animated::new(
+            sphere(),
+            Track::keyframes(vec![
+                (0.0, Vector::zero()),
+                (1.0, Vector::new(0.0, 4.0, 0.0)),
+            ]),
+        );
+        let src = Vector::new(1.0, 2.0, -5.0);
+
+        assert_eq!(a.intersect(src, Vector::one_z(), 0.0, 0.0), None);
+        assert_eq!(a.intersect(src, Vector::one_z(), 0.0, 0.5), Some(4.5));
+        assert_eq!(a.normal(Vector::new(1.0, 2.0, -0.5), 0.5), -Vector::one_z());
+    }
+
+    #[test]
+    fn rotation() {
+        let a = Animated {
+            shape: sphere(),
+            translation: Track::Constant(Vector::zero()),
+            rotation: Track::expression(|t| {
+                Quaternion::from_axis_angle(&Vector::one_y(), t * PI / 2.0)
+            }),
+        };
+
+        // after a quarter turn around Y the sphere sits at (0, 0, -1)
+        let t = a.intersect(Vector::new(0.0, 0.0, 5.0), -Vector::one_z(), 0.0, 1.0);
+        assert_delta!(t.unwrap(), 5.5, 1e-12);
+        let n = a.normal(Vector::new(0.0, 0.0, -0.5), 1.0);
+        assert_delta!(n.z, 1.0, 1e-12);
+    }
+}
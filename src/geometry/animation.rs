@@ -0,0 +1,110 @@
+use super::{quaternion::Quaternion, vector::Vector};
+
+pub trait Lerp {
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    #[inline]
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vector {
+    #[inline]
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Lerp for Quaternion {
+    #[inline]
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self.slerp(other, t)
+    }
+}
+
+/// Value changing over scene time, either keyframed or computed by an expression.
+pub enum Track<T> {
+    Constant(T),
+    /// Interpolated linearly and held constant outside of the keyed range
+    Keyframes(Keyframes<T>),
+    Expression(Box<dyn Fn(f64) -> T + Send + Sync>),
+}
+
+/// `(time, value)` pairs sorted by time, at least one of them. Only
+/// `Track::keyframes` builds them, so they stay that way.
+pub struct Keyframes<T>(Vec<(f64, T)>);
+
+impl<T> Keyframes<T> {
+    pub fn keys(&self) -> &[(f64, T)] {
+        &self.0
+    }
+}
+
+impl<T: Lerp + Copy> Track<T> {
+    pub fn keyframes(mut keys: Vec<(f64, T)>) -> Track<T> {
+        assert!(!keys.is_empty(), "Keyframe track needs at least one key");
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Track::Keyframes(Keyframes(keys))
+    }
+
+    pub fn expression<F: Fn(f64) -> T + Send + Sync + 'static>(f: F) -> Track<T> {
+        Track::Expression(Box::new(f))
+    }
+
+    pub fn at(&self, time: f64) -> T {
+        match self {
+            Track::Constant(value) => *value,
+            Track::Expression(f) => f(time),
+            Track::Keyframes(Keyframes(keys)) => {
+                let i = keys.partition_point(|k| k.0 <= time);
+                if i == 0 {
+                    return keys[0].1;
+                }
+                if i == keys.len() {
+                    return keys[i - 1].1;
+                }
+
+                let (t0, v0) = &keys[i - 1];
+                let (t1, v1) = &keys[i];
+                v0.lerp(v1, (time - t0) / (t1 - t0))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::geometry::vector::Vector;
+
+    use super::Track;
+
+    #[test]
+    fn constant() {
+        let track = Track::Constant(2.5);
+        assert_eq!(track.at(-10.0), 2.5);
+        assert_eq!(track.at(10.0), 2.5);
+    }
+
+    #[test]
+    fn keyframes() {
+        let track = Track::keyframes(vec![(2.0, Vector::one_x()), (0.0, Vector::zero())]);
+
+        assert_eq!(track.at(-1.0), Vector::zero());
+        assert_eq!(track.at(0.5), Vector::new(0.25, 0.0, 0.0));
+        assert_eq!(track.at(3.0), Vector::one_x());
+        match &track {
+            Track::Keyframes(keyframes) => assert_eq!(keyframes.keys()[0].0, 0.0),
+            _ => panic!("keyframes() builds a keyframe track"),
+        }
+    }
+
+    #[test]
+    fn expression() {
+        let track = Track::expression(|t: f64| t.sin());
+        assert_delta!(track.at(1.0), 1.0_f64.sin(), 1e-15);
+    }
+}
@@ -0,0 +1,19 @@
+use super::vector::Vector;
+
+/// Light from infinitely far away arriving along parallel rays, such as the sun.
+/// `color` is the irradiance it delivers to a surface facing it.
+pub struct DirectionalLight {
+    /// Unit vector pointing towards the light
+    pub direction: Vector,
+    pub color: Vector,
+}
+
+impl DirectionalLight {
+    #[inline]
+    pub fn new(direction: Vector, color: Vector) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.normalized(),
+            color,
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use std::f64::consts::PI;
+use std::io;
+use std::path::Path;
+
+use crate::image::Image;
+
+use super::vector::Vector;
+
+/// Piecewise constant density over 0..1 proportional to `func`.
+struct Distribution {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    /// Average of `func`, zero when there's nothing to sample
+    integral: f64,
+}
+
+impl Distribution {
+    fn new(func: Vec<f64>) -> Distribution {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i] / n as f64;
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            // all zero falls back to uniform
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n as f64
+            };
+        }
+        Distribution {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    /// Position in 0..1 for `u` and the bucket it fell into
+    fn sample(&self, u: f64) -> (f64, usize) {
+        let n = self.func.len();
+        let i = (self.cdf.partition_point(|&c| c <= u) - 1).min(n - 1);
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let offset = if width > 0.0 {
+            (u - self.cdf[i]) / width
+        } else {
+            0.0
+        };
+        ((i as f64 + offset) / n as f64, i)
+    }
+
+    fn pdf(&self, i: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[i] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Light arriving from infinitely far away in every direction, from an
+/// equirectangular (latitude-longitude) image whose top row looks straight up.
+/// Directions are importance sampled by luminance.
+pub struct Environment {
+    pub image: Image,
+    /// Turn about the vertical axis, in radians
+    pub rotation: f64,
+    pub intensity: f64,
+    rows: Distribution,
+    columns: Vec<Distribution>,
+}
+
+impl Environment {
+    pub fn new(image: Image) -> Environment {
+        let (w, h) = (image.width, image.height);
+        let columns: Vec<Distribution> = (0..h)
+            .map(|y| {
+                // rows near the poles cover less solid angle
+                let sin_theta = (PI * (y as f64 + 0.5) / h as f64).sin();
+                Distribution::new(
+                    (0..w)
+                        .map(|x| image.get(x, y).luminance() * sin_theta)
+                        .collect(),
+                )
+            })
+            .collect();
+        let rows = Distribution::new(columns.iter().map(|c| c.integral).collect());
+
+        Environment {
+            image,
+            rotation: 0.0,
+            intensity: 1.0,
+            rows,
+            columns,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Environment> {
+        Ok(Environment::new(Image::load(path)?))
+    }
+
+    /// Image coordinates of `dir`: `u` around the vertical axis, `v` from the bottom (0) to the top (1)
+    fn to_uv(&self, dir: Vector) -> (f64, f64) {
+        let d = dir.rotate(&Vector::one_y(), -self.rotation);
+        let u = 0.5 + d.x.atan2(d.z) / (2.0 * PI);
+        let v = 0.5 + d.y.clamp(-1.0, 1.0).asin() / PI;
+        (u, v)
+    }
+
+    /// Direction towards image coordinates `u`, `v`
+    pub(super) fn direction(&self, u: f64, v: f64) -> Vector {
+        let phi = (u - 0.5) * 2.0 * PI;
+        let latitude = (v - 0.5) * PI;
+        let d = Vector::new(
+            latitude.cos() * phi.sin(),
+            latitude.sin(),
+            latitude.cos() * phi.cos(),
+        );
+        d.rotate(&Vector::one_y(), self.rotation)
+    }
+
+    fn texel(&self, u: f64, v: f64) -> (u32, u32) {
+        let (w, h) = (self.image.width, self.image.height);
+        let x = ((u * w as f64) as i64).rem_euclid(w as i64) as u32;
+        let y = (((1.0 - v) * h as f64) as i64).clamp(0, h as i64 - 1) as u32;
+        (x, y)
+    }
+
+    /// Radiance arriving from direction `dir`
+    pub fn radiance(&self, dir: Vector) -> Vector {
+        let (u, v) = self.to_uv(dir);
+        let (x, y) = self.texel(u, v);
+        self.image.get(x, y) * self.intensity
+    }
+
+    /// Picks a direction in proportion to its brightness, returning it with the
+    /// radiance from there and its density per unit solid angle.
+    pub fn sample(&self, u1: f64, u2: f64) -> Option<(Vector, Vector, f64)> {
+        let (row_pos, y) = self.rows.sample(u1);
+        let (u, x) = self.columns[y].sample(u2);
+        let sin_theta = (row_pos * PI).sin();
+        if sin_theta <= 0.0 {
+            return None;
+        }
+        // area of the unit image square maps onto 2pi x pi of angles
+        let pdf = self.rows.pdf(y) * self.columns[y].pdf(x) / (2.0 * PI * PI * sin_theta);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let dir = self.direction(u, 1.0 - row_pos);
+        Some((
+            dir,
+            self.image.get(x as u32, y as u32) * self.intensity,
+            pdf,
+        ))
+    }
+
+    /// Density `sample` picks `dir` with
+    pub fn pdf(&self, dir: Vector) -> f64 {
+        let (u, v) = self.to_uv(dir);
+        let sin_theta = ((1.0 - v) * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let (x, y) = self.texel(u, v);
+        self.rows.pdf(y as usize) * self.columns[y as usize].pdf(x as usize)
+            / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::assert_delta;
+    use crate::geometry::vector::Vector;
+    use crate::image::Image;
+    use crate::random::Rng;
+
+    use super::Environment;
+
+    fn uniform() -> Environment {
+        let mut image = Image::new(8, 4);
+        image.pixels.iter_mut().for_each(|p| *p = Vector::one());
+        Environment::new(image)
+    }
+
+    #[test]
+    fn directions() {
+        let env = uniform();
+        let up = env.direction(0.3, 1.0);
+        assert_delta!(up.y, 1.0, 1e-12);
+        let forward = env.direction(0.5, 0.5);
+        assert_delta!(forward.z, 1.0, 1e-12);
+
+        let d = Vector::new(0.3, -0.5, 0.7).normalized();
+        let (u, v) = env.to_uv(d);
+        let back = env.direction(u, v);
+        assert_delta!(back.x, d.x, 1e-12);
+        assert_delta!(back.y, d.y, 1e-12);
+    }
+
+    #[test]
+    fn uniform_density() {
+        let mut env = uniform();
+        env.intensity = 2.0;
+        let mut rng = Rng::new(4);
+        for _ in 0..100 {
+            let (dir, radiance, pdf) = env.sample(rng.next_f64(), rng.next_f64()).unwrap();
+            assert_eq!(radiance, Vector::one() * 2.0);
+            assert_delta!(dir.len(), 1.0, 1e-12);
+            assert_delta!(pdf, env.pdf(dir), 1e-9);
+        }
+        // rows get the solid angle they cover, so the density is close to uniform
+        assert_delta!(env.pdf(Vector::one_x()), 1.0 / (4.0 * PI), 0.01);
+    }
+
+    #[test]
+    fn samples_bright_spots() {
+        let mut image = Image::new(16, 8);
+        image.set(12, 3, Vector::one() * 100.0);
+        let mut env = Environment::new(image);
+        env.rotation = PI / 2.0;
+
+        let mut rng = Rng::new(8);
+        let (dir, radiance, pdf) = env.sample(rng.next_f64(), rng.next_f64()).unwrap();
+        assert_eq!(radiance, Vector::one() * 100.0);
+        assert_eq!(env.radiance(dir), radiance);
+        assert_delta!(pdf, env.pdf(dir), 1e-9);
+        // the texel sits at u = 0.78, a quarter turn right of +Z, rotated another quarter
+        assert!(dir.z < -0.8);
+    }
+}
@@ -1,6 +1,13 @@
+pub mod animated;
+pub mod animation;
+pub mod directional_light;
+pub mod environment;
 pub mod planes;
 pub mod point_light;
+pub mod quaternion;
 pub mod scene;
 pub mod shape;
+pub mod sky;
 pub mod sphere;
+pub mod triangle;
 pub mod vector;
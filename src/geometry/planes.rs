@@ -31,6 +31,7 @@ impl Plane {
         &self,
         source: super::vector::Vector,
         direction: super::vector::Vector,
+        t_min: f64,
     ) -> Option<f64> {
         // Solves system of equations w.r.t. t (intersect distance from ray source):
         // x.z = src.z + t * dir.z
@@ -44,7 +45,7 @@ impl Plane {
         }
 
         let t = (self.fixed - source.z) / direction.z;
-        if t < 0.0 {
+        if t <= t_min {
             return None;
         }
 
@@ -59,6 +60,26 @@ impl Plane {
         }
         Some(t)
     }
+
+    pub fn area(&self) -> f64 {
+        (self.x_range.1 - self.x_range.0) * (self.y_range.1 - self.y_range.0)
+    }
+
+    /// Point of the rectangle at fractions `u`, `v` of its two ranges, in local coordinates
+    #[inline]
+    pub fn point_at(&self, u: f64, v: f64) -> Vector {
+        Vector::new(
+            self.x_range.0 + u * (self.x_range.1 - self.x_range.0),
+            self.y_range.0 + v * (self.y_range.1 - self.y_range.0),
+            self.fixed,
+        )
+    }
+
+    /// Planar mapping in scene units, measured from the low corner of the plane
+    #[inline]
+    pub fn uv(&self, local: Vector) -> (f64, f64) {
+        (local.x - self.x_range.0, local.y - self.y_range.0)
+    }
 }
 
 pub struct PlaneXZ {
@@ -84,14 +105,16 @@ impl Shape for PlaneXZ {
         &self,
         source: super::vector::Vector,
         direction: super::vector::Vector,
+        t_min: f64,
+        _: f64,
     ) -> Option<f64> {
         let src = Vector::new(source.x, source.z, source.y);
         let dir = Vector::new(direction.x, direction.z, direction.y);
-        self.plane.intersect(src, dir)
+        self.plane.intersect(src, dir, t_min)
     }
 
     #[inline]
-    fn normal(&self, _: Vector) -> Vector {
+    fn normal(&self, _: Vector, _: f64) -> Vector {
         Vector::new(0.0, if self.plane.negative { -1.0 } else { 1.0 }, 0.0)
     }
 
@@ -99,6 +122,26 @@ impl Shape for PlaneXZ {
     fn get_material(&self) -> &Material {
         &self.plane.material
     }
+
+    #[inline]
+    fn uv(&self, ip: Vector, _: f64) -> Option<(f64, f64)> {
+        Some(self.plane.uv(Vector::new(ip.x, ip.z, ip.y)))
+    }
+
+    fn area(&self) -> f64 {
+        self.plane.area()
+    }
+
+    fn sample_point(&self, u: f64, v: f64, time: f64) -> Option<(Vector, Vector)> {
+        let local = self.plane.point_at(u, v);
+        let point = Vector::new(local.x, local.z, local.y);
+        Some((point, self.normal(point, time)))
+    }
+
+    #[inline]
+    fn tangents(&self, _: Vector, _: f64) -> (Vector, Vector) {
+        (Vector::one_x(), Vector::one_z())
+    }
 }
 
 pub struct PlaneXY {
@@ -124,12 +167,14 @@ impl Shape for PlaneXY {
         &self,
         source: super::vector::Vector,
         direction: super::vector::Vector,
+        t_min: f64,
+        _: f64,
     ) -> Option<f64> {
-        self.plane.intersect(source, direction)
+        self.plane.intersect(source, direction, t_min)
     }
 
     #[inline]
-    fn normal(&self, _: Vector) -> Vector {
+    fn normal(&self, _: Vector, _: f64) -> Vector {
         Vector::new(0.0, 0.0, if self.plane.negative { -1.0 } else { 1.0 })
     }
 
@@ -137,6 +182,26 @@ impl Shape for PlaneXY {
     fn get_material(&self) -> &Material {
         &self.plane.material
     }
+
+    #[inline]
+    fn uv(&self, ip: Vector, _: f64) -> Option<(f64, f64)> {
+        Some(self.plane.uv(ip))
+    }
+
+    fn area(&self) -> f64 {
+        self.plane.area()
+    }
+
+    fn sample_point(&self, u: f64, v: f64, time: f64) -> Option<(Vector, Vector)> {
+        let local = self.plane.point_at(u, v);
+        let point = local;
+        Some((point, self.normal(point, time)))
+    }
+
+    #[inline]
+    fn tangents(&self, _: Vector, _: f64) -> (Vector, Vector) {
+        (Vector::one_x(), Vector::one_y())
+    }
 }
 
 pub struct PlaneYZ {
@@ -162,14 +227,16 @@ impl Shape for PlaneYZ {
         &self,
         source: super::vector::Vector,
         direction: super::vector::Vector,
+        t_min: f64,
+        _: f64,
     ) -> Option<f64> {
         let src = Vector::new(source.y, source.z, source.x);
         let dir = Vector::new(direction.y, direction.z, direction.x);
-        self.plane.intersect(src, dir)
+        self.plane.intersect(src, dir, t_min)
     }
 
     #[inline]
-    fn normal(&self, _: Vector) -> Vector {
+    fn normal(&self, _: Vector, _: f64) -> Vector {
         Vector::new(if self.plane.negative { -1.0 } else { 1.0 }, 0.0, 0.0)
     }
 
@@ -177,4 +244,24 @@ impl Shape for PlaneYZ {
     fn get_material(&self) -> &Material {
         &self.plane.material
     }
+
+    #[inline]
+    fn uv(&self, ip: Vector, _: f64) -> Option<(f64, f64)> {
+        Some(self.plane.uv(Vector::new(ip.y, ip.z, ip.x)))
+    }
+
+    fn area(&self) -> f64 {
+        self.plane.area()
+    }
+
+    fn sample_point(&self, u: f64, v: f64, time: f64) -> Option<(Vector, Vector)> {
+        let local = self.plane.point_at(u, v);
+        let point = Vector::new(local.z, local.x, local.y);
+        Some((point, self.normal(point, time)))
+    }
+
+    #[inline]
+    fn tangents(&self, _: Vector, _: f64) -> (Vector, Vector) {
+        (Vector::one_y(), Vector::one_z())
+    }
 }
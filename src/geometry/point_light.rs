@@ -1,9 +1,13 @@
-use super::vector::Vector;
+use super::{animation::Track, vector::Vector};
 
 pub struct PointLight {
     pub pos: Vector,
     pub color: Vector,
     pub power: f64,
+    /// Overrides `pos` when the light is animated
+    pub pos_track: Option<Track<Vector>>,
+    /// Overrides `color` when the light is animated
+    pub color_track: Option<Track<Vector>>,
 }
 
 impl PointLight {
@@ -13,6 +17,30 @@ impl PointLight {
             pos,
             color: color.normalized(),
             power,
+            pos_track: None,
+            color_track: None,
         }
     }
+
+    pub fn with_pos_track(mut self, track: Track<Vector>) -> PointLight {
+        self.pos_track = Some(track);
+        self
+    }
+
+    pub fn with_color_track(mut self, track: Track<Vector>) -> PointLight {
+        self.color_track = Some(track);
+        self
+    }
+
+    #[inline]
+    pub fn pos_at(&self, time: f64) -> Vector {
+        self.pos_track.as_ref().map_or(self.pos, |t| t.at(time))
+    }
+
+    #[inline]
+    pub fn color_at(&self, time: f64) -> Vector {
+        self.color_track
+            .as_ref()
+            .map_or(self.color, |t| t.at(time).normalized())
+    }
 }
@@ -0,0 +1,201 @@
+use std::ops::Mul;
+
+use super::vector::Vector;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    #[inline]
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Quaternion {
+        Quaternion { w, x, y, z }
+    }
+
+    #[inline]
+    pub fn identity() -> Quaternion {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    pub fn from_axis_angle(axis: &Vector, angle: f64) -> Quaternion {
+        let axis = axis.normalized();
+        let (s, c) = (angle / 2.0).sin_cos();
+        Quaternion::new(c, axis.x * s, axis.y * s, axis.z * s)
+    }
+
+    /// Rotation taking the reference frame (right = +X, up = +Y, forward = +Z)
+    /// onto the given orthonormal basis.
+    pub fn from_basis(right: &Vector, up: &Vector, forward: &Vector) -> Quaternion {
+        // rotation matrix columns are right, up, forward
+        let (m00, m01, m02) = (right.x, up.x, forward.x);
+        let (m10, m11, m12) = (right.y, up.y, forward.y);
+        let (m20, m21, m22) = (right.z, up.z, forward.z);
+
+        let trace = m00 + m11 + m22;
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion::new(0.25 * s, (m21 - m12) / s, (m02 - m20) / s, (m10 - m01) / s)
+        } else if m00 > m11 && m00 > m22 {
+            let s = (1.0 + m00 - m11 - m22).sqrt() * 2.0;
+            Quaternion::new((m21 - m12) / s, 0.25 * s, (m01 + m10) / s, (m02 + m20) / s)
+        } else if m11 > m22 {
+            let s = (1.0 + m11 - m00 - m22).sqrt() * 2.0;
+            Quaternion::new((m02 - m20) / s, (m01 + m10) / s, 0.25 * s, (m12 + m21) / s)
+        } else {
+            let s = (1.0 + m22 - m00 - m11).sqrt() * 2.0;
+            Quaternion::new((m10 - m01) / s, (m02 + m20) / s, (m12 + m21) / s, 0.25 * s)
+        };
+        q.normalized()
+    }
+
+    #[inline]
+    pub fn dot(&self, rhs: &Self) -> f64 {
+        self.w * rhs.w + self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    #[inline]
+    pub fn len(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    #[inline]
+    pub fn normalized(self) -> Self {
+        let len = self.len();
+        Quaternion::new(self.w / len, self.x / len, self.y / len, self.z / len)
+    }
+
+    #[inline]
+    pub fn conjugate(&self) -> Self {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn rotate(&self, v: &Vector) -> Vector {
+        let u = Vector::new(self.x, self.y, self.z);
+        let t = 2.0 * u.cross(v);
+        *v + self.w * t + u.cross(&t)
+    }
+
+    /// Spherical linear interpolation along the shortest arc.
+    pub fn slerp(&self, other: &Self, t: f64) -> Quaternion {
+        let mut other = *other;
+        let mut cos = self.dot(&other);
+        if cos < 0.0 {
+            other = Quaternion::new(-other.w, -other.x, -other.y, -other.z);
+            cos = -cos;
+        }
+
+        let (k0, k1) = if cos > 0.9995 {
+            // nearly parallel - plain lerp is accurate and avoids dividing by ~0
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+
+        Quaternion::new(
+            k0 * self.w + k1 * other.w,
+            k0 * self.x + k1 * other.x,
+            k0 * self.y + k1 * other.y,
+            k0 * self.z + k1 * other.z,
+        )
+        .normalized()
+    }
+}
+
+impl Mul<Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    #[inline]
+    fn mul(self, rhs: Quaternion) -> Self::Output {
+        Quaternion::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::geometry::vector::Vector;
+    use std::f64::consts::PI;
+
+    use super::Quaternion;
+
+    fn compare_delta(v1: Vector, v2: Vector, delta: f64) {
+        assert_delta!(v1.x, v2.x, delta);
+        assert_delta!(v1.y, v2.y, delta);
+        assert_delta!(v1.z, v2.z, delta);
+    }
+
+    #[test]
+    fn rotate_matches_vector_rotate() {
+        let axis = Vector::new(1.0, 2.0, -0.5).normalized();
+        let v = Vector::new(0.3, -1.0, 2.0);
+        let q = Quaternion::from_axis_angle(&axis, 0.7);
+
+        compare_delta(q.rotate(&v), v.rotate(&axis, 0.7), 1e-12);
+    }
+
+    #[test]
+    fn identity() {
+        let v = Vector::new(1.0, 2.0, 3.0);
+        assert_eq!(Quaternion::identity().rotate(&v), v);
+    }
+
+    #[test]
+    fn from_basis() {
+        let forward = Vector::new(1.0, 0.5, 0.2).normalized();
+        let right = Vector::one_y().cross(&forward).normalized();
+        let up = forward.cross(&right);
+        let q = Quaternion::from_basis(&right, &up, &forward);
+
+        compare_delta(q.rotate(&Vector::one_x()), right, 1e-12);
+        compare_delta(q.rotate(&Vector::one_y()), up, 1e-12);
+        compare_delta(q.rotate(&Vector::one_z()), forward, 1e-12);
+    }
+
+    #[test]
+    fn from_basis_half_turn() {
+        // trace is negative here, exercising the alternative branches
+        let q = Quaternion::from_basis(&-Vector::one_x(), &Vector::one_y(), &-Vector::one_z());
+        compare_delta(q.rotate(&Vector::one_z()), -Vector::one_z(), 1e-12);
+        compare_delta(q.rotate(&Vector::one_y()), Vector::one_y(), 1e-12);
+    }
+
+    #[test]
+    fn slerp() {
+        let a = Quaternion::identity();
+        let b = Quaternion::from_axis_angle(&Vector::one_y(), PI / 2.0);
+        let half = a.slerp(&b, 0.5);
+
+        compare_delta(
+            half.rotate(&Vector::one_z()),
+            Vector::one_z().rotate(&Vector::one_y(), PI / 4.0),
+            1e-12,
+        );
+        assert_eq!(a.slerp(&b, 0.0), a);
+        compare_delta(
+            a.slerp(&b, 1.0).rotate(&Vector::one_z()),
+            b.rotate(&Vector::one_z()),
+            1e-12,
+        );
+    }
+
+    #[test]
+    fn mul_composes() {
+        let a = Quaternion::from_axis_angle(&Vector::one_x(), 0.4);
+        let b = Quaternion::from_axis_angle(&Vector::one_z(), -1.1);
+        let v = Vector::new(0.5, 1.5, -2.0);
+
+        compare_delta((a * b).rotate(&v), a.rotate(&b.rotate(&v)), 1e-12);
+        compare_delta(a.conjugate().rotate(&a.rotate(&v)), v, 1e-12);
+    }
+}
@@ -1,9 +1,17 @@
+use crate::bsdf::{conductor::Conductor, principled::Principled};
 use crate::material::Material;
+use crate::medium::Volume;
+use crate::texture::procedural::{Bricks, Checker, Marble, Noise};
 
 use super::{
+    animated::Animated,
+    animation::Track,
+    directional_light::DirectionalLight,
+    environment::Environment,
     planes::{PlaneXY, PlaneXZ, PlaneYZ},
     point_light::PointLight,
     shape::Shape,
+    sky::Sky,
     sphere::Sphere,
     vector::Vector,
 };
@@ -12,9 +20,58 @@ pub struct Scene {
     pub shapes: Vec<Box<dyn Shape>>,
     pub ambient_light: Vector,
     pub point_lights: Vec<PointLight>,
+    pub directional_lights: Vec<DirectionalLight>,
+    /// Seen by rays leaving the scene and sampled for lighting; black when absent
+    pub environment: Option<Environment>,
+    /// Daylight sky and sun, replaces `environment` while set
+    pub sky: Option<Sky>,
+    /// Fog and other participating media the rays travel through
+    pub volumes: Vec<Volume>,
+}
+
+impl Default for Scene {
+    fn default() -> Scene {
+        Scene::new()
+    }
 }
 
 impl Scene {
+    /// Shapes with an emissive material, sampled directly for lighting
+    pub fn emitters(&self) -> impl Iterator<Item = &dyn Shape> {
+        self.shapes
+            .iter()
+            .map(|shape| shape.as_ref())
+            .filter(|shape| shape.get_material().emitted() != Vector::zero())
+    }
+
+    /// What rays leaving the scene see: the sky when there is one, otherwise the environment map
+    pub fn background(&self) -> Option<&Environment> {
+        self.sky
+            .as_ref()
+            .map(|sky| sky.environment())
+            .or(self.environment.as_ref())
+    }
+
+    /// Directional lights including the sun of the sky
+    pub fn directional_lights(&self) -> impl Iterator<Item = &DirectionalLight> {
+        self.directional_lights
+            .iter()
+            .chain(self.sky.as_ref().map(|sky| sky.sun()))
+    }
+
+    /// Nothing to see and nothing lighting it, to be filled in by the caller
+    pub fn empty() -> Scene {
+        Scene {
+            shapes: Vec::new(),
+            ambient_light: Vector::zero(),
+            point_lights: Vec::new(),
+            directional_lights: Vec::new(),
+            environment: None,
+            sky: None,
+            volumes: Vec::new(),
+        }
+    }
+
     pub fn new() -> Scene {
         let sphere_big = Sphere::new(
             Vector::new(0.0, -0.5, 3.0),
@@ -22,14 +79,22 @@ impl Scene {
             Material {
                 color: Vector::new(1.0, 0.8, 0.5),
                 refletivity_index: 0.2,
+                texture: Some(Box::new(Marble::new(
+                    Vector::one(),
+                    Vector::new(0.55, 0.45, 0.4),
+                    Vector::new(1.0, 0.4, 0.2),
+                    2.0,
+                    7,
+                ))),
+                ..Default::default()
             },
         );
         let sphere_small = Sphere::new(
             Vector::new(1.0, 1.0, 4.0),
             0.75,
             Material {
-                color: Vector::new(0.7, 0.7, 1.0),
-                refletivity_index: 0.10,
+                bsdf: Some(Box::new(Conductor::gold(0.3))),
+                ..Default::default()
             },
         );
 
@@ -41,6 +106,14 @@ impl Scene {
             Material {
                 color: Vector::new(0.5, 1.0, 0.8),
                 refletivity_index: 0.75,
+                bump_map: Some(Box::new(Bricks::new(
+                    Vector::one(),
+                    Vector::zero(),
+                    (0.8, 0.35),
+                    0.04,
+                ))),
+                bump_strength: 0.02,
+                ..Default::default()
             },
         );
         let plane_bottom = PlaneXZ::new(
@@ -51,6 +124,12 @@ impl Scene {
             Material {
                 color: Vector::new(1.0, 0.25, 0.0),
                 refletivity_index: 0.65,
+                texture: Some(Box::new(Checker::new(
+                    Vector::one(),
+                    Vector::new(0.3, 0.3, 0.3),
+                    0.8,
+                ))),
+                ..Default::default()
             },
         );
         let plane_right = PlaneYZ::new(
@@ -60,19 +139,46 @@ impl Scene {
             (1.0, 7.0),
             Material {
                 color: Vector::new(0.7, 0.25, 1.0),
-                refletivity_index: 0.8,
+                bsdf: Some(Box::new(Principled {
+                    metallic: 1.0,
+                    roughness: 0.15,
+                    ..Default::default()
+                })),
+                // hammered metal look
+                bump_map: Some(Box::new(Noise::new(Vector::zero(), Vector::one(), 6.0, 3))),
+                bump_strength: 0.03,
+                ..Default::default()
+            },
+        );
+        let neon = Sphere::new(
+            Vector::new(1.8, -1.6, 3.5),
+            0.3,
+            Material {
+                color: Vector::zero(),
+                emission: Vector::new(1.0, 0.2, 0.6),
+                emission_strength: 4.0,
+                ..Default::default()
             },
         );
         let shapes: Vec<Box<dyn Shape>> = vec![
             Box::new(sphere_big),
-            Box::new(sphere_small),
+            Box::new(neon),
+            Box::new(Animated::new(
+                Box::new(sphere_small),
+                Track::expression(|t: f64| Vector::new(0.0, 0.25 * (t * 1.5).sin(), 0.0)),
+            )),
             Box::new(plane_front),
             Box::new(plane_bottom),
             Box::new(plane_right),
         ];
 
         let point_lights = vec![
-            PointLight::new(Vector::new(2.5, 4.0, 2.0), Vector::new(0.7, 0.8, 1.0), 5.0),
+            PointLight::new(Vector::new(2.5, 4.0, 2.0), Vector::new(0.7, 0.8, 1.0), 5.0)
+                .with_pos_track(Track::keyframes(vec![
+                    (0.0, Vector::new(2.5, 4.0, 2.0)),
+                    (4.0, Vector::new(-0.5, 4.0, 2.0)),
+                    (8.0, Vector::new(2.5, 4.0, 2.0)),
+                ])),
             PointLight::new(
                 Vector::new(-5.0, 2.0, -3.0),
                 Vector::new(0.8, 1.0, 0.95),
@@ -82,13 +188,20 @@ impl Scene {
                 Vector::new(-4.0, 0.0, 3.0),
                 Vector::new(1.0, 0.95, 0.9),
                 3.0,
-            ),
+            )
+            .with_color_track(Track::expression(|t: f64| {
+                Vector::new(1.0, 0.95, 0.9 + 0.1 * (t * 2.0).sin())
+            })),
         ];
 
         Scene {
             shapes,
             ambient_light: Vector::new(0.01, 0.02, 0.04),
             point_lights,
+            directional_lights: Vec::new(),
+            environment: None,
+            sky: None,
+            volumes: Vec::new(),
         }
     }
 }
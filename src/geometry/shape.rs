@@ -2,8 +2,89 @@ use crate::material::Material;
 
 use super::vector::Vector;
 
-pub trait Shape {
-    fn intersect(&self, source: Vector, direction: Vector) -> Option<f64>;
-    fn normal(&self, intersect_point: Vector) -> Vector;
+/// Everything the tracer needs to know about a ray-surface intersection.
+#[derive(Clone, Copy, Debug)]
+pub struct HitRecord {
+    pub point: Vector,
+    pub normal: Vector,
+    pub uv: Option<(f64, f64)>,
+    /// Surface direction of growing `u`
+    pub tangent: Vector,
+    /// Surface direction of growing `v`
+    pub bitangent: Vector,
+    /// Change of `u` and `v` per unit of length along `tangent` and `bitangent`
+    pub uv_scale: (f64, f64),
+}
+
+/// Distance below which points are not told apart from a surface they were
+/// computed on, for coordinates up to about one
+const SURFACE_EPSILON: f64 = 1e-6;
+/// Growth of that distance with the coordinates, whose rounding error grows with them
+const RELATIVE_EPSILON: f64 = 1e-12;
+
+pub trait Shape: Send + Sync {
+    /// Distance along `direction` to the nearest hit further than `t_min`,
+    /// so rays can skip the surface they start on
+    fn intersect(&self, source: Vector, direction: Vector, t_min: f64, time: f64) -> Option<f64>;
+    fn normal(&self, intersect_point: Vector, time: f64) -> Vector;
     fn get_material(&self) -> &Material;
+
+    /// Surface parameterisation at a point on the shape, if it has one
+    fn uv(&self, _intersect_point: Vector, _time: f64) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// Unit tangent and bitangent following the UV directions. Shapes without
+    /// a parameterisation get an arbitrary frame around the normal.
+    fn tangents(&self, intersect_point: Vector, time: f64) -> (Vector, Vector) {
+        tangent_frame(self.normal(intersect_point, time))
+    }
+
+    /// Change of `u` and `v` per unit of length along the tangent and the
+    /// bitangent, one for UVs measured in scene units
+    fn uv_scale(&self, _intersect_point: Vector, _time: f64) -> (f64, f64) {
+        (1.0, 1.0)
+    }
+
+    /// Surface area, zero for shapes that can't be sampled as lights
+    fn area(&self) -> f64 {
+        0.0
+    }
+
+    /// Point spread uniformly over the surface for `u`, `v` in 0..1, with the
+    /// normal there, so emissive shapes can be sampled as lights.
+    fn sample_point(&self, _u: f64, _v: f64, _time: f64) -> Option<(Vector, Vector)> {
+        None
+    }
+
+    fn hit(&self, source: Vector, direction: Vector, t: f64, time: f64) -> HitRecord {
+        let point = source + direction * t;
+        let (tangent, bitangent) = self.tangents(point, time);
+        HitRecord {
+            point,
+            normal: self.normal(point, time),
+            uv: self.uv(point, time),
+            tangent,
+            bitangent,
+            uv_scale: self.uv_scale(point, time),
+        }
+    }
+}
+
+/// How far a point computed on a surface near `p` may be off it from
+/// rounding, generously: rays leaving the surface start this far away
+pub fn surface_epsilon(p: Vector) -> f64 {
+    let magnitude = p.x.abs().max(p.y.abs()).max(p.z.abs());
+    SURFACE_EPSILON + magnitude * RELATIVE_EPSILON
+}
+
+/// Any orthonormal tangent and bitangent perpendicular to `normal`
+pub fn tangent_frame(normal: Vector) -> (Vector, Vector) {
+    let helper = if normal.x.abs() < 0.9 {
+        Vector::one_x()
+    } else {
+        Vector::one_y()
+    };
+    let bitangent = normal.cross(&helper).normalized();
+    (bitangent.cross(&normal), bitangent)
 }
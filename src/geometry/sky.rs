@@ -0,0 +1,257 @@
+use std::f64::consts::PI;
+
+use crate::image::Image;
+
+use super::{directional_light::DirectionalLight, environment::Environment, vector::Vector};
+
+/// Sky luminance comes in kcd/m2, this brings a clear noon sky near the brightness of the demo lights
+const LUMINANCE_SCALE: f64 = 0.05;
+/// Irradiance of the sun above the atmosphere, in the same units as the sky
+const SUN_IRRADIANCE: f64 = 3.0;
+/// Share of the horizon radiance sent back by the ground seen below it
+const GROUND_ALBEDO: f64 = 0.3;
+/// Resolution of the baked environment, the sky is smooth so a coarse map will do
+const BAKE_WIDTH: u32 = 256;
+const BAKE_HEIGHT: u32 = 128;
+
+/// Perez et al. sky distribution, relative brightness of a point at zenith angle
+/// `theta` and angle `gamma` from the sun.
+#[inline]
+fn perez(coeffs: &[f64; 5], theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coeffs;
+    (1.0 + a * (b / theta.cos().max(1e-3)).exp())
+        * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+/// Linear sRGB from CIE xyY
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Vector {
+    let cx = x / y * luminance;
+    let cz = (1.0 - x - y) / y * luminance;
+    Vector::new(
+        3.2406 * cx - 1.5372 * luminance - 0.4986 * cz,
+        -0.9689 * cx + 1.8758 * luminance + 0.0415 * cz,
+        0.0557 * cx - 0.2040 * luminance + 1.0570 * cz,
+    )
+}
+
+/// Clear daylight sky after Preetham, Shirley and Smits (1999), with a matching
+/// sun. The sky is baked into an `Environment` so it is importance sampled like
+/// any other environment map, the sun disc is left out of it and lights the
+/// scene as a directional light instead.
+///
+/// Angles are in radians: `elevation` above the horizon and `azimuth` around the
+/// vertical axis from +Z towards +X. `turbidity` runs from about 2 for a very
+/// clear sky to 10 for a hazy one. Call `update` after changing any of them.
+pub struct Sky {
+    pub elevation: f64,
+    pub azimuth: f64,
+    pub turbidity: f64,
+    pub intensity: f64,
+    environment: Environment,
+    sun: DirectionalLight,
+}
+
+impl Default for Sky {
+    fn default() -> Sky {
+        Sky::new(35f64.to_radians(), 225f64.to_radians(), 3.0)
+    }
+}
+
+impl Sky {
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Sky {
+        let mut sky = Sky {
+            elevation,
+            azimuth,
+            turbidity,
+            intensity: 1.0,
+            environment: Environment::new(Image::new(1, 1)),
+            sun: DirectionalLight::new(Vector::one_y(), Vector::zero()),
+        };
+        sky.update();
+        sky
+    }
+
+    /// Rebuilds the baked sky and the sun from the current parameters
+    pub fn update(&mut self) {
+        self.elevation = self.elevation.clamp(0.0, PI / 2.0);
+
+        let mut image = Image::new(BAKE_WIDTH, BAKE_HEIGHT);
+        for y in 0..BAKE_HEIGHT {
+            for x in 0..BAKE_WIDTH {
+                let u = (x as f64 + 0.5) / BAKE_WIDTH as f64;
+                let v = 1.0 - (y as f64 + 0.5) / BAKE_HEIGHT as f64;
+                // the baked map is never rotated, the sun azimuth turns the sky instead
+                let dir = self.environment.direction(u, v);
+                image.set(x, y, self.radiance(dir));
+            }
+        }
+
+        self.environment = Environment::new(image);
+        self.sun = DirectionalLight::new(
+            self.sun_direction(),
+            self.sun_color() * (SUN_IRRADIANCE * self.intensity),
+        );
+    }
+
+    #[inline]
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    #[inline]
+    pub fn sun(&self) -> &DirectionalLight {
+        &self.sun
+    }
+
+    /// Unit vector pointing at the sun
+    pub fn sun_direction(&self) -> Vector {
+        let (sin_e, cos_e) = self.elevation.sin_cos();
+        Vector::new(
+            cos_e * self.azimuth.sin(),
+            sin_e,
+            cos_e * self.azimuth.cos(),
+        )
+    }
+
+    /// Radiance of the sky from direction `dir`, without the sun disc
+    pub fn radiance(&self, dir: Vector) -> Vector {
+        let t = self.turbidity;
+        let sun_theta = PI / 2.0 - self.elevation;
+        let below_horizon = dir.y < 0.0;
+        // the ground reflects the horizon, Perez isn't defined under it
+        let dir = if below_horizon {
+            Vector::new(dir.x, 0.0, dir.z).normalized()
+        } else {
+            dir
+        };
+        let theta = dir.y.clamp(-1.0, 1.0).acos();
+        let gamma = dir.dot(&self.sun_direction()).clamp(-1.0, 1.0).acos();
+
+        let coeffs_lum = [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ];
+        let coeffs_x = [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ];
+        let coeffs_y = [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ];
+
+        let (zenith_lum, zenith_x, zenith_y) = self.zenith();
+        let relative =
+            |coeffs: &[f64; 5]| perez(coeffs, theta, gamma) / perez(coeffs, 0.0, sun_theta);
+        let color = xyy_to_rgb(
+            zenith_x * relative(&coeffs_x),
+            zenith_y * relative(&coeffs_y),
+            zenith_lum * relative(&coeffs_lum),
+        );
+        let ground = if below_horizon { GROUND_ALBEDO } else { 1.0 };
+        Vector::new(color.x.max(0.0), color.y.max(0.0), color.z.max(0.0))
+            * (LUMINANCE_SCALE * self.intensity * ground)
+    }
+
+    /// Luminance (kcd/m2) and chromaticity straight up
+    fn zenith(&self) -> (f64, f64, f64) {
+        let t = self.turbidity;
+        let theta = PI / 2.0 - self.elevation;
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let (t2, th2, th3) = (t * t, theta * theta, theta * theta * theta);
+        let x = t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * theta)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * theta + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * theta + 0.25886);
+        let y = t2 * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * theta)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * theta + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * theta + 0.26688);
+        (luminance.max(0.0), x, y)
+    }
+
+    /// Share of sunlight that makes it through the atmosphere, from Rayleigh and
+    /// aerosol scattering at red, green and blue wavelengths
+    pub fn sun_color(&self) -> Vector {
+        let theta = PI / 2.0 - self.elevation;
+        // relative optical mass, Kasten's fit keeps it finite at the horizon
+        let mass = 1.0 / (theta.cos() + 0.15 * (93.885 - theta.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |lambda: f64| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * mass).exp();
+            rayleigh * aerosol
+        };
+        Vector::new(
+            transmittance(0.65),
+            transmittance(0.55),
+            transmittance(0.45),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::assert_delta;
+    use crate::geometry::vector::Vector;
+
+    use super::Sky;
+
+    #[test]
+    fn sun_direction() {
+        let sky = Sky::new(PI / 2.0, 0.0, 3.0);
+        assert_delta!(sky.sun_direction().y, 1.0, 1e-12);
+
+        let sky = Sky::new(0.0, PI / 2.0, 3.0);
+        let d = sky.sun_direction();
+        assert_delta!(d.x, 1.0, 1e-12);
+        assert_eq!(sky.sun().direction, d);
+    }
+
+    #[test]
+    fn brighter_towards_the_sun() {
+        let sky = Sky::new(0.4, 0.0, 3.0);
+        let lum = |c: Vector| c.x + c.y + c.z;
+        let near_sun = Vector::new(0.0, 0.5, 1.0).normalized();
+        let away = Vector::new(0.0, 0.5, -1.0).normalized();
+        assert!(lum(sky.radiance(near_sun)) > 2.0 * lum(sky.radiance(away)));
+        // clear skies are blue overhead
+        let zenith = sky.radiance(Vector::one_y());
+        assert!(zenith.z > zenith.x);
+        // the ground only gives back part of the horizon
+        let horizon = sky.radiance(Vector::one_x());
+        assert!(lum(sky.radiance(-Vector::one_y())) < lum(horizon));
+    }
+
+    #[test]
+    fn sun_reddens_near_horizon() {
+        let noon = Sky::new(PI / 2.0, 0.0, 3.0).sun_color();
+        let sunset = Sky::new(0.05, 0.0, 3.0).sun_color();
+        assert!(noon.x < 1.0 && noon.x > noon.z);
+        assert!(sunset.x < noon.x);
+        assert!(sunset.z / sunset.x < noon.z / noon.x);
+        // hazier air lets less through
+        assert!(Sky::new(PI / 2.0, 0.0, 8.0).sun_color().y < noon.y);
+    }
+
+    #[test]
+    fn baked_environment() {
+        let sky = Sky::default();
+        let dir = Vector::new(0.3, 0.6, -0.2).normalized();
+        let baked = sky.environment().radiance(dir);
+        let exact = sky.radiance(dir);
+        assert_delta!(baked.y, exact.y, 0.05 * exact.y);
+        assert!(sky.sun().color.x > 0.0);
+    }
+}
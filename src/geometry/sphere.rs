@@ -1,4 +1,6 @@
-use super::shape::Shape;
+use std::f64::consts::PI;
+
+use super::shape::{tangent_frame, Shape};
 use crate::{geometry::vector::Vector, material::Material};
 
 pub struct Sphere {
@@ -19,11 +21,11 @@ impl Sphere {
 
 impl Shape for Sphere {
     #[inline]
-    fn normal(&self, t: Vector) -> Vector {
+    fn normal(&self, t: Vector, _: f64) -> Vector {
         (t - self.center) / self.radius
     }
 
-    fn intersect(&self, src: Vector, dir: Vector) -> Option<f64> {
+    fn intersect(&self, src: Vector, dir: Vector, t_min: f64, _: f64) -> Option<f64> {
         // Solves system of equations w.r.t. t (intersect distance from ray source):
         // x = src + t * dir
         // |x - c|^2 = r^2
@@ -31,12 +33,17 @@ impl Shape for Sphere {
         let v = src - self.center;
         let vd = v.dot(&dir);
 
-        let dd = vd * vd - (v.len_sq() - self.radius * self.radius);
+        // r^2 minus the squared distance of the line from the centre; unlike
+        // vd^2 - (|v|^2 - r^2) it keeps its precision when the ray starts far away
+        let closest = v - dir * vd;
+        let dd = self.radius * self.radius - closest.len_sq();
         if dd < 0.0 {
             return None;
         }
+        // the far side is hit when the ray starts inside, e.g. refracted into glass
         let t = -vd - dd.sqrt();
-        if t < 0.0 {
+        let t = if t <= t_min { -vd + dd.sqrt() } else { t };
+        if t <= t_min {
             return None;
         }
         Some(t)
@@ -46,10 +53,51 @@ impl Shape for Sphere {
     fn get_material(&self) -> &Material {
         &self.material
     }
+
+    /// Spherical mapping: `u` goes once around the vertical axis, `v` from the
+    /// bottom pole (0) to the top pole (1).
+    fn uv(&self, intersect_point: Vector, time: f64) -> Option<(f64, f64)> {
+        let n = self.normal(intersect_point, time);
+        let u = 0.5 + n.x.atan2(n.z) / (2.0 * PI);
+        let v = 0.5 + n.y.clamp(-1.0, 1.0).asin() / PI;
+        Some((u, v))
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn sample_point(&self, u: f64, v: f64, _: f64) -> Option<(Vector, Vector)> {
+        // uniform in height is uniform in area on a sphere
+        let z = 1.0 - 2.0 * u;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        let normal = Vector::new(r * phi.cos(), r * phi.sin(), z);
+        Some((self.center + normal * self.radius, normal))
+    }
+
+    fn uv_scale(&self, intersect_point: Vector, time: f64) -> (f64, f64) {
+        let n = self.normal(intersect_point, time);
+        // u goes once around the circle of latitude, v half way around the sphere
+        let ring = self.radius * (n.x * n.x + n.z * n.z).sqrt().max(1e-6);
+        (1.0 / (2.0 * PI * ring), 1.0 / (PI * self.radius))
+    }
+
+    fn tangents(&self, intersect_point: Vector, time: f64) -> (Vector, Vector) {
+        let n = self.normal(intersect_point, time);
+        let around = Vector::new(n.z, 0.0, -n.x);
+        if around.len_sq() < 1e-12 {
+            // poles have no well defined direction of u
+            return tangent_frame(n);
+        }
+        let tangent = around.normalized();
+        (tangent, n.cross(&tangent))
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::assert_delta;
     use crate::geometry::shape::Shape;
     use crate::geometry::vector::Vector;
     use crate::material::Material;
@@ -64,6 +112,7 @@ mod tests {
             Material {
                 color: Vector::zero(),
                 refletivity_index: 0.0,
+                ..Default::default()
             },
         );
         assert_eq!(s.center, Vector::zero());
@@ -79,9 +128,15 @@ mod tests {
             Material {
                 color: Vector::zero(),
                 refletivity_index: 0.0,
+                ..Default::default()
             },
         );
-        let i = s.intersect(Vector::new(2.0, 0.0, 0.0), Vector::new(-1.0, 0.0, 0.0));
+        let i = s.intersect(
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            0.0,
+            0.0,
+        );
         assert_eq!(i, Some(1.0));
     }
 
@@ -93,9 +148,52 @@ mod tests {
             Material {
                 color: Vector::zero(),
                 refletivity_index: 0.0,
+                ..Default::default()
             },
         );
-        let i = s.intersect(Vector::new(2.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let i = s.intersect(
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+            0.0,
+            0.0,
+        );
+        assert_eq!(i, None);
+    }
+
+    #[test]
+    fn sample_point() {
+        let s = Sphere::new(Vector::new(1.0, 2.0, 3.0), 2.0, Material::default());
+        for (u, v) in [(0.0, 0.0), (0.3, 0.7), (0.9, 0.1)] {
+            let (p, n) = s.sample_point(u, v, 0.0).unwrap();
+            assert_delta!((p - s.center).len(), 2.0, 1e-12);
+            assert_delta!(s.normal(p, 0.0).dot(&n), 1.0, 1e-12);
+        }
+    }
+
+    #[test]
+    fn intersect_from_inside() {
+        let s = Sphere::new(Vector::zero(), 1.0, Material::default());
+        let i = s.intersect(
+            Vector::new(0.5, 0.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            0.0,
+            0.0,
+        );
+        assert_eq!(i, Some(1.5));
+        // hits up to t_min are skipped
+        let i = s.intersect(
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            1.0,
+            0.0,
+        );
+        assert_eq!(i, Some(3.0));
+        let i = s.intersect(
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            3.0,
+            0.0,
+        );
         assert_eq!(i, None);
     }
 
@@ -107,10 +205,62 @@ mod tests {
             Material {
                 color: Vector::zero(),
                 refletivity_index: 0.0,
+                ..Default::default()
             },
         );
-        assert_eq!(s.normal(Vector::one_x()), Vector::one_x());
-        assert_eq!(s.normal(Vector::one_y()), Vector::one_y());
-        assert_eq!(s.normal(Vector::one_z()), Vector::one_z());
+        assert_eq!(s.normal(Vector::one_x(), 0.0), Vector::one_x());
+        assert_eq!(s.normal(Vector::one_y(), 0.0), Vector::one_y());
+        assert_eq!(s.normal(Vector::one_z(), 0.0), Vector::one_z());
+    }
+
+    #[test]
+    fn uv() {
+        let s = Sphere::new(
+            Vector::zero(),
+            2.0,
+            Material {
+                color: Vector::zero(),
+                refletivity_index: 0.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(s.uv(Vector::new(0.0, 0.0, 2.0), 0.0), Some((0.5, 0.5)));
+        assert_eq!(s.uv(Vector::new(2.0, 0.0, 0.0), 0.0), Some((0.75, 0.5)));
+        assert_eq!(s.uv(Vector::new(0.0, 2.0, 0.0), 0.0).unwrap().1, 1.0);
+        assert_eq!(s.uv(Vector::new(0.0, -2.0, 0.0), 0.0).unwrap().1, 0.0);
+    }
+
+    #[test]
+    fn tangents() {
+        let s = Sphere::new(
+            Vector::zero(),
+            1.0,
+            Material {
+                color: Vector::zero(),
+                refletivity_index: 0.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            s.tangents(Vector::one_z(), 0.0),
+            (Vector::one_x(), Vector::one_y())
+        );
+
+        let (t, b) = s.tangents(Vector::one_y(), 0.0);
+        assert!(t.dot(&Vector::one_y()).abs() < 1e-12);
+        assert!(b.dot(&Vector::one_y()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn uv_scale() {
+        let s = Sphere::new(Vector::zero(), 2.0, Default::default());
+        // a step along the equator against the uv change it makes
+        let (du, dv) = s.uv_scale(Vector::new(0.0, 0.0, 2.0), 0.0);
+        let (u0, _) = s.uv(Vector::new(0.0, 0.0, 2.0), 0.0).unwrap();
+        let step = 1e-4_f64;
+        let moved = Vector::new(2.0 * (step / 2.0).sin(), 0.0, 2.0 * (step / 2.0).cos());
+        let (u1, _) = s.uv(moved, 0.0).unwrap();
+        assert_delta!((u1 - u0) / step, du, 1e-6);
+        assert_delta!(dv, 1.0 / (2.0 * std::f64::consts::PI), 1e-12);
     }
 }
@@ -0,0 +1,216 @@
+use crate::material::Material;
+
+use super::{
+    shape::{tangent_frame, Shape},
+    vector::Vector,
+};
+
+/// Single-sided triangle with per-vertex UVs interpolated across the face. The
+/// front is the side `(v1 - v0) x (v2 - v0)` points out of; rays reaching the
+/// back pass through.
+pub struct Triangle {
+    pub vertices: [Vector; 3],
+    pub uvs: [(f64, f64); 3],
+    pub material: Material,
+    normal: Vector,
+    tangents: (Vector, Vector),
+    /// Change of u and v per unit of length along the tangents
+    uv_scale: (f64, f64),
+}
+
+impl Triangle {
+    pub fn new(vertices: [Vector; 3], uvs: [(f64, f64); 3], material: Material) -> Triangle {
+        let e1 = vertices[1] - vertices[0];
+        let e2 = vertices[2] - vertices[0];
+        let normal = e1.cross(&e2).normalized();
+
+        // solve e1 = du1 * T + dv1 * B, e2 = du2 * T + dv2 * B
+        let (du1, dv1) = (uvs[1].0 - uvs[0].0, uvs[1].1 - uvs[0].1);
+        let (du2, dv2) = (uvs[2].0 - uvs[0].0, uvs[2].1 - uvs[0].1);
+        let det = du1 * dv2 - du2 * dv1;
+        let (tangents, uv_scale) = if det.abs() < 1e-12 {
+            (tangent_frame(normal), (1.0, 1.0))
+        } else {
+            let dp_du = (e1 * dv2 - e2 * dv1) / det;
+            let dp_dv = (e2 * du1 - e1 * du2) / det;
+            (
+                (dp_du.normalized(), dp_dv.normalized()),
+                (1.0 / dp_du.len(), 1.0 / dp_dv.len()),
+            )
+        };
+
+        Triangle {
+            vertices,
+            uvs,
+            material,
+            normal,
+            tangents,
+            uv_scale,
+        }
+    }
+
+    /// Barycentric weights of the second and third vertex for a point in the triangle plane
+    fn barycentric(&self, p: Vector) -> (f64, f64) {
+        let e1 = self.vertices[1] - self.vertices[0];
+        let e2 = self.vertices[2] - self.vertices[0];
+        let d = p - self.vertices[0];
+
+        let (d11, d12, d22) = (e1.dot(&e1), e1.dot(&e2), e2.dot(&e2));
+        let (dp1, dp2) = (d.dot(&e1), d.dot(&e2));
+        let denom = d11 * d22 - d12 * d12;
+        (
+            (d22 * dp1 - d12 * dp2) / denom,
+            (d11 * dp2 - d12 * dp1) / denom,
+        )
+    }
+}
+
+impl Shape for Triangle {
+    fn intersect(&self, source: Vector, direction: Vector, t_min: f64, _: f64) -> Option<f64> {
+        // Moller-Trumbore: solves src + t * dir = v0 + b1 * e1 + b2 * e2
+        if direction.dot(&self.normal) >= 0.0 {
+            return None;
+        }
+
+        let e1 = self.vertices[1] - self.vertices[0];
+        let e2 = self.vertices[2] - self.vertices[0];
+        let p = direction.cross(&e2);
+        let det = e1.dot(&p);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        let s = source - self.vertices[0];
+        let b1 = s.dot(&p) / det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+        let q = s.cross(&e1);
+        let b2 = direction.dot(&q) / det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = e2.dot(&q) / det;
+        if t <= t_min {
+            return None;
+        }
+        Some(t)
+    }
+
+    #[inline]
+    fn normal(&self, _: Vector, _: f64) -> Vector {
+        self.normal
+    }
+
+    #[inline]
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn uv(&self, intersect_point: Vector, _: f64) -> Option<(f64, f64)> {
+        let (b1, b2) = self.barycentric(intersect_point);
+        let b0 = 1.0 - b1 - b2;
+        let [uv0, uv1, uv2] = self.uvs;
+        Some((
+            b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+            b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+        ))
+    }
+
+    #[inline]
+    fn tangents(&self, _: Vector, _: f64) -> (Vector, Vector) {
+        self.tangents
+    }
+
+    #[inline]
+    fn uv_scale(&self, _: Vector, _: f64) -> (f64, f64) {
+        self.uv_scale
+    }
+
+    fn area(&self) -> f64 {
+        let e1 = self.vertices[1] - self.vertices[0];
+        let e2 = self.vertices[2] - self.vertices[0];
+        e1.cross(&e2).len() / 2.0
+    }
+
+    fn sample_point(&self, u: f64, v: f64, _: f64) -> Option<(Vector, Vector)> {
+        // square root warp keeps the barycentric samples uniform in area
+        let su = u.sqrt();
+        let (b1, b2) = (su * (1.0 - v), su * v);
+        let [v0, v1, v2] = self.vertices;
+        Some((v0 + (v1 - v0) * b1 + (v2 - v0) * b2, self.normal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::geometry::shape::Shape;
+    use crate::geometry::vector::Vector;
+    use crate::material::Material;
+
+    use super::Triangle;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            [
+                Vector::zero(),
+                Vector::new(0.0, 1.0, 0.0),
+                Vector::new(1.0, 0.0, 0.0),
+            ],
+            [(0.0, 0.0), (0.0, 1.0), (1.0, 0.0)],
+            Material::default(),
+        )
+    }
+
+    #[test]
+    fn sample_point() {
+        let t = triangle();
+        assert_delta!(t.area(), 0.5, 1e-12);
+        for (u, v) in [(0.0, 0.0), (1.0, 1.0), (0.5, 0.25), (0.9, 0.6)] {
+            let (p, n) = t.sample_point(u, v, 0.0).unwrap();
+            assert_eq!(n, t.normal(p, 0.0));
+            assert!(p.z == 0.0 && p.x >= 0.0 && p.y >= 0.0 && p.x + p.y <= 1.0 + 1e-12);
+        }
+    }
+
+    #[test]
+    fn normal() {
+        assert_eq!(triangle().normal(Vector::zero(), 0.0), -Vector::one_z());
+    }
+
+    #[test]
+    fn intersect_front() {
+        let t = triangle().intersect(Vector::new(0.25, 0.25, -2.0), Vector::one_z(), 0.0, 0.0);
+        assert_eq!(t, Some(2.0));
+    }
+
+    #[test]
+    fn intersect_back_and_outside() {
+        let tri = triangle();
+        assert_eq!(
+            tri.intersect(Vector::new(0.25, 0.25, 2.0), -Vector::one_z(), 0.0, 0.0),
+            None
+        );
+        assert_eq!(
+            tri.intersect(Vector::new(0.75, 0.75, -2.0), Vector::one_z(), 0.0, 0.0),
+            None
+        );
+    }
+
+    #[test]
+    fn tangents_follow_uv() {
+        // u grows along X, v along Y
+        let (t, b) = triangle().tangents(Vector::zero(), 0.0);
+        assert_eq!(t, Vector::one_x());
+        assert_eq!(b, Vector::one_y());
+    }
+
+    #[test]
+    fn interpolated_uv() {
+        let (u, v) = triangle().uv(Vector::new(0.25, 0.5, 0.0), 0.0).unwrap();
+        assert_delta!(u, 0.25, 1e-12);
+        assert_delta!(v, 0.5, 1e-12);
+    }
+}
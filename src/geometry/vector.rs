@@ -84,6 +84,17 @@ impl Vector {
         Self::new(self.x * rhs.x, self.y * rhs.y, self.z * rhs.z)
     }
 
+    /// Brightness of a linear Rec.709 colour
+    #[inline]
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
+    #[inline]
+    pub fn max_component(&self) -> f64 {
+        self.x.max(self.y).max(self.z)
+    }
+
     #[inline]
     pub fn normalized(self) -> Self {
         self / self.len()
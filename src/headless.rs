@@ -0,0 +1,252 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::camera::Camera;
+use crate::camera_path::CameraPath;
+use crate::framebuffer::{Framebuffer, RenderJob};
+use crate::geometry::scene::Scene;
+use crate::image::exr::{self, Channel, Compression, PixelType};
+use crate::recording::{FrameSink, SequenceFormat};
+use crate::tracer::{PixelAovs, Tracer};
+
+/// Extra EXR layers next to the beauty render
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    /// Distance to the first hit, `Z`
+    Depth,
+    /// World space shading normal, `N.X`, `N.Y`, `N.Z`
+    Normal,
+    /// Surface colour without lighting, `albedo.R`, ...
+    Albedo,
+    /// Index of the shape plus one, zero for the background, `objectId`
+    ObjectId,
+    /// Light reaching the first hit straight from the lights, `direct.R`, ...
+    Direct,
+    /// Light that bounced on the way, `indirect.R`, ...
+    Indirect,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 6] = [
+        Layer::Depth,
+        Layer::Normal,
+        Layer::Albedo,
+        Layer::ObjectId,
+        Layer::Direct,
+        Layer::Indirect,
+    ];
+
+    pub fn parse(name: &str) -> Option<Layer> {
+        match name {
+            "depth" => Some(Layer::Depth),
+            "normal" => Some(Layer::Normal),
+            "albedo" => Some(Layer::Albedo),
+            "id" => Some(Layer::ObjectId),
+            "direct" => Some(Layer::Direct),
+            "indirect" => Some(Layer::Indirect),
+            _ => None,
+        }
+    }
+}
+
+/// Linear OpenEXR output, unclamped
+#[derive(Clone, Debug)]
+pub struct ExrOptions {
+    pub pixel_type: PixelType,
+    pub compression: Compression,
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Clone, Debug)]
+pub enum FrameFormat {
+    /// 8-bit images or video, clamped like the window
+    Sequence(SequenceFormat),
+    Exr(ExrOptions),
+}
+
+/// Where rendered frames go and how they are stored
+pub struct FrameOutput {
+    /// Directory of numbered images, or the file of a video stream
+    pub path: PathBuf,
+    pub format: FrameFormat,
+}
+
+/// Traces a frame and writes it as an EXR file with the requested layers.
+pub fn render_exr(
+    tracer: &Tracer,
+    scene: &Scene,
+    camera: &Camera,
+    (width, height): (u32, u32),
+    time: f64,
+    options: &ExrOptions,
+    path: &Path,
+) -> io::Result<()> {
+    let pixels = RenderJob {
+        tracer,
+        camera,
+        scene,
+        time,
+    }
+    .render_aovs((width, height));
+
+    let channel = |name: &str, value: &dyn Fn(&PixelAovs) -> f64| {
+        Channel::new(name, pixels.iter().map(|p| value(p) as f32).collect())
+    };
+    let mut channels = vec![
+        channel("R", &|p| p.color.x),
+        channel("G", &|p| p.color.y),
+        channel("B", &|p| p.color.z),
+    ];
+    for layer in &options.layers {
+        match layer {
+            Layer::Depth => channels.push(channel("Z", &|p| p.depth)),
+            Layer::Normal => {
+                channels.push(channel("N.X", &|p| p.normal.x));
+                channels.push(channel("N.Y", &|p| p.normal.y));
+                channels.push(channel("N.Z", &|p| p.normal.z));
+            }
+            Layer::Albedo => {
+                channels.push(channel("albedo.R", &|p| p.albedo.x));
+                channels.push(channel("albedo.G", &|p| p.albedo.y));
+                channels.push(channel("albedo.B", &|p| p.albedo.z));
+            }
+            Layer::ObjectId => channels.push(channel("objectId", &|p| {
+                p.object_id.map_or(0.0, |id| id as f64 + 1.0)
+            })),
+            Layer::Direct => {
+                channels.push(channel("direct.R", &|p| p.direct.x));
+                channels.push(channel("direct.G", &|p| p.direct.y));
+                channels.push(channel("direct.B", &|p| p.direct.z));
+            }
+            Layer::Indirect => {
+                channels.push(channel("indirect.R", &|p| p.indirect.x));
+                channels.push(channel("indirect.G", &|p| p.indirect.y));
+                channels.push(channel("indirect.B", &|p| p.indirect.z));
+            }
+        }
+    }
+
+    exr::write(
+        path,
+        (width, height),
+        &channels,
+        options.pixel_type,
+        options.compression,
+    )
+}
+
+/// Renders the camera path sampled at `fps` as numbered images (`frame_00000.png`,
+/// ...) or a video stream, returning the number of frames written. Scene time
+/// follows the path time so animated objects move in step with the camera.
+/// A non-zero `shutter` keeps each frame exposed for that long, blurring both
+/// moving objects and the camera motion along the path. `progress` is told the
+/// number of frames written so far and of all frames after each one.
+pub fn render_camera_path(
+    tracer: &Tracer,
+    scene: &Scene,
+    path: &CameraPath,
+    (width, height): (u32, u32),
+    (fps, shutter): (f64, f64),
+    output: &FrameOutput,
+    mut progress: impl FnMut(usize, usize),
+) -> io::Result<usize> {
+    if path.keyframes().is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Camera path has no keyframes",
+        ));
+    }
+
+    let mut sink = match &output.format {
+        FrameFormat::Sequence(format) => Some(FrameSink::create(
+            &output.path,
+            *format,
+            (width, height),
+            fps,
+        )?),
+        FrameFormat::Exr(_) => {
+            fs::create_dir_all(&output.path)?;
+            None
+        }
+    };
+
+    let start = path.keyframes().first().map_or(0.0, |k| k.time);
+    let frame_count = (path.duration() * fps).floor() as usize + 1;
+    let mut frame_buffer = Framebuffer::new(width, height);
+
+    for frame in 0..frame_count {
+        let time = start + frame as f64 / fps;
+        let ar = width as f64 / height as f64;
+        let mut camera = match path.sample(time, ar) {
+            Some(camera) => camera,
+            None => break,
+        };
+        if shutter > 0.0 {
+            camera.shutter = shutter;
+            camera.end_pose = path
+                .sample(time + shutter, ar)
+                .map(|end| (end.pos, end.orientation()));
+        }
+
+        if let Some(sink) = &mut sink {
+            RenderJob {
+                tracer,
+                camera: &camera,
+                scene,
+                time,
+            }
+            .render(&mut frame_buffer);
+            sink.write_frame((width, height), &frame_buffer.to_rgb())?;
+        } else if let FrameFormat::Exr(options) = &output.format {
+            let file = output.path.join(format!("frame_{:05}.exr", frame));
+            render_exr(
+                tracer,
+                scene,
+                &camera,
+                (width, height),
+                time,
+                options,
+                &file,
+            )?;
+        }
+        progress(frame + 1, frame_count);
+    }
+
+    if let Some(sink) = sink {
+        sink.finish()?;
+    }
+    Ok(frame_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::path::PathBuf;
+
+    use crate::camera_path::CameraPath;
+    use crate::geometry::scene::Scene;
+    use crate::recording::SequenceFormat;
+    use crate::tracer::Tracer;
+
+    use super::{render_camera_path, FrameFormat, FrameOutput};
+
+    #[test]
+    fn empty_path_writes_nothing() {
+        let output = FrameOutput {
+            path: PathBuf::from("frames"),
+            format: FrameFormat::Sequence(SequenceFormat::Png),
+        };
+        let err = render_camera_path(
+            &Tracer::new(),
+            &Scene::empty(),
+            &CameraPath::new(),
+            (4, 4),
+            (30.0, 0.0),
+            &output,
+            |_, _| panic!("no frame to report"),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
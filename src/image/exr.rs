@@ -0,0 +1,305 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::zlib;
+
+/// Scanlines per chunk with ZIP compression, fixed by the format
+const ZIP_LINES: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PixelType {
+    Half,
+    Float,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    None,
+    Zip,
+}
+
+/// One named channel of linear values, rows from top to bottom. Layers are
+/// channels sharing a prefix, such as `albedo.R`, `albedo.G`, `albedo.B`.
+pub struct Channel {
+    pub name: String,
+    pub values: Vec<f32>,
+}
+
+impl Channel {
+    pub fn new(name: &str, values: Vec<f32>) -> Channel {
+        Channel {
+            name: name.to_string(),
+            values,
+        }
+    }
+}
+
+/// Writes the channels as a single part scanline OpenEXR file.
+pub fn write(
+    path: &Path,
+    (width, height): (u32, u32),
+    channels: &[Channel],
+    pixel_type: PixelType,
+    compression: Compression,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(&encode((width, height), channels, pixel_type, compression))?;
+    out.flush()
+}
+
+pub fn encode(
+    (width, height): (u32, u32),
+    channels: &[Channel],
+    pixel_type: PixelType,
+    compression: Compression,
+) -> Vec<u8> {
+    let pixels = width as usize * height as usize;
+    assert!(channels.iter().all(|c| c.values.len() == pixels));
+    // readers expect the channel list in alphabetical order
+    let mut channels: Vec<&Channel> = channels.iter().collect();
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+
+    let mut chlist = Vec::new();
+    for channel in &channels {
+        chlist.extend_from_slice(channel.name.as_bytes());
+        chlist.push(0);
+        let type_id: i32 = match pixel_type {
+            PixelType::Half => 1,
+            PixelType::Float => 2,
+        };
+        chlist.extend_from_slice(&type_id.to_le_bytes());
+        // linear flag and padding, then x and y sampling
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    attribute(&mut out, "channels", "chlist", &chlist);
+
+    let compression_id = match compression {
+        Compression::None => 0,
+        Compression::Zip => 3,
+    };
+    attribute(&mut out, "compression", "compression", &[compression_id]);
+
+    let mut window = Vec::new();
+    for v in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+    attribute(&mut out, "dataWindow", "box2i", &window);
+    attribute(&mut out, "displayWindow", "box2i", &window);
+    attribute(&mut out, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut out, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+    attribute(&mut out, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(&mut out, "screenWindowWidth", "float", &1f32.to_le_bytes());
+    out.push(0);
+
+    let lines_per_chunk = match compression {
+        Compression::None => 1,
+        Compression::Zip => ZIP_LINES,
+    };
+    let chunk_count = (height as usize).div_ceil(lines_per_chunk);
+    let table_pos = out.len();
+    out.resize(table_pos + chunk_count * 8, 0);
+
+    for chunk in 0..chunk_count {
+        let first = chunk * lines_per_chunk;
+        let last = (first + lines_per_chunk).min(height as usize);
+
+        // each line holds every channel in turn
+        let mut raw = Vec::new();
+        for y in first..last {
+            for channel in &channels {
+                let row = &channel.values[y * width as usize..(y + 1) * width as usize];
+                for &v in row {
+                    match pixel_type {
+                        PixelType::Half => raw.extend_from_slice(&to_half(v).to_le_bytes()),
+                        PixelType::Float => raw.extend_from_slice(&v.to_le_bytes()),
+                    }
+                }
+            }
+        }
+        let data = match compression {
+            Compression::None => raw,
+            Compression::Zip => {
+                let packed = zlib::compress(&predict(&raw));
+                // readers take data as stored when it didn't get any smaller
+                if packed.len() < raw.len() {
+                    packed
+                } else {
+                    raw
+                }
+            }
+        };
+
+        let offset = out.len() as u64;
+        out[table_pos + chunk * 8..table_pos + chunk * 8 + 8]
+            .copy_from_slice(&offset.to_le_bytes());
+        out.extend_from_slice(&(first as i32).to_le_bytes());
+        out.extend_from_slice(&(data.len() as i32).to_le_bytes());
+        out.extend_from_slice(&data);
+    }
+
+    out
+}
+
+fn attribute(out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out.extend_from_slice(kind.as_bytes());
+    out.push(0);
+    out.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+/// ZIP preprocessing: bytes split into even and odd halves, then delta coded
+fn predict(raw: &[u8]) -> Vec<u8> {
+    let half = raw.len().div_ceil(2);
+    let mut out = vec![0u8; raw.len()];
+    for (i, &b) in raw.iter().enumerate() {
+        out[if i % 2 == 0 { i / 2 } else { half + i / 2 }] = b;
+    }
+    let mut previous = out.first().copied().unwrap_or(0);
+    for b in out.iter_mut().skip(1) {
+        let current = *b;
+        *b = current.wrapping_sub(previous).wrapping_add(128);
+        previous = current;
+    }
+    out
+}
+
+/// IEEE 754 half precision bits of `v`, rounded to nearest even
+pub fn to_half(v: f32) -> u16 {
+    let bits = v.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // infinity stays infinite, NaN keeps a set mantissa bit
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // subnormal: shift the mantissa with its implicit bit into place
+        let m = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = m >> shift;
+        let rest = m & ((1 << shift) - 1);
+        let midpoint = 1 << (shift - 1);
+        let round = (rest > midpoint || (rest == midpoint && half & 1 == 1)) as u32;
+        return sign | (half + round) as u16;
+    }
+
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1fff;
+    let round = (rest > 0x1000 || (rest == 0x1000 && half & 1 == 1)) as u32;
+    // a carry out of the mantissa bumps the exponent, up to infinity
+    sign | (half + round) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::zlib;
+
+    use super::{encode, predict, to_half, Channel, Compression, PixelType};
+
+    #[test]
+    fn half_conversion() {
+        assert_eq!(to_half(0.0), 0x0000);
+        assert_eq!(to_half(-0.0), 0x8000);
+        assert_eq!(to_half(1.0), 0x3c00);
+        assert_eq!(to_half(-2.0), 0xc000);
+        assert_eq!(to_half(0.5), 0x3800);
+        assert_eq!(to_half(65504.0), 0x7bff);
+        assert_eq!(to_half(1e6), 0x7c00);
+        assert_eq!(to_half(f32::INFINITY), 0x7c00);
+        assert_eq!(to_half(f32::NAN) & 0x7c00, 0x7c00);
+        assert_ne!(to_half(f32::NAN) & 0x3ff, 0);
+        // smallest subnormal and rounding to nearest even
+        assert_eq!(to_half(5.960_464_5e-8), 0x0001);
+        assert_eq!(to_half(1.0 + 1.0 / 2048.0), 0x3c00);
+        assert_eq!(to_half(1.0 + 3.0 / 2048.0), 0x3c02);
+        assert_eq!(to_half(1e-9), 0x0000);
+    }
+
+    fn u32_at(data: &[u8], pos: usize) -> u32 {
+        u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+    }
+
+    /// Offset of the first chunk, from the offset table after the header
+    fn first_chunk(data: &[u8]) -> usize {
+        let attr = data
+            .windows(17)
+            .position(|w| w == b"screenWindowWidth")
+            .unwrap();
+        // name, type "float", size, value, then the header terminator
+        let table = attr + 18 + 6 + 4 + 4 + 1;
+        u32_at(data, table) as usize
+    }
+
+    #[test]
+    fn uncompressed_float() {
+        let channels = [
+            Channel::new("G", vec![0.5, 1.5]),
+            Channel::new("B", vec![2.0, 3.0]),
+        ];
+        let data = encode((2, 1), &channels, PixelType::Float, Compression::None);
+        assert_eq!(&data[..4], &[0x76, 0x2f, 0x31, 0x01]);
+
+        // channels come out sorted, B before G
+        let b = data.windows(2).position(|w| w == b"B\0").unwrap();
+        let g = data.windows(2).position(|w| w == b"G\0").unwrap();
+        assert!(b < g);
+
+        let chunk = first_chunk(&data);
+        assert_eq!(u32_at(&data, chunk), 0);
+        assert_eq!(u32_at(&data, chunk + 4), 16);
+        let floats: Vec<f32> = (0..4)
+            .map(|i| f32::from_bits(u32_at(&data, chunk + 8 + i * 4)))
+            .collect();
+        assert_eq!(floats, vec![2.0, 3.0, 0.5, 1.5]);
+    }
+
+    #[test]
+    fn zip_half() {
+        let width = 64;
+        let values: Vec<f32> = (0..width * 20).map(|i| (i % width) as f32 / 8.0).collect();
+        let channels = [Channel::new("Y", values.clone())];
+        let data = encode(
+            (width as u32, 20),
+            &channels,
+            PixelType::Half,
+            Compression::Zip,
+        );
+
+        let chunk = first_chunk(&data);
+        assert_eq!(u32_at(&data, chunk), 0);
+        let size = u32_at(&data, chunk + 4) as usize;
+        let packed = &data[chunk + 8..chunk + 8 + size];
+        let raw: Vec<u8> = values[..width * 16]
+            .iter()
+            .flat_map(|&v| to_half(v).to_le_bytes())
+            .collect();
+        assert!(size < raw.len());
+        assert_eq!(zlib::decompress(packed).unwrap(), predict(&raw));
+
+        // the last chunk starts at line 16 and holds the remaining four lines
+        let second = chunk + 8 + size;
+        assert_eq!(u32_at(&data, second), 16);
+    }
+
+    #[test]
+    fn predictor() {
+        assert_eq!(predict(&[1, 10, 2, 20, 3]), vec![1, 129, 129, 135, 138]);
+    }
+}
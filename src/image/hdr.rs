@@ -0,0 +1,190 @@
+use std::io;
+
+use super::Image;
+use crate::geometry::vector::Vector;
+
+/// Decodes Radiance RGBE (`.hdr`) images, flat or run-length encoded scanlines,
+/// in the standard top-to-bottom (`-Y h +X w`) orientation.
+pub fn decode(data: &[u8]) -> io::Result<Image> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("HDR: {}", msg));
+
+    let mut pos = 0;
+    let mut next_line = || -> Option<&[u8]> {
+        let start = pos;
+        let len = data[start..].iter().position(|&b| b == b'\n')?;
+        pos = start + len + 1;
+        Some(&data[start..start + len])
+    };
+
+    let magic = next_line().ok_or_else(|| invalid("truncated header"))?;
+    if !magic.starts_with(b"#?") {
+        return Err(invalid("not a Radiance file"));
+    }
+    // header variables run until an empty line
+    loop {
+        let line = next_line().ok_or_else(|| invalid("truncated header"))?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid("only RGBE pixels are supported"));
+        }
+    }
+
+    let resolution = next_line().ok_or_else(|| invalid("missing resolution"))?;
+    let resolution = std::str::from_utf8(resolution).map_err(|_| invalid("bad resolution"))?;
+    let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => (
+            w.parse::<u32>().map_err(|_| invalid("bad resolution"))?,
+            h.parse::<u32>().map_err(|_| invalid("bad resolution"))?,
+        ),
+        _ => return Err(invalid("only -Y h +X w orientation is supported")),
+    };
+
+    let mut image = Image::new(width, height);
+    let mut scanline = vec![[0u8; 4]; width as usize];
+    for y in 0..height {
+        pos = read_scanline(data, pos, &mut scanline).ok_or_else(|| invalid("truncated data"))?;
+        for (x, rgbe) in scanline.iter().enumerate() {
+            image.set(x as u32, y, from_rgbe(*rgbe));
+        }
+    }
+    Ok(image)
+}
+
+/// Reads one scanline starting at `pos`, returning the position after it
+fn read_scanline(data: &[u8], mut pos: usize, scanline: &mut [[u8; 4]]) -> Option<usize> {
+    let width = scanline.len();
+    let header = data.get(pos..pos + 4)?;
+    let rle = (8..0x8000).contains(&width)
+        && header[0] == 2
+        && header[1] == 2
+        && ((header[2] as usize) << 8 | header[3] as usize) == width;
+
+    if !rle {
+        for pixel in scanline.iter_mut() {
+            pixel.copy_from_slice(data.get(pos..pos + 4)?);
+            pos += 4;
+        }
+        return Some(pos);
+    }
+
+    // each channel separately, as runs (count > 128) or literal spans
+    pos += 4;
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *data.get(pos)? as usize;
+            pos += 1;
+            if count > 128 {
+                let count = count - 128;
+                let value = *data.get(pos)?;
+                pos += 1;
+                for pixel in scanline.get_mut(x..x + count)? {
+                    pixel[channel] = value;
+                }
+                x += count;
+            } else {
+                if count == 0 {
+                    return None;
+                }
+                for (pixel, value) in scanline
+                    .get_mut(x..x + count)?
+                    .iter_mut()
+                    .zip(data.get(pos..pos + count)?)
+                {
+                    pixel[channel] = *value;
+                }
+                pos += count;
+                x += count;
+            }
+        }
+    }
+    Some(pos)
+}
+
+#[inline]
+fn from_rgbe([r, g, b, e]: [u8; 4]) -> Vector {
+    if e == 0 {
+        return Vector::zero();
+    }
+    let scale = 2f64.powi(e as i32 - 136);
+    Vector::new(r as f64, g as f64, b as f64) * scale
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use crate::geometry::vector::Vector;
+    use crate::image::Image;
+
+    use super::decode;
+
+    fn to_rgbe(color: Vector) -> [u8; 4] {
+        let max = color.x.max(color.y).max(color.z);
+        if max < 1e-32 {
+            return [0; 4];
+        }
+        let exponent = max.log2().floor() as i32 + 1;
+        let scale = 256.0 / 2f64.powi(exponent);
+        [
+            (color.x * scale) as u8,
+            (color.y * scale) as u8,
+            (color.z * scale) as u8,
+            (exponent + 128) as u8,
+        ]
+    }
+
+    /// Writes flat (not run-length encoded) RGBE pixels
+    fn encode<W: Write>(out: &mut W, image: &Image) -> io::Result<()> {
+        write!(
+            out,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            image.height, image.width
+        )?;
+        for pixel in &image.pixels {
+            out.write_all(&to_rgbe(*pixel))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut image = Image::new(3, 2);
+        image.set(0, 0, Vector::new(1.0, 0.5, 0.25));
+        image.set(2, 1, Vector::new(1000.0, 20.0, 0.0));
+        let mut data = Vec::new();
+        encode(&mut data, &image).unwrap();
+
+        let decoded = decode(&data).unwrap();
+        assert_eq!((decoded.width, decoded.height), (3, 2));
+        assert_eq!(decoded.get(0, 0), Vector::new(1.0, 0.5, 0.25));
+        assert_eq!(decoded.get(1, 0), Vector::zero());
+        // 8-bit mantissas keep about two significant digits
+        let p = decoded.get(2, 1);
+        assert!((p.x - 1000.0).abs() < 4.0 && (p.y - 20.0).abs() < 4.0);
+    }
+
+    #[test]
+    fn run_length() {
+        let mut data = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // red: run of 8; green: 8 literals; blue: run of 8 zeros; exponent: run of 8
+        data.extend_from_slice(&[128 + 8, 128]);
+        data.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        data.extend_from_slice(&[128 + 8, 0]);
+        data.extend_from_slice(&[128 + 8, 129]);
+
+        let image = decode(&data).unwrap();
+        assert_eq!(image.get(0, 0), Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(image.get(3, 0), Vector::new(1.0, 0.375, 0.0));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(decode(b"P6\n").is_err());
+        assert!(decode(b"#?RADIANCE\n\n+Y 1 +X 1\n\0\0\0\0").is_err());
+        assert!(decode(b"#?RADIANCE\n\n-Y 2 +X 1\n\0\0\0\0").is_err());
+    }
+}
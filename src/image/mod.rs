@@ -0,0 +1,68 @@
+pub mod exr;
+pub mod hdr;
+pub mod pfm;
+pub mod png;
+pub mod ppm;
+pub mod y4m;
+pub mod zlib;
+
+use std::io;
+use std::path::Path;
+
+use crate::geometry::vector::Vector;
+
+/// RGB image with floating point channels, 8-bit files map onto 0..1 and
+/// high dynamic range files keep their values.
+#[derive(Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Vector>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![Vector::zero(); width as usize * height as usize],
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, x: u32, y: u32) -> Vector {
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+
+    #[inline]
+    pub fn set(&mut self, x: u32, y: u32, color: Vector) {
+        self.pixels[y as usize * self.width as usize + x as usize] = color;
+    }
+
+    /// Loads a PNG, PPM/PNM, Radiance HDR or PFM file, picking the decoder by extension.
+    pub fn load(path: &Path) -> io::Result<Image> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let data = std::fs::read(path)?;
+        let image = match ext.as_deref() {
+            Some("png") => png::decode(&data),
+            Some("ppm") | Some("pnm") => ppm::decode(&data),
+            Some("hdr") => hdr::decode(&data),
+            Some("pfm") => pfm::decode(&data),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported image format: {}", path.display()),
+            )),
+        }?;
+        // textures and environments sample at least one pixel
+        if image.width == 0 || image.height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Empty image: {}", path.display()),
+            ));
+        }
+        Ok(image)
+    }
+}
@@ -0,0 +1,98 @@
+use std::io;
+
+use super::Image;
+use crate::geometry::vector::Vector;
+
+/// Decodes portable float maps: colour (`PF`) or greyscale (`Pf`) 32-bit
+/// floats, little endian when the scale is negative, rows stored bottom to top.
+pub fn decode(data: &[u8]) -> io::Result<Image> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("PFM: {}", msg));
+
+    // header: magic, width, height, scale - separated by whitespace, then one whitespace byte
+    let mut pos = 0;
+    let mut tokens = Vec::new();
+    while tokens.len() < 4 {
+        while pos < data.len() && data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let start = pos;
+        while pos < data.len() && !data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(invalid("truncated header"));
+        }
+        tokens.push(std::str::from_utf8(&data[start..pos]).map_err(|_| invalid("bad header"))?);
+    }
+    pos += 1;
+
+    let channels = match tokens[0] {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid("not a float map")),
+    };
+    let parse = |t: &str| t.parse::<u32>().map_err(|_| invalid("bad header"));
+    let (width, height) = (parse(tokens[1])?, parse(tokens[2])?);
+    let scale = tokens[3]
+        .parse::<f64>()
+        .map_err(|_| invalid("bad header"))?;
+    let little_endian = scale < 0.0;
+
+    let count = width as usize * height as usize * channels;
+    let bytes = data
+        .get(pos..pos + count * 4)
+        .ok_or_else(|| invalid("truncated data"))?;
+    let values: Vec<f64> = bytes
+        .chunks_exact(4)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            (if little_endian {
+                f32::from_le_bytes(b)
+            } else {
+                f32::from_be_bytes(b)
+            }) as f64
+        })
+        .collect();
+
+    let mut image = Image::new(width, height);
+    for (i, pixel) in values.chunks_exact(channels).enumerate() {
+        let (x, row) = (i as u32 % width, i as u32 / width);
+        let color = match pixel {
+            [r, g, b] => Vector::new(*r, *g, *b),
+            [v] => Vector::one() * *v,
+            _ => unreachable!(),
+        };
+        image.set(x, height - 1 - row, color);
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::vector::Vector;
+
+    use super::decode;
+
+    #[test]
+    fn colour_little_endian() {
+        let mut data = b"PF\n1 2\n-1.0\n".to_vec();
+        for v in [1.0f32, 2.0, 3.0, 0.5, 0.25, 100.0] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        let image = decode(&data).unwrap();
+        // first stored row is the bottom one
+        assert_eq!(image.get(0, 1), Vector::new(1.0, 2.0, 3.0));
+        assert_eq!(image.get(0, 0), Vector::new(0.5, 0.25, 100.0));
+    }
+
+    #[test]
+    fn grey_big_endian() {
+        let mut data = b"Pf 2 1 1.0\n".to_vec();
+        for v in [4.0f32, 8.0] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        let image = decode(&data).unwrap();
+        assert_eq!(image.get(1, 0), Vector::one() * 8.0);
+        assert!(decode(b"PF\n1 1\n-1.0\n\0\0").is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::{zlib, Image};
+use crate::geometry::vector::Vector;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("PNG: {}", msg))
+}
+
+/// Decodes a non-interlaced PNG of any colour type and bit depth; alpha is dropped.
+pub fn decode(data: &[u8]) -> io::Result<Image> {
+    if data.len() < 8 || data[..8] != SIGNATURE {
+        return Err(invalid("bad signature"));
+    }
+
+    let mut header = None;
+    let mut palette: Vec<Vector> = Vec::new();
+    let mut idat = Vec::new();
+
+    let mut pos = 8;
+    while pos + 8 <= data.len() {
+        let len = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
+        let kind = &data[pos + 4..pos + 8];
+        let start = pos + 8;
+        let end = start + len as usize;
+        if end + 4 > data.len() {
+            return Err(invalid("truncated chunk"));
+        }
+        let body = &data[start..end];
+
+        match kind {
+            b"IHDR" => {
+                if body.len() != 13 {
+                    return Err(invalid("bad header"));
+                }
+                let width = u32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+                let height = u32::from_be_bytes([body[4], body[5], body[6], body[7]]);
+                if width == 0 || height == 0 {
+                    return Err(invalid("empty image"));
+                }
+                let (depth, color_type) = (body[8], body[9]);
+                let depths: &[u8] = match color_type {
+                    0 => &[1, 2, 4, 8, 16],
+                    3 => &[1, 2, 4, 8],
+                    2 | 4 | 6 => &[8, 16],
+                    _ => return Err(invalid("unknown colour type")),
+                };
+                if !depths.contains(&depth) {
+                    return Err(invalid("unsupported bit depth"));
+                }
+                if body[12] != 0 {
+                    return Err(invalid("interlaced images are not supported"));
+                }
+                header = Some((width, height, depth, color_type));
+            }
+            b"PLTE" => {
+                palette = body
+                    .chunks_exact(3)
+                    .map(|c| Vector::new(c[0] as f64, c[1] as f64, c[2] as f64) / 255.0)
+                    .collect();
+            }
+            b"IDAT" => idat.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+        pos = end + 4;
+    }
+
+    let (width, height, depth, color_type) = header.ok_or_else(|| invalid("missing header"))?;
+    let channels = match color_type {
+        0 | 3 => 1,
+        2 => 3,
+        4 => 2,
+        _ => 4,
+    };
+
+    let bits_per_pixel = channels * depth as usize;
+    let bpp = (bits_per_pixel / 8).max(1);
+    let too_large = || invalid("image too large");
+    let row_len = (width as usize)
+        .checked_mul(bits_per_pixel)
+        .ok_or_else(too_large)?
+        .div_ceil(8);
+    let data_len = (row_len + 1)
+        .checked_mul(height as usize)
+        .ok_or_else(too_large)?;
+
+    let raw = zlib::decompress(&idat)?;
+    if raw.len() < data_len {
+        return Err(invalid("not enough image data"));
+    }
+
+    let max = ((1u32 << depth) - 1) as f64;
+    let mut image = Image::new(width, height);
+    let mut prev = vec![0u8; row_len];
+    let mut row = vec![0u8; row_len];
+
+    for y in 0..height as usize {
+        let line = &raw[y * (row_len + 1)..(y + 1) * (row_len + 1)];
+        unfilter(line[0], &line[1..], &prev, &mut row, bpp)?;
+
+        for x in 0..width as usize {
+            let sample = |c: usize| -> u32 {
+                let bit = (x * channels + c) * depth as usize;
+                match depth {
+                    16 => u16::from_be_bytes([row[bit / 8], row[bit / 8 + 1]]) as u32,
+                    8 => row[bit / 8] as u32,
+                    _ => (row[bit / 8] as u32 >> (8 - depth as usize - bit % 8)) & max as u32,
+                }
+            };
+
+            let color = match color_type {
+                3 => *palette
+                    .get(sample(0) as usize)
+                    .ok_or_else(|| invalid("palette index out of range"))?,
+                0 | 4 => Vector::one() * (sample(0) as f64 / max),
+                _ => Vector::new(sample(0) as f64, sample(1) as f64, sample(2) as f64) / max,
+            };
+            image.set(x as u32, y as u32, color);
+        }
+
+        std::mem::swap(&mut prev, &mut row);
+    }
+
+    Ok(image)
+}
+
+/// Writes tightly packed 8-bit RGB pixels as a PNG file.
+pub fn write(path: &Path, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(&encode(width, height, rgb))?;
+    out.flush()
+}
+
+/// Encodes 8-bit RGB as a PNG, every line Sub filtered.
+pub fn encode(width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+    assert_eq!(rgb.len(), width as usize * height as usize * 3);
+
+    let row_len = width as usize * 3;
+    let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+    for line in rgb.chunks_exact(row_len.max(1)).take(height as usize) {
+        raw.push(1);
+        for i in 0..line.len() {
+            let left = if i >= 3 { line[i - 3] } else { 0 };
+            raw.push(line[i].wrapping_sub(left));
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per sample, RGB, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut out = SIGNATURE.to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &zlib::compress(&raw));
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(body);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// CRC-32 as used by PNG chunks (reflected, polynomial 0xedb88320)
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 == 1 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+    !data.iter().fold(!0u32, |c, &b| {
+        table[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
+
+fn unfilter(filter: u8, line: &[u8], prev: &[u8], out: &mut [u8], bpp: usize) -> io::Result<()> {
+    for i in 0..line.len() {
+        let a = if i >= bpp { out[i - bpp] } else { 0 };
+        let b = prev[i];
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+        let predictor = match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            4 => paeth(a, b, c),
+            _ => return Err(invalid("unknown filter type")),
+        };
+        out[i] = line[i].wrapping_add(predictor);
+    }
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::vector::Vector;
+
+    use super::{crc32, decode, encode};
+
+    fn rgb(r: u8, g: u8, b: u8) -> Vector {
+        Vector::new(r as f64, g as f64, b as f64) / 255.0
+    }
+
+    #[test]
+    fn rgb_with_filters() {
+        // 3x2 RGB, first row Sub filtered, second row Paeth filtered
+        let data = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02, 0x08, 0x02, 0x00, 0x00,
+            0x00, 0x12, 0x16, 0xf1, 0x4d, 0x00, 0x00, 0x00, 0x1b, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0x9c, 0x63, 0xe4, 0x12, 0x91, 0x03, 0x82, 0x05, 0x46, 0x47, 0x58, 0x58, 0x59, 0x59,
+            0x6f, 0x9c, 0x75, 0xb1, 0x64, 0x3e, 0x0e, 0x00, 0x28, 0xaf, 0x05, 0x2d, 0x46, 0x0e,
+            0xef, 0x5c, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+        let image = decode(&data).unwrap();
+
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.get(0, 0), rgb(10, 20, 30));
+        assert_eq!(image.get(2, 0), rgb(200, 100, 0));
+        assert_eq!(image.get(1, 1), rgb(0, 255, 128));
+        assert_eq!(image.get(2, 1), rgb(1, 2, 3));
+    }
+
+    #[test]
+    fn palette_1bit() {
+        let data = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x01, 0x03, 0x00, 0x00,
+            0x00, 0xce, 0xec, 0xed, 0xc9, 0x00, 0x00, 0x00, 0x06, 0x50, 0x4c, 0x54, 0x45, 0xff,
+            0x00, 0x00, 0x00, 0x00, 0xff, 0x6c, 0xa1, 0xfd, 0x8e, 0x00, 0x00, 0x00, 0x0a, 0x49,
+            0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x70, 0x00, 0x00, 0x00, 0x42, 0x00, 0x41, 0x29,
+            0x37, 0xf4, 0xef, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60,
+            0x82,
+        ];
+        let image = decode(&data).unwrap();
+
+        assert_eq!(image.get(0, 0), rgb(255, 0, 0));
+        assert_eq!(image.get(1, 0), rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn round_trip() {
+        let pixels = [
+            10, 20, 30, 200, 100, 0, 5, 5, 5, 0, 255, 128, 1, 2, 3, 255, 255, 255,
+        ];
+        let data = encode(3, 2, &pixels);
+        let image = decode(&data).unwrap();
+
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.get(0, 0), rgb(10, 20, 30));
+        assert_eq!(image.get(1, 0), rgb(200, 100, 0));
+        assert_eq!(image.get(0, 1), rgb(0, 255, 128));
+        assert_eq!(image.get(2, 1), rgb(255, 255, 255));
+        // the header chunk carries the checksum every reader verifies
+        assert_eq!(&data[29..33], &[0x12, 0x16, 0xf1, 0x4d]);
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn invalid_header() {
+        let data = encode(3, 2, &[0; 18]);
+        // width, then bit depth and colour type, inside the header chunk
+        let with = |offset: usize, bytes: &[u8]| {
+            let mut data = data.clone();
+            data[offset..offset + bytes.len()].copy_from_slice(bytes);
+            decode(&data)
+        };
+        assert!(with(16, &[0, 0, 0, 0]).is_err());
+        assert!(with(24, &[4, 2]).is_err());
+        assert!(with(24, &[16, 3]).is_err());
+        assert!(with(24, &[8, 5]).is_err());
+        assert!(with(24, &[8, 2]).is_ok());
+    }
+
+    #[test]
+    fn bad_signature() {
+        assert!(decode(b"not a png file").is_err());
+    }
+}
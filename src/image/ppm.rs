@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::Image;
+use crate::geometry::vector::Vector;
+
+/// Writes tightly packed 8-bit RGB pixels as a binary (P6) portable pixmap.
+pub fn write(path: &Path, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    assert_eq!(rgb.len(), width as usize * height as usize * 3);
+
+    let mut out = BufWriter::new(File::create(path)?);
+    encode(&mut out, width, height, rgb)?;
+    out.flush()
+}
+
+pub fn encode<W: Write>(out: &mut W, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", width, height)?;
+    out.write_all(rgb)
+}
+
+/// Decodes ASCII (P3) and binary (P6) pixmaps with any maximum value.
+pub fn decode(data: &[u8]) -> io::Result<Image> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("PPM: {}", msg));
+
+    // header: magic, width, height, maxval - separated by whitespace, `#` starts a comment
+    let mut pos = 0;
+    let mut tokens = Vec::new();
+    while tokens.len() < 4 {
+        while pos < data.len() && (data[pos].is_ascii_whitespace() || data[pos] == b'#') {
+            if data[pos] == b'#' {
+                while pos < data.len() && data[pos] != b'\n' {
+                    pos += 1;
+                }
+            } else {
+                pos += 1;
+            }
+        }
+        let start = pos;
+        while pos < data.len() && !data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(invalid("truncated header"));
+        }
+        tokens.push(std::str::from_utf8(&data[start..pos]).map_err(|_| invalid("bad header"))?);
+    }
+
+    let binary = match tokens[0] {
+        "P6" => true,
+        "P3" => false,
+        _ => return Err(invalid("only P3 and P6 pixmaps are supported")),
+    };
+    let parse = |t: &str| t.parse::<u32>().map_err(|_| invalid("bad header"));
+    let (width, height, max) = (parse(tokens[1])?, parse(tokens[2])?, parse(tokens[3])?);
+    if max == 0 || max > 65535 {
+        return Err(invalid("bad maximum value"));
+    }
+
+    let count = width as usize * height as usize * 3;
+    let samples: Vec<u32> = if binary {
+        // exactly one whitespace byte separates the header from the data
+        let body = &data[(pos + 1).min(data.len())..];
+        if max < 256 {
+            body.iter().take(count).map(|&b| b as u32).collect()
+        } else {
+            body.chunks_exact(2)
+                .take(count)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]) as u32)
+                .collect()
+        }
+    } else {
+        std::str::from_utf8(&data[pos..])
+            .map_err(|_| invalid("bad pixel data"))?
+            .split_whitespace()
+            .take(count)
+            .map(|t| t.parse::<u32>().map_err(|_| invalid("bad pixel data")))
+            .collect::<io::Result<_>>()?
+    };
+    if samples.len() != count {
+        return Err(invalid("not enough pixel data"));
+    }
+
+    let mut image = Image::new(width, height);
+    for (pixel, rgb) in image.pixels.iter_mut().zip(samples.chunks_exact(3)) {
+        *pixel = Vector::new(rgb[0] as f64, rgb[1] as f64, rgb[2] as f64) / max as f64;
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::vector::Vector;
+
+    use super::{decode, encode};
+
+    #[test]
+    fn header_and_data() {
+        let mut out = Vec::new();
+        encode(&mut out, 2, 1, &[1, 2, 3, 4, 5, 6]).unwrap();
+
+        let header = b"P6\n2 1\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(&out[header.len()..], &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn decode_binary() {
+        let mut data = Vec::new();
+        encode(&mut data, 2, 1, &[255, 0, 0, 0, 51, 255]).unwrap();
+        let image = decode(&data).unwrap();
+
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.get(0, 0), Vector::one_x());
+        assert_eq!(image.get(1, 0), Vector::new(0.0, 0.2, 1.0));
+    }
+
+    #[test]
+    fn decode_ascii() {
+        let image = decode(b"P3\n# comment\n1 2\n10\n10 0 5\n0 10 0\n").unwrap();
+        assert_eq!(image.get(0, 0), Vector::new(1.0, 0.0, 0.5));
+        assert_eq!(image.get(0, 1), Vector::one_y());
+    }
+
+    #[test]
+    fn decode_truncated() {
+        assert!(decode(b"P6\n4 4\n255\n\x01\x02").is_err());
+        assert!(decode(b"P5\n1 1\n255\n\x01").is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use std::io::{self, Write};
+
+/// RGB to YCbCr weights
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Matrix {
+    Bt601,
+    Bt709,
+}
+
+/// Code values luma and chroma use: studio swing (16 - 235, 16 - 240) or all 256
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Range {
+    Limited,
+    Full,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Y4mOptions {
+    pub matrix: Matrix,
+    pub range: Range,
+}
+
+/// Uncompressed YUV4MPEG2 stream, 4:2:0 with chroma sited between the luma samples.
+pub struct Y4mWriter<W: Write> {
+    out: W,
+    width: u32,
+    height: u32,
+    options: Y4mOptions,
+}
+
+impl Default for Y4mOptions {
+    fn default() -> Self {
+        Y4mOptions {
+            matrix: Matrix::Bt709,
+            range: Range::Limited,
+        }
+    }
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// Writes the stream header
+    pub fn new(
+        mut out: W,
+        width: u32,
+        height: u32,
+        fps: f64,
+        options: Y4mOptions,
+    ) -> io::Result<Self> {
+        let (num, den) = frame_rate_ratio(fps);
+        let range = match options.range {
+            Range::Limited => "LIMITED",
+            Range::Full => "FULL",
+        };
+        writeln!(
+            out,
+            "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C420jpeg XCOLORRANGE={}",
+            width, height, num, den, range
+        )?;
+        Ok(Y4mWriter {
+            out,
+            width,
+            height,
+            options,
+        })
+    }
+
+    /// Appends a frame of tightly packed 8-bit RGB pixels.
+    pub fn write_frame(&mut self, rgb: &[u8]) -> io::Result<()> {
+        let (w, h) = (self.width as usize, self.height as usize);
+        assert_eq!(rgb.len(), w * h * 3);
+
+        let mut luma = Vec::with_capacity(w * h);
+        let mut chroma = Vec::with_capacity(w * h);
+        for p in rgb.chunks_exact(3) {
+            let (y, cb, cr) = to_ycbcr([p[0], p[1], p[2]], self.options.matrix);
+            luma.push(y);
+            chroma.push((cb, cr));
+        }
+
+        // chroma averaged over 2x2 blocks, odd edges use the pixels they have
+        let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+        let mut cb_plane = Vec::with_capacity(cw * ch);
+        let mut cr_plane = Vec::with_capacity(cw * ch);
+        for cy in 0..ch {
+            for cx in 0..cw {
+                let (mut cb, mut cr, mut n) = (0.0, 0.0, 0.0);
+                for y in cy * 2..(cy * 2 + 2).min(h) {
+                    for x in cx * 2..(cx * 2 + 2).min(w) {
+                        cb += chroma[y * w + x].0;
+                        cr += chroma[y * w + x].1;
+                        n += 1.0;
+                    }
+                }
+                cb_plane.push(self.chroma_code(cb / n));
+                cr_plane.push(self.chroma_code(cr / n));
+            }
+        }
+
+        let y_plane: Vec<u8> = luma.iter().map(|&y| self.luma_code(y)).collect();
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&y_plane)?;
+        self.out.write_all(&cb_plane)?;
+        self.out.write_all(&cr_plane)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn luma_code(&self, y: f64) -> u8 {
+        let code = match self.options.range {
+            Range::Limited => 16.0 + 219.0 * y,
+            Range::Full => 255.0 * y,
+        };
+        code.round().clamp(0.0, 255.0) as u8
+    }
+
+    fn chroma_code(&self, c: f64) -> u8 {
+        let code = match self.options.range {
+            Range::Limited => 128.0 + 224.0 * c,
+            Range::Full => 128.0 + 255.0 * c,
+        };
+        code.round().clamp(0.0, 255.0) as u8
+    }
+}
+
+/// Luma in 0..1 and colour differences in -0.5..0.5 of a gamma encoded pixel
+fn to_ycbcr(rgb: [u8; 3], matrix: Matrix) -> (f64, f64, f64) {
+    let (kr, kb) = match matrix {
+        Matrix::Bt601 => (0.299, 0.114),
+        Matrix::Bt709 => (0.2126, 0.0722),
+    };
+    let [r, g, b] = rgb.map(|c| c as f64 / 255.0);
+    let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+    (
+        y,
+        (b - y) / (2.0 * (1.0 - kb)),
+        (r - y) / (2.0 * (1.0 - kr)),
+    )
+}
+
+/// Frame rate as the ratio the header wants, recognising the NTSC rates
+pub fn frame_rate_ratio(fps: f64) -> (u32, u32) {
+    let whole = fps.round();
+    if (fps - whole).abs() < 1e-6 {
+        return (whole as u32, 1);
+    }
+    let ntsc = (fps * 1.001).round();
+    if (fps - ntsc / 1.001).abs() < 1e-3 {
+        return (ntsc as u32 * 1000, 1001);
+    }
+    ((fps * 1000.0).round() as u32, 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame_rate_ratio, Matrix, Range, Y4mOptions, Y4mWriter};
+
+    fn encode(width: u32, height: u32, rgb: &[u8], matrix: Matrix, range: Range) -> Vec<u8> {
+        let options = Y4mOptions { matrix, range };
+        let mut writer = Y4mWriter::new(Vec::new(), width, height, 30.0, options).unwrap();
+        writer.write_frame(rgb).unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn header_and_planes() {
+        // 3x1 frame: chroma planes are 2x1
+        let data = encode(3, 1, &[255; 9], Matrix::Bt709, Range::Limited);
+        let header = b"YUV4MPEG2 W3 H1 F30:1 Ip A1:1 C420jpeg XCOLORRANGE=LIMITED\nFRAME\n";
+        assert_eq!(&data[..header.len()], header);
+        assert_eq!(&data[header.len()..], &[235, 235, 235, 128, 128, 128, 128]);
+    }
+
+    #[test]
+    fn bt709_colours() {
+        let data = encode(1, 1, &[255, 0, 0], Matrix::Bt709, Range::Limited);
+        assert_eq!(&data[data.len() - 3..], &[63, 102, 240]);
+        let data = encode(1, 1, &[0, 0, 0], Matrix::Bt709, Range::Full);
+        assert_eq!(&data[data.len() - 3..], &[0, 128, 128]);
+        // the matrices weigh green differently
+        let bt601 = encode(1, 1, &[0, 255, 0], Matrix::Bt601, Range::Full);
+        let bt709 = encode(1, 1, &[0, 255, 0], Matrix::Bt709, Range::Full);
+        assert_eq!(bt601[bt601.len() - 3], 150);
+        assert_eq!(bt709[bt709.len() - 3], 182);
+    }
+
+    #[test]
+    fn chroma_is_averaged() {
+        // red and blue side by side share one chroma sample
+        let data = encode(2, 1, &[255, 0, 0, 0, 0, 255], Matrix::Bt709, Range::Full);
+        let planes = &data[data.len() - 4..];
+        assert_eq!(planes, &[54, 18, 177, 186]);
+    }
+
+    #[test]
+    fn frame_rates() {
+        assert_eq!(frame_rate_ratio(30.0), (30, 1));
+        assert_eq!(frame_rate_ratio(29.97), (30000, 1001));
+        assert_eq!(frame_rate_ratio(23.976), (24000, 1001));
+        assert_eq!(frame_rate_ratio(12.5), (12500, 1000));
+    }
+}
@@ -0,0 +1,460 @@
+use std::io;
+
+/// Decompresses a zlib stream (RFC 1950) holding DEFLATE data (RFC 1951).
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    if data.len() < 6 {
+        return Err(invalid("zlib stream is too short"));
+    }
+    let (cmf, flg) = (data[0], data[1]);
+    if cmf & 0x0f != 8 || !((cmf as u16) << 8 | flg as u16).is_multiple_of(31) {
+        return Err(invalid("not a zlib deflate stream"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(invalid("preset zlib dictionaries are not supported"));
+    }
+
+    let out = inflate(&data[2..])?;
+
+    let tail = &data[data.len() - 4..];
+    let expected = u32::from_be_bytes([tail[0], tail[1], tail[2], tail[3]]);
+    if adler32(&out) != expected {
+        return Err(invalid("zlib checksum mismatch"));
+    }
+    Ok(out)
+}
+
+/// Compresses `data` into a zlib stream, LZ77 matches coded with the fixed
+/// Huffman tables: far from the best ratio but simple and quick.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter {
+        out: vec![0x78, 0x01],
+        acc: 0,
+        count: 0,
+    };
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    // most recent position of every 3 byte hash, and the one before each position
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW];
+    let hash = |p: usize| {
+        let v = (data[p] as u32) << 16 | (data[p + 1] as u32) << 8 | data[p + 2] as u32;
+        (v.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+    };
+    let insert = |p: usize, head: &mut [usize], prev: &mut [usize]| {
+        if p + 2 < data.len() {
+            let h = hash(p);
+            prev[p % WINDOW] = head[h];
+            head[h] = p;
+        }
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let mut best = (0, 0);
+        if pos + 2 < data.len() {
+            let max_len = (data.len() - pos).min(258);
+            let mut candidate = head[hash(pos)];
+            for _ in 0..MAX_CHAIN {
+                if candidate == usize::MAX || pos - candidate > WINDOW - 1 {
+                    break;
+                }
+                let len = (0..max_len)
+                    .take_while(|&i| data[candidate + i] == data[pos + i])
+                    .count();
+                if len > best.0 {
+                    best = (len, pos - candidate);
+                    if len == max_len {
+                        break;
+                    }
+                }
+                let next = prev[candidate % WINDOW];
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+            }
+        }
+
+        if best.0 >= 3 {
+            let (len, dist) = best;
+            let li = LENGTH_BASE
+                .iter()
+                .rposition(|&b| b as usize <= len)
+                .unwrap();
+            writer.fixed_literal(257 + li as u16);
+            writer.bits(
+                (len - LENGTH_BASE[li] as usize) as u32,
+                LENGTH_EXTRA[li] as u32,
+            );
+            let di = DIST_BASE.iter().rposition(|&b| b as usize <= dist).unwrap();
+            writer.reversed(di as u32, 5);
+            writer.bits(
+                (dist - DIST_BASE[di] as usize) as u32,
+                DIST_EXTRA[di] as u32,
+            );
+            for p in pos..pos + len {
+                insert(p, &mut head, &mut prev);
+            }
+            pos += len;
+        } else {
+            writer.fixed_literal(data[pos] as u16);
+            insert(pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+    writer.fixed_literal(256);
+
+    let mut out = writer.finish();
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn bits(&mut self, count: u32) -> io::Result<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| invalid("unexpected end of deflate data"))?;
+            value |= ((byte as u32 >> self.bit) & 1) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+/// Packs bits into bytes least significant first, as DEFLATE stores them
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        self.acc |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Huffman codes go out most significant bit first
+    fn reversed(&mut self, code: u32, len: u32) {
+        self.bits(code.reverse_bits() >> (32 - len), len);
+    }
+
+    fn fixed_literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.reversed(0x30 + symbol, 8),
+            144..=255 => self.reversed(0x190 + symbol - 144, 9),
+            256..=279 => self.reversed(symbol - 256, 7),
+            _ => self.reversed(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+/// Canonical Huffman code stored as symbol counts per length plus sorted symbols.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for len in 1..16 {
+            offsets[len] = offsets[len - 1] + counts[len - 1];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> io::Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+/// Match finder settings for `compress`
+const WINDOW: usize = 32768;
+const HASH_BITS: u32 = 15;
+const MAX_CHAIN: usize = 64;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order in which code length code lengths are stored in dynamic blocks
+const CL_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+pub fn inflate(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reader = BitReader {
+        data,
+        pos: 0,
+        bit: 0,
+    };
+    let mut out = Vec::new();
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let p = reader.pos;
+                if p + 4 > data.len() {
+                    return Err(invalid("unexpected end of deflate data"));
+                }
+                let len = u16::from_le_bytes([data[p], data[p + 1]]) as usize;
+                let nlen = u16::from_le_bytes([data[p + 2], data[p + 3]]) as usize;
+                if len != !nlen & 0xffff || p + 4 + len > data.len() {
+                    return Err(invalid("corrupt stored deflate block"));
+                }
+                out.extend_from_slice(&data[p + 4..p + 4 + len]);
+                reader.pos = p + 4 + len;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let lit = Huffman::new(&lengths);
+                let dist = Huffman::new(&[5; 30]);
+                inflate_block(&mut reader, &mut out, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = read_dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &lit, &dist)?;
+            }
+            _ => return Err(invalid("invalid deflate block type")),
+        }
+
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn read_dynamic_tables(reader: &mut BitReader) -> io::Result<(Huffman, Huffman)> {
+    let hlit = reader.bits(5)? as usize + 257;
+    let hdist = reader.bits(5)? as usize + 1;
+    let hclen = reader.bits(4)? as usize + 4;
+
+    let mut cl_lengths = [0u8; 19];
+    for &idx in CL_ORDER.iter().take(hclen) {
+        cl_lengths[idx] = reader.bits(3)? as u8;
+    }
+    let cl = Huffman::new(&cl_lengths);
+
+    let mut lengths = Vec::with_capacity(hlit + hdist);
+    while lengths.len() < hlit + hdist {
+        let symbol = cl.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let prev = *lengths
+                    .last()
+                    .ok_or_else(|| invalid("repeat without previous code length"))?;
+                (prev, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        for _ in 0..repeat {
+            lengths.push(value);
+        }
+    }
+    if lengths.len() != hlit + hdist {
+        return Err(invalid("code lengths overflow"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..hlit]),
+        Huffman::new(&lengths[hlit..]),
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman,
+) -> io::Result<()> {
+    loop {
+        let symbol = lit.decode(reader)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let i = symbol - 257;
+                let len = LENGTH_BASE[i] as usize + reader.bits(LENGTH_EXTRA[i] as u32)? as usize;
+                let d = dist.decode(reader)? as usize;
+                if d >= 30 {
+                    return Err(invalid("invalid deflate distance code"));
+                }
+                let distance = DIST_BASE[d] as usize + reader.bits(DIST_EXTRA[d] as u32)? as usize;
+                if distance > out.len() {
+                    return Err(invalid("deflate distance before start of data"));
+                }
+                let start = out.len() - distance;
+                for k in 0..len {
+                    out.push(out[start + k]);
+                }
+            }
+            _ => return Err(invalid("invalid deflate literal/length code")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adler32, compress, decompress};
+
+    #[test]
+    fn adler() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn fixed_huffman() {
+        // zlib.compress(b"hello hello hello hello")
+        let data = [
+            0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0x01, 0x68, 0x03,
+            0x08, 0xb1,
+        ];
+        assert_eq!(decompress(&data).unwrap(), b"hello hello hello hello");
+    }
+
+    #[test]
+    fn dynamic_huffman() {
+        // zlib.compress(bytes((i * i // 7) % 26 + 97 for i in range(600)), 9)
+        let data = [
+            0x78, 0xda, 0xed, 0xce, 0xd1, 0x15, 0x04, 0x11, 0x0c, 0x40, 0xd1, 0x5a, 0x11, 0x64,
+            0x08, 0x23, 0xc8, 0x10, 0xd5, 0xef, 0x4e, 0x17, 0xfb, 0xb1, 0xb7, 0x80, 0x77, 0x9e,
+            0x31, 0xc6, 0x3a, 0x08, 0x98, 0xe8, 0xee, 0xa2, 0x2e, 0xe6, 0x26, 0x27, 0x50, 0xdf,
+            0x9e, 0x86, 0xb9, 0x58, 0x91, 0x4f, 0x9a, 0x50, 0x35, 0x49, 0x1c, 0x9e, 0x81, 0xa1,
+            0x87, 0x89, 0x8b, 0x0c, 0xc7, 0x55, 0x40, 0x08, 0x9e, 0x1a, 0xb4, 0x67, 0xaf, 0xa3,
+            0xa0, 0xdb, 0xb3, 0x11, 0x7a, 0xab, 0x4b, 0x06, 0xb7, 0xbb, 0xbe, 0xee, 0xc6, 0x43,
+            0x96, 0x5a, 0x8f, 0xd4, 0xe6, 0x76, 0x58, 0x86, 0xfa, 0xdc, 0x35, 0xd4, 0x07, 0x48,
+            0xa0, 0xac, 0xc8, 0x86, 0x16, 0xce, 0xd0, 0xbf, 0x65, 0xf6, 0x23, 0x4a, 0xd2, 0x0a,
+            0x33, 0x1d, 0x46, 0xe5, 0xcb, 0x0c, 0xf2, 0xbb, 0x53, 0x38, 0xd2, 0x72, 0x74, 0x2a,
+            0xfd, 0xa6, 0x84, 0x01, 0x9c, 0x35, 0xaf, 0xff, 0xf5, 0x4f, 0x5f, 0x7f, 0x00, 0x73,
+            0x25, 0xff, 0xd2,
+        ];
+        let expected: Vec<u8> = (0..600u32).map(|i| ((i * i / 7) % 26 + 97) as u8).collect();
+        assert_eq!(decompress(&data).unwrap(), expected);
+    }
+
+    #[test]
+    fn stored() {
+        // zlib.compress(b"abc", 0)
+        let data = [
+            0x78, 0x01, 0x01, 0x03, 0x00, 0xfc, 0xff, 0x61, 0x62, 0x63, 0x02, 0x4d, 0x01, 0x27,
+        ];
+        assert_eq!(decompress(&data).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn corrupt() {
+        assert!(decompress(&[0x78, 0x9c, 0xff, 0xff, 0, 0, 0, 0]).is_err());
+        assert!(decompress(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        let repetitive: Vec<u8> = (0..5000).map(|i| (i % 37) as u8).collect();
+        let packed = compress(&repetitive);
+        assert!(packed.len() < repetitive.len() / 10);
+        assert_eq!(decompress(&packed).unwrap(), repetitive);
+
+        let noisy: Vec<u8> = (0..70000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        assert_eq!(decompress(&compress(&noisy)).unwrap(), noisy);
+        assert_eq!(decompress(&compress(&[])).unwrap(), Vec::<u8>::new());
+        assert_eq!(decompress(&compress(b"ab")).unwrap(), b"ab");
+    }
+}
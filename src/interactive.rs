@@ -0,0 +1,421 @@
+use sdl2::{
+    event::Event,
+    keyboard::{Keycode, Mod},
+};
+use std::{
+    f64::consts::PI,
+    path::{Path, PathBuf},
+};
+
+use ray_tracer::bookmarks::Bookmarks;
+use ray_tracer::camera_path::{CameraKeyframe, CameraPath, PathPreview};
+use ray_tracer::clock::Clock;
+use ray_tracer::demo;
+use ray_tracer::display::sdl::SdlDisplay;
+use ray_tracer::image::y4m::Y4mOptions;
+use ray_tracer::pass::Pass;
+use ray_tracer::recording::{Recording, SequenceFormat};
+use ray_tracer::renderer::Renderer;
+use ray_tracer::screenshot::{self, Screenshot};
+use ray_tracer::{Camera, Scene, Sky, Tracer};
+
+/// Bookmarks of the demo scene, scene files keep theirs next to them
+const BOOKMARKS_PATH: &str = "bookmarks.txt";
+/// Camera path of the demo scene, scene files keep theirs next to them
+const CAMERA_PATH_PATH: &str = "camera_path.txt";
+/// Spacing of keyframes appended interactively, in seconds
+const KEYFRAME_STEP: f64 = 1.0;
+/// Environment map turn and brightness change per key press
+const ENVIRONMENT_ROTATION_STEP: f64 = PI / 12.0;
+const ENVIRONMENT_INTENSITY_STEP: f64 = 1.25;
+/// Sun height change per key press
+const SUN_ELEVATION_STEP: f64 = PI / 36.0;
+/// Screenshots land here, PNG images with their render settings next to them
+const SCREENSHOT_DIR: &str = "screenshots";
+/// Resolution multiplier and samples per pixel of high quality screenshots
+const SCREENSHOT_SCALE: u32 = 2;
+const SCREENSHOT_SAMPLES: u32 = 16;
+/// Recordings of the interactive session land here, Y4M video or PNG sequences
+const RECORDING_DIR: &str = "recordings";
+const RECORDING_FPS: f64 = 30.0;
+
+/// Opens the window and explores `scene`, loaded from `scene_file` if it came
+/// from one, with the keyboard until it is closed. Frames of `width` x `height`
+/// are scaled up `scale` times to fill the window.
+pub fn run(
+    tracer: Tracer,
+    scene: Scene,
+    scene_file: Option<PathBuf>,
+    camera: Camera,
+    (width, height): (u32, u32),
+    scale: u32,
+) {
+    let display = SdlDisplay::initialize(width * scale, height * scale, width, height);
+    let renderer = Renderer::new(display, width, height);
+    run_render_loop(renderer, tracer, camera, scene, scene_file);
+}
+
+/// Saves the frame on screen, or renders it again at `SCREENSHOT_SCALE` times
+/// the resolution with `SCREENSHOT_SAMPLES` samples per pixel, as a timestamped
+/// PNG with the settings needed to reproduce it alongside.
+fn take_screenshot(
+    renderer: &Renderer<SdlDisplay>,
+    tracer: &Tracer,
+    camera: &Camera,
+    scene: &Scene,
+    scene_file: Option<&PathBuf>,
+    time: f64,
+    high_quality: bool,
+) {
+    let dir = Path::new(SCREENSHOT_DIR);
+    if let Err(err) = std::fs::create_dir_all(dir) {
+        println!("Cannot create screenshot directory! {}", err);
+        return;
+    }
+    let path = screenshot::timestamped_path(dir, "screenshot", "png");
+
+    let frame = renderer.frame();
+    let (width, height) = frame.size();
+    let mut shot = Screenshot::capture(camera, time, (width, height), tracer, scene);
+    shot.scene = scene_file.cloned();
+    let result = if high_quality {
+        shot.size = (width * SCREENSHOT_SCALE, height * SCREENSHOT_SCALE);
+        shot.samples_per_pixel = tracer.samples_per_pixel.max(SCREENSHOT_SAMPLES);
+        println!("Rendering {}x{} screenshot...", shot.size.0, shot.size.1);
+
+        screenshot::save_png(&path, &shot.render(scene))
+    } else {
+        screenshot::save_png(&path, frame)
+    };
+
+    match result.and_then(|_| shot.save(&path.with_extension("txt"))) {
+        Ok(()) => println!("Saved screenshot {}", path.display()),
+        Err(err) => println!("Cannot save screenshot! {}", err),
+    }
+}
+
+/// Starts recording the window into a timestamped Y4M file or PNG directory
+fn start_recording(renderer: &Renderer<SdlDisplay>, images: bool) -> Option<Recording> {
+    let dir = Path::new(RECORDING_DIR);
+    let (format, extension) = if images {
+        (SequenceFormat::Png, "")
+    } else {
+        (SequenceFormat::Y4m(Y4mOptions::default()), "y4m")
+    };
+    let path = screenshot::timestamped_path(dir, "recording", extension);
+    match Recording::start(&path, format, renderer.frame().size(), RECORDING_FPS) {
+        Ok(recording) => {
+            println!("Recording to {}", path.display());
+            Some(recording)
+        }
+        Err(err) => {
+            println!("Cannot start recording! {}", err);
+            None
+        }
+    }
+}
+
+/// Bookmarks file of the scene, `room.txt` keeps them in `room.bookmarks`
+fn bookmarks_path(scene_file: Option<&Path>) -> PathBuf {
+    scene_file.map_or(PathBuf::from(BOOKMARKS_PATH), |path| {
+        path.with_extension("bookmarks")
+    })
+}
+
+/// Camera path file of the scene, `room.txt` keeps it in `room.path`
+fn camera_path_path(scene_file: Option<&Path>) -> PathBuf {
+    scene_file.map_or(PathBuf::from(CAMERA_PATH_PATH), |path| {
+        path.with_extension("path")
+    })
+}
+
+fn finish_recording(recording: Recording) {
+    let path = recording.path.clone();
+    match recording.finish() {
+        Ok(frames) => println!("Recorded {} frames to {}", frames, path.display()),
+        Err(err) => println!("Cannot finish recording! {}", err),
+    }
+}
+
+/// Requests from the keyboard that reach beyond the state `handle_events` edits
+pub enum Command {
+    Quit,
+    ShowPass(Pass),
+    /// Save the frame on screen, or render a high quality copy of it
+    Screenshot {
+        high_quality: bool,
+    },
+    /// Start recording the window, as a PNG sequence or Y4M video, or stop
+    ToggleRecording {
+        images: bool,
+    },
+    /// Write the bookmarks out after a slot was stored
+    SaveBookmarks,
+    /// Write the camera path out after a keyframe was added or it was cleared
+    SaveCameraPath,
+}
+
+fn run_render_loop(
+    mut renderer: Renderer<SdlDisplay>,
+    mut tracer: Tracer,
+    mut camera: Camera,
+    mut scene: Scene,
+    scene_file: Option<PathBuf>,
+) {
+    let bookmarks_path = bookmarks_path(scene_file.as_deref());
+    let mut bookmarks = if bookmarks_path.exists() {
+        Bookmarks::load(&bookmarks_path).unwrap_or_else(|err| {
+            println!("Cannot load camera bookmarks! {}", err);
+            Bookmarks::new()
+        })
+    } else {
+        Bookmarks::new()
+    };
+
+    let camera_path_path = camera_path_path(scene_file.as_deref());
+    let mut camera_path = if camera_path_path.exists() {
+        CameraPath::load(&camera_path_path).unwrap_or_else(|err| {
+            println!("Cannot load camera path! {}", err);
+            CameraPath::new()
+        })
+    } else {
+        CameraPath::new()
+    };
+    let mut preview = PathPreview::new();
+    let mut clock = Clock::new();
+    // viewpoint and time of the frame on screen, for screenshots
+    let mut shown = (camera.clone(), clock.time());
+    let mut recording: Option<Recording> = None;
+
+    loop {
+        match handle_events(
+            &mut renderer.display.event_pump,
+            &mut camera,
+            &mut bookmarks,
+            &mut camera_path,
+            &mut preview,
+            &mut clock,
+            &mut scene,
+        ) {
+            Some(Command::Quit) => break,
+            Some(Command::ShowPass(pass)) => {
+                println!("Showing the {} pass", pass.name());
+                tracer.pass = pass;
+            }
+            Some(Command::Screenshot { high_quality }) => take_screenshot(
+                &renderer,
+                &tracer,
+                &shown.0,
+                &scene,
+                scene_file.as_ref(),
+                shown.1,
+                high_quality,
+            ),
+            Some(Command::ToggleRecording { images }) => {
+                recording = match recording.take() {
+                    Some(recording) => {
+                        finish_recording(recording);
+                        None
+                    }
+                    None => start_recording(&renderer, images),
+                }
+            }
+            Some(Command::SaveBookmarks) => {
+                if let Err(err) = bookmarks.save(&bookmarks_path) {
+                    println!("Cannot save camera bookmarks! {}", err);
+                }
+            }
+            Some(Command::SaveCameraPath) => {
+                if let Err(err) = camera_path.save(&camera_path_path) {
+                    println!("Cannot save camera path! {}", err);
+                }
+            }
+            None => {}
+        }
+
+        preview.update(&camera_path, &mut camera);
+        let time = clock.tick();
+        renderer.draw_frame(&tracer, &camera, &scene, time);
+        shown = (camera.clone(), time);
+        if let Some(active) = &mut recording {
+            if let Err(err) = active.record(renderer.frame()) {
+                println!("Cannot record frame! {}", err);
+                recording = None;
+            }
+        }
+    }
+    if let Some(recording) = recording {
+        finish_recording(recording);
+    }
+}
+
+pub fn handle_events(
+    event_pump: &mut sdl2::EventPump,
+    camera: &mut Camera,
+    bookmarks: &mut Bookmarks,
+    camera_path: &mut CameraPath,
+    preview: &mut PathPreview,
+    clock: &mut Clock,
+    scene: &mut Scene,
+) -> Option<Command> {
+    for event in event_pump.poll_iter() {
+        match event {
+            Event::Quit { .. } => return Some(Command::Quit),
+            Event::KeyDown {
+                keycode: Some(key),
+                keymod,
+                ..
+            } => match key {
+                Keycode::Escape => return Some(Command::Quit),
+
+                Keycode::A => camera.shift_lateral(-0.25),
+                Keycode::D => camera.shift_lateral(0.25),
+                Keycode::W => camera.shift_longitudinal(0.25),
+                Keycode::S => camera.shift_longitudinal(-0.25),
+                Keycode::Q => camera.shift_vertical(0.25),
+                Keycode::Z => camera.shift_vertical(-0.25),
+
+                Keycode::I => camera.rotate_pitch(-0.15),
+                Keycode::K => camera.rotate_pitch(0.15),
+                Keycode::J => camera.rotate_yaw(-0.15),
+                Keycode::L => camera.rotate_yaw(0.15),
+                Keycode::U => camera.rotate_roll(0.15),
+                Keycode::O => camera.rotate_roll(-0.15),
+
+                Keycode::Space => clock.toggle_pause(),
+                Keycode::P => preview.toggle(camera_path),
+                Keycode::N => {
+                    let time = if camera_path.is_empty() {
+                        0.0
+                    } else {
+                        camera_path.end_time() + KEYFRAME_STEP
+                    };
+                    camera_path.add_keyframe(CameraKeyframe::from_camera(time, camera));
+                    return Some(Command::SaveCameraPath);
+                }
+                Keycode::Delete => {
+                    camera_path.clear();
+                    return Some(Command::SaveCameraPath);
+                }
+
+                Keycode::F12 => {
+                    return Some(Command::Screenshot {
+                        high_quality: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+                    })
+                }
+
+                Keycode::R => {
+                    return Some(Command::ToggleRecording {
+                        images: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+                    })
+                }
+
+                Keycode::T => {
+                    scene.sky = match scene.sky {
+                        Some(_) => None,
+                        None => Some(Sky::default()),
+                    }
+                }
+                Keycode::G => {
+                    if scene.volumes.iter().any(|v| v.density.is_none()) {
+                        scene.volumes.retain(|v| v.density.is_some());
+                    } else {
+                        scene.volumes.push(demo::fog());
+                    }
+                }
+                Keycode::H => {
+                    if scene.volumes.iter().any(|v| v.density.is_some()) {
+                        scene.volumes.retain(|v| v.density.is_none());
+                    } else {
+                        match demo::smoke() {
+                            Ok(smoke) => scene.volumes.push(smoke),
+                            Err(err) => println!("Cannot load voxel grid! {}", err),
+                        }
+                    }
+                }
+                Keycode::Comma | Keycode::Period => {
+                    if let Some(sky) = &mut scene.sky {
+                        sky.elevation += match key {
+                            Keycode::Comma => -SUN_ELEVATION_STEP,
+                            _ => SUN_ELEVATION_STEP,
+                        };
+                        sky.update();
+                    }
+                }
+
+                Keycode::LeftBracket | Keycode::RightBracket | Keycode::Minus | Keycode::Equals => {
+                    if let Some(sky) = &mut scene.sky {
+                        // the sun moves with the sky, so its azimuth turns them both
+                        match key {
+                            Keycode::LeftBracket => sky.azimuth -= ENVIRONMENT_ROTATION_STEP,
+                            Keycode::RightBracket => sky.azimuth += ENVIRONMENT_ROTATION_STEP,
+                            Keycode::Minus => sky.intensity /= ENVIRONMENT_INTENSITY_STEP,
+                            _ => sky.intensity *= ENVIRONMENT_INTENSITY_STEP,
+                        }
+                        sky.update();
+                    } else if let Some(environment) = &mut scene.environment {
+                        match key {
+                            Keycode::LeftBracket => {
+                                environment.rotation -= ENVIRONMENT_ROTATION_STEP
+                            }
+                            Keycode::RightBracket => {
+                                environment.rotation += ENVIRONMENT_ROTATION_STEP
+                            }
+                            Keycode::Minus => environment.intensity /= ENVIRONMENT_INTENSITY_STEP,
+                            _ => environment.intensity *= ENVIRONMENT_INTENSITY_STEP,
+                        }
+                    }
+                }
+
+                _ => {
+                    if let Some(pass) = pass_for_key(key) {
+                        return Some(Command::ShowPass(pass));
+                    }
+                    if let Some(slot) = bookmark_slot(key) {
+                        if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                            bookmarks.store(slot, camera);
+                            return Some(Command::SaveBookmarks);
+                        } else {
+                            bookmarks.recall(slot, camera);
+                        }
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Number keys 1 to 9 pick the pass on screen, 1 being the beauty render
+fn pass_for_key(key: Keycode) -> Option<Pass> {
+    let keys = [
+        Keycode::Num1,
+        Keycode::Num2,
+        Keycode::Num3,
+        Keycode::Num4,
+        Keycode::Num5,
+        Keycode::Num6,
+        Keycode::Num7,
+        Keycode::Num8,
+        Keycode::Num9,
+    ];
+    keys.iter().position(|k| *k == key).map(|i| Pass::ALL[i])
+}
+
+/// F1..F10 select bookmark slots 0..9; Shift stores, plain press recalls.
+fn bookmark_slot(key: Keycode) -> Option<usize> {
+    let keys = [
+        Keycode::F1,
+        Keycode::F2,
+        Keycode::F3,
+        Keycode::F4,
+        Keycode::F5,
+        Keycode::F6,
+        Keycode::F7,
+        Keycode::F8,
+        Keycode::F9,
+        Keycode::F10,
+    ];
+    keys.iter().position(|k| *k == key)
+}
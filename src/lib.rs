@@ -0,0 +1,51 @@
+//! Ray tracer with physically based materials, image based and sky lighting,
+//! participating media and motion blur.
+//!
+//! Build a [`Scene`] from [`Shape`]s with [`Material`]s and lights, point a
+//! [`Camera`] at it and [`render`] a frame with a [`Tracer`] into a
+//! [`Framebuffer`]. The modules below hold the rest: textures, BSDFs, image
+//! readers and writers, camera paths and headless animation output.
+
+pub mod bookmarks;
+pub mod bsdf;
+pub mod camera;
+pub mod camera_path;
+pub mod clock;
+pub mod demo;
+pub mod display;
+pub mod framebuffer;
+pub mod geometry;
+pub mod headless;
+pub mod image;
+pub mod material;
+pub mod medium;
+pub mod pass;
+pub mod random;
+pub mod recording;
+pub mod renderer;
+pub mod scene_file;
+pub mod screenshot;
+pub mod texture;
+pub mod tracer;
+
+pub use camera::Camera;
+pub use framebuffer::{render, Framebuffer, RenderJob};
+pub use geometry::{
+    directional_light::DirectionalLight, environment::Environment, point_light::PointLight,
+    scene::Scene, shape::Shape, sky::Sky, vector::Vector,
+};
+pub use material::Material;
+pub use tracer::Tracer;
+
+#[cfg(test)]
+#[macro_use]
+mod tests {
+    #[macro_export]
+    macro_rules! assert_delta {
+        ($x:expr, $y:expr, $d:expr) => {
+            if !($x - $y < $d && $y - $x < $d) {
+                panic!("assert_delta: \n left: {:?}\nright: {:?}\n", $x, $y);
+            }
+        };
+    }
+}
@@ -1,89 +1,236 @@
-mod camera;
-mod geometry;
-mod material;
-mod renderer;
-mod tracer;
+mod cli;
+#[cfg(feature = "sdl")]
+mod interactive;
 
-use sdl2::{event::Event, keyboard::Keycode};
-use std::f64::consts::PI;
+use std::{env, path::Path};
 
-use camera::Camera;
-use geometry::{scene::Scene, vector::Vector};
-use renderer::Renderer;
-use tracer::Tracer;
+use ray_tracer::bookmarks::Bookmark;
+use ray_tracer::camera_path::CameraPath;
+use ray_tracer::demo;
+use ray_tracer::headless::{self, ExrOptions, FrameOutput};
+use ray_tracer::image::ppm;
+use ray_tracer::scene_file::SceneFile;
+use ray_tracer::screenshot::{self, Screenshot};
+use ray_tracer::{render, Camera, Scene, Sky, Vector};
 
-const SCR_W: u32 = 1920;
-const SCR_H: u32 = 1440;
+use cli::{Command, RenderSettings};
 
-const REAL_W: u32 = 640;
-const REAL_H: u32 = 480;
-
-const VFOV: f64 = PI / 3.0;
+/// Part of the frame interval the shutter stays open (180 degree shutter)
+const SHUTTER_FRACTION: f64 = 0.5;
 
 fn main() {
-    let camera = Camera::new(
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = cli::parse(&args).unwrap_or_else(|err| {
+        println!("{}! Run with --help for the options.", err);
+        std::process::exit(1);
+    });
+
+    match command {
+        Command::Help => println!("{}", cli::USAGE),
+        Command::View { settings, scale } => view(&settings, scale),
+        Command::Render {
+            settings,
+            output,
+            time,
+            exr,
+        } => render_frame(&settings, &output, time, exr.as_ref()),
+        Command::RenderPath {
+            settings,
+            camera_path,
+            output,
+            fps,
+            format,
+        } => {
+            let output = FrameOutput {
+                path: output,
+                format,
+            };
+            render_path(&settings, &camera_path, fps, &output);
+        }
+        Command::RenderShot {
+            settings_file,
+            output,
+            threads,
+        } => render_shot(&settings_file, &output, threads),
+    }
+}
+
+#[cfg(feature = "sdl")]
+fn view(settings: &RenderSettings, scale: u32) {
+    let (scene, camera) = load_scene(settings);
+    interactive::run(
+        settings.tracer(),
+        scene,
+        settings.scene.clone(),
+        camera,
+        settings.size,
+        scale,
+    );
+}
+
+#[cfg(not(feature = "sdl"))]
+fn view(_: &RenderSettings, _: u32) {
+    println!("Built without the interactive window! Use render, render-path or render-shot.");
+    std::process::exit(1);
+}
+
+/// The scene file of `settings` or the demo scene, and the camera to look at it
+/// through: the one the scene file sets up or one at the origin looking along +Z
+fn load_scene(settings: &RenderSettings) -> (Scene, Camera) {
+    let (scene, start) = match &settings.scene {
+        Some(path) => {
+            let file = SceneFile::load(path).unwrap_or_else(|err| {
+                println!("Cannot load scene {}! {}", path.display(), err);
+                std::process::exit(1);
+            });
+            (file.scene, file.camera)
+        }
+        None => (demo_scene(), None),
+    };
+
+    let (width, height) = settings.size;
+    let mut camera = Camera::new(
         Vector::zero(),
         Vector::one_z(),
         Vector::one_y(),
-        VFOV,
-        REAL_W as f64 / REAL_H as f64,
+        settings.vfov_or_default(),
+        width as f64 / height as f64,
     );
-    let tracer = Tracer::new();
-    let scene = Scene::new();
-
-    let renderer = Renderer::initialize(SCR_W, SCR_H, REAL_W, REAL_H);
-    run_render_loop(renderer, tracer, camera, scene);
+    if let Some(start) = start {
+        Bookmark {
+            vfov: settings.vfov.unwrap_or(start.vfov),
+            ..start
+        }
+        .apply(&mut camera);
+    }
+    (scene, camera)
 }
 
-fn run_render_loop(mut renderer: Renderer, tracer: Tracer, mut camera: Camera, scene: Scene) {
-    loop {
-        if handle_events(&mut renderer.event_pump, &mut camera) {
-            break;
+/// Renders a single frame as PNG, PPM or EXR, following the file extension
+fn render_frame(settings: &RenderSettings, output: &Path, time: f64, exr: Option<&ExrOptions>) {
+    let (scene, camera) = load_scene(settings);
+    let tracer = settings.tracer();
+
+    let result = match exr {
+        Some(options) => headless::render_exr(
+            &tracer,
+            &scene,
+            &camera,
+            settings.size,
+            time,
+            options,
+            output,
+        ),
+        None => {
+            let frame = render(&tracer, &camera, &scene, settings.size, time);
+            if output.extension().is_some_and(|e| e == "ppm") {
+                let (width, height) = frame.size();
+                ppm::write(output, width, height, &frame.to_rgb())
+            } else {
+                screenshot::save_png(output, &frame)
+            }
         }
+    };
+    result.unwrap_or_else(|err| {
+        println!("Cannot write image! {}", err);
+        std::process::exit(1);
+    });
+    println!("Wrote {}", output.display());
+}
 
-        renderer.draw_frame(&tracer, &camera, &scene);
-    }
+/// Renders the camera path sampled at `fps` into `output`. With more than one
+/// sample per pixel frames are rendered with motion blur.
+fn render_path(settings: &RenderSettings, path_file: &Path, fps: f64, output: &FrameOutput) {
+    let path = CameraPath::load(path_file).unwrap_or_else(|err| {
+        println!("Cannot load camera path! {}", err);
+        std::process::exit(1);
+    });
+    let (scene, _) = load_scene(settings);
+
+    let tracer = settings.tracer();
+    let shutter = if tracer.samples_per_pixel > 1 {
+        SHUTTER_FRACTION / fps
+    } else {
+        0.0
+    };
+
+    let frames = headless::render_camera_path(
+        &tracer,
+        &scene,
+        &path,
+        settings.size,
+        (fps, shutter),
+        output,
+        |frame, count| println!("Rendered frame {}/{}", frame, count),
+    )
+    .unwrap_or_else(|err| {
+        println!("Cannot write frames! {}", err);
+        std::process::exit(1);
+    });
+    println!("Wrote {} frames to {}", frames, output.path.display());
 }
 
-pub fn handle_events(event_pump: &mut sdl2::EventPump, camera: &mut Camera) -> bool {
-    for event in event_pump.poll_iter() {
-        match event {
-            Event::Quit { .. } => return true,
-            Event::KeyDown {
-                keycode: Some(key), ..
-            } => match key {
-                Keycode::Escape => return true,
-
-                Keycode::A => camera.shift_lateral(-0.25),
-                Keycode::D => camera.shift_lateral(0.25),
-                Keycode::W => camera.shift_longitudinal(0.25),
-                Keycode::S => camera.shift_longitudinal(-0.25),
-                Keycode::Q => camera.shift_vertical(0.25),
-                Keycode::Z => camera.shift_vertical(-0.25),
-
-                Keycode::I => camera.rotate_pitch(-0.15),
-                Keycode::K => camera.rotate_pitch(0.15),
-                Keycode::J => camera.rotate_yaw(-0.15),
-                Keycode::L => camera.rotate_yaw(0.15),
-                Keycode::U => camera.rotate_roll(0.15),
-                Keycode::O => camera.rotate_roll(-0.15),
-                _ => {}
-            },
-            _ => {}
+/// Renders a screenshot again from the settings saved next to it.
+fn render_shot(settings_file: &Path, out_file: &Path, threads: Option<usize>) {
+    let shot = Screenshot::load(settings_file).unwrap_or_else(|err| {
+        println!("Cannot load screenshot settings! {}", err);
+        std::process::exit(1);
+    });
+    let mut scene = match &shot.scene {
+        Some(path) => {
+            SceneFile::load(path)
+                .unwrap_or_else(|err| {
+                    println!("Cannot load scene {}! {}", path.display(), err);
+                    std::process::exit(1);
+                })
+                .scene
         }
+        None => demo_scene(),
+    };
+    apply_screenshot_scene(&shot, &mut scene);
+
+    let mut tracer = shot.tracer();
+    if let Some(threads) = threads {
+        tracer.threads = threads;
     }
-    false
+    let frame = render(&tracer, &shot.camera(), &scene, shot.size, shot.time);
+    screenshot::save_png(out_file, &frame).unwrap_or_else(|err| {
+        println!("Cannot write screenshot! {}", err);
+        std::process::exit(1);
+    });
+    println!("Wrote {}", out_file.display());
 }
 
-#[cfg(test)]
-#[macro_use]
-mod tests {
-    #[macro_export]
-    macro_rules! assert_delta {
-        ($x:expr, $y:expr, $d:expr) => {
-            if !($x - $y < $d && $y - $x < $d) {
-                panic!("assert_delta: \n left: {:?}\nright: {:?}\n", $x, $y);
-            }
-        };
+/// Sets the sky, environment and volumes of the scene the way they were captured
+fn apply_screenshot_scene(shot: &Screenshot, scene: &mut Scene) {
+    scene.sky = shot.sky.map(|(elevation, azimuth, turbidity, intensity)| {
+        let mut sky = Sky::new(elevation, azimuth, turbidity);
+        sky.intensity = intensity;
+        sky.update();
+        sky
+    });
+    if let (Some(environment), Some((rotation, intensity))) =
+        (&mut scene.environment, shot.environment)
+    {
+        environment.rotation = rotation;
+        environment.intensity = intensity;
+    }
+    scene.volumes.clear();
+    if shot.fog {
+        scene.volumes.push(demo::fog());
     }
+    if shot.smoke {
+        scene.volumes.push(demo::smoke().unwrap_or_else(|err| {
+            println!("Cannot load voxel grid! {}", err);
+            std::process::exit(1);
+        }));
+    }
+}
+
+/// The demo scene, exiting when the environment map next to it is broken
+fn demo_scene() -> Scene {
+    demo::scene().unwrap_or_else(|err| {
+        println!("Cannot load environment map! {}", err);
+        std::process::exit(1);
+    })
 }
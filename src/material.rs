@@ -1,6 +1,212 @@
-use crate::geometry::vector::Vector;
+use crate::bsdf::Bsdf;
+use crate::geometry::{animation::Track, shape::HitRecord, vector::Vector};
+use crate::texture::{TexCoord, Texture};
+
+/// Step along the surface used to estimate bump map slopes
+const BUMP_DELTA: f64 = 1e-3;
 
 pub struct Material {
     pub color: Vector,
     pub refletivity_index: f64,
+    /// Overrides `color` when the material is animated
+    pub color_track: Option<Track<Vector>>,
+    /// Overrides `refletivity_index` when the material is animated
+    pub reflectivity_track: Option<Track<f64>>,
+    /// Multiplies `color` at every hit point
+    pub texture: Option<Box<dyn Texture>>,
+    /// Multiplies `refletivity_index` at every hit point
+    pub reflectivity_texture: Option<Box<dyn Texture>>,
+    /// Tangent-space normal map, RGB in 0..1 encodes XYZ in -1..1
+    pub normal_map: Option<Box<dyn Texture>>,
+    /// Height field whose slope tilts the shading normal
+    pub bump_map: Option<Box<dyn Texture>>,
+    /// Height in scene units of a bump map value of one, whether the map
+    /// follows UVs or positions
+    pub bump_strength: f64,
+    /// Physically based scattering replacing the diffuse + mirror model, fed by `color`
+    pub bsdf: Option<Box<dyn Bsdf>>,
+    /// Light given off by the front of the surface, scaled by `emission_strength`
+    pub emission: Vector,
+    pub emission_strength: f64,
+    /// Bounces followed from this surface at most. The render's depth still
+    /// applies, so surfaces that hardly show reflections can stop paths early
+    /// while mirrors use the full depth.
+    pub max_depth: Option<u32>,
+}
+
+impl Material {
+    /// Radiance the surface emits, zero for anything that isn't a light
+    pub fn emitted(&self) -> Vector {
+        self.emission * self.emission_strength
+    }
+
+    pub fn color_at(&self, coord: &TexCoord) -> Vector {
+        let color = self
+            .color_track
+            .as_ref()
+            .map_or(self.color, |t| t.at(coord.time));
+        match &self.texture {
+            Some(texture) => color.scale(&texture.value(coord)),
+            None => color,
+        }
+    }
+
+    pub fn reflectivity_at(&self, coord: &TexCoord) -> f64 {
+        let reflectivity = self
+            .reflectivity_track
+            .as_ref()
+            .map_or(self.refletivity_index, |t| t.at(coord.time));
+        match &self.reflectivity_texture {
+            Some(texture) => reflectivity * texture.scalar(coord),
+            None => reflectivity,
+        }
+    }
+
+    /// Geometric normal perturbed by the normal map and then by the bump map.
+    pub fn shading_normal(&self, hit: &HitRecord, coord: &TexCoord) -> Vector {
+        let mut normal = hit.normal;
+
+        if let Some(normal_map) = &self.normal_map {
+            let m = normal_map.value(coord) * 2.0 - Vector::one();
+            normal = (hit.tangent * m.x + hit.bitangent * m.y + hit.normal * m.z).normalized();
+        }
+
+        if let Some(bump_map) = &self.bump_map {
+            // the point and its UVs move by the same distance along the surface,
+            // so slopes are per scene unit whichever of them the map reads
+            let (u_scale, v_scale) = hit.uv_scale;
+            let shifted = |dt: f64, db: f64| {
+                TexCoord::new(
+                    coord.point + hit.tangent * dt + hit.bitangent * db,
+                    coord.uv.map(|(u, v)| (u + dt * u_scale, v + db * v_scale)),
+                    coord.time,
+                )
+            };
+            let height = bump_map.scalar(coord);
+            let dh_dt = (bump_map.scalar(&shifted(BUMP_DELTA, 0.0)) - height) / BUMP_DELTA;
+            let dh_db = (bump_map.scalar(&shifted(0.0, BUMP_DELTA)) - height) / BUMP_DELTA;
+
+            normal = (normal - (hit.tangent * dh_dt + hit.bitangent * dh_db) * self.bump_strength)
+                .normalized();
+        }
+
+        normal
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            color: Vector::one(),
+            refletivity_index: 0.0,
+            color_track: None,
+            reflectivity_track: None,
+            texture: None,
+            reflectivity_texture: None,
+            normal_map: None,
+            bump_map: None,
+            bump_strength: 1.0,
+            bsdf: None,
+            emission: Vector::zero(),
+            emission_strength: 1.0,
+            max_depth: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::geometry::{shape::HitRecord, vector::Vector};
+    use crate::texture::{procedural::Gradient, TexCoord, Texture};
+
+    use super::Material;
+
+    fn hit() -> HitRecord {
+        HitRecord {
+            point: Vector::zero(),
+            normal: Vector::one_z(),
+            uv: Some((0.0, 0.0)),
+            tangent: Vector::one_x(),
+            bitangent: Vector::one_y(),
+            uv_scale: (1.0, 1.0),
+        }
+    }
+
+    /// Height map following `u`, ignoring the position
+    struct RampU;
+
+    impl Texture for RampU {
+        fn value(&self, coord: &TexCoord) -> Vector {
+            Vector::one() * coord.uv.map_or(0.0, |(u, _)| u)
+        }
+    }
+
+    #[test]
+    fn no_maps_keep_normal() {
+        let hit = hit();
+        let coord = TexCoord::new(hit.point, hit.uv, 0.0);
+        assert_eq!(
+            Material::default().shading_normal(&hit, &coord),
+            Vector::one_z()
+        );
+    }
+
+    #[test]
+    fn normal_map() {
+        let material = Material {
+            // tilted halfway towards the tangent
+            normal_map: Some(Box::new(Vector::new(1.0, 0.5, 1.0))),
+            ..Default::default()
+        };
+        let hit = hit();
+        let n = material.shading_normal(&hit, &TexCoord::new(hit.point, hit.uv, 0.0));
+
+        let expected = Vector::new(1.0, 0.0, 1.0).normalized();
+        assert_delta!(n.x, expected.x, 1e-12);
+        assert_delta!(n.y, expected.y, 1e-12);
+        assert_delta!(n.z, expected.z, 1e-12);
+    }
+
+    #[test]
+    fn bump_map_tilts_against_slope() {
+        let material = Material {
+            // height grows along X with slope 1
+            bump_map: Some(Box::new(Gradient::new(
+                Vector::zero(),
+                Vector::one() * 10.0,
+                Vector::one_x(),
+                -5.0,
+                5.0,
+            ))),
+            ..Default::default()
+        };
+        let hit = hit();
+        let n = material.shading_normal(&hit, &TexCoord::new(hit.point, hit.uv, 0.0));
+
+        let expected = Vector::new(-1.0, 0.0, 1.0).normalized();
+        assert_delta!(n.x, expected.x, 1e-6);
+        assert_delta!(n.y, expected.y, 1e-6);
+        assert_delta!(n.z, expected.z, 1e-6);
+    }
+
+    #[test]
+    fn uv_bumps_follow_surface_size() {
+        let material = Material {
+            bump_map: Some(Box::new(RampU)),
+            ..Default::default()
+        };
+        // u runs once over a surface 1 and then 4 units long: the same map
+        // is a quarter as steep on the larger one
+        let tilt = |length: f64| {
+            let hit = HitRecord {
+                uv_scale: (1.0 / length, 1.0 / length),
+                ..hit()
+            };
+            let n = material.shading_normal(&hit, &TexCoord::new(hit.point, hit.uv, 0.0));
+            -n.x / n.z
+        };
+        assert_delta!(tilt(1.0), 1.0, 1e-6);
+        assert_delta!(tilt(4.0), 0.25, 1e-6);
+    }
 }
@@ -0,0 +1,243 @@
+use std::io;
+use std::path::Path;
+
+use crate::geometry::vector::Vector;
+use crate::texture::noise::Perlin;
+
+/// Density samples on a regular 3D lattice stretched over the box `min`..`max`,
+/// looked up with trilinear interpolation and zero outside the box.
+pub struct VoxelGrid {
+    pub min: Vector,
+    pub max: Vector,
+    size: (usize, usize, usize),
+    /// x varies fastest, then y, then z
+    values: Vec<f64>,
+    max_value: f64,
+}
+
+impl VoxelGrid {
+    pub fn new(
+        min: Vector,
+        max: Vector,
+        size: (usize, usize, usize),
+        values: Vec<f64>,
+    ) -> VoxelGrid {
+        assert_eq!(values.len(), size.0 * size.1 * size.2);
+        let max_value = values.iter().cloned().fold(0.0, f64::max);
+        VoxelGrid {
+            min,
+            max,
+            size,
+            values,
+            max_value,
+        }
+    }
+
+    /// Samples `density` at the centre of every voxel
+    pub fn from_fn(
+        min: Vector,
+        max: Vector,
+        size: (usize, usize, usize),
+        density: impl Fn(Vector) -> f64,
+    ) -> VoxelGrid {
+        let extent = max - min;
+        let mut values = Vec::with_capacity(size.0 * size.1 * size.2);
+        for z in 0..size.2 {
+            for y in 0..size.1 {
+                for x in 0..size.0 {
+                    let p = Vector::new(
+                        min.x + extent.x * (x as f64 + 0.5) / size.0 as f64,
+                        min.y + extent.y * (y as f64 + 0.5) / size.1 as f64,
+                        min.z + extent.z * (z as f64 + 0.5) / size.2 as f64,
+                    );
+                    values.push(density(p).max(0.0));
+                }
+            }
+        }
+        VoxelGrid::new(min, max, size, values)
+    }
+
+    /// Puffy cloud: turbulence carved by a falloff towards the edges of the box
+    pub fn cloud(min: Vector, max: Vector, resolution: usize, seed: u64) -> VoxelGrid {
+        let perlin = Perlin::new(seed);
+        let center = (min + max) / 2.0;
+        let half = (max - min) / 2.0;
+        VoxelGrid::from_fn(min, max, (resolution, resolution, resolution), |p| {
+            let d = p - center;
+            let r = Vector::new(d.x / half.x, d.y / half.y, d.z / half.z).len();
+            let detail = perlin.turbulence(p * 2.0, 5);
+            (1.0 - r) * 2.0 + detail - 0.6
+        })
+    }
+
+    /// Loads a grid in the raw voxel format: an ASCII `VOXELS` line, a line
+    /// with the x, y and z sample counts, then the samples as little endian
+    /// 32-bit floats, x varying fastest.
+    pub fn load(path: &Path, min: Vector, max: Vector) -> io::Result<VoxelGrid> {
+        let (size, values) = decode(&std::fs::read(path)?)?;
+        Ok(VoxelGrid::new(min, max, size, values))
+    }
+
+    /// Highest density anywhere in the grid, bounds the tracking steps
+    #[inline]
+    pub fn max_value(&self) -> f64 {
+        self.max_value
+    }
+
+    #[inline]
+    fn value(&self, x: usize, y: usize, z: usize) -> f64 {
+        self.values[(z * self.size.1 + y) * self.size.0 + x]
+    }
+
+    /// Interpolated density at `p`
+    pub fn density(&self, p: Vector) -> f64 {
+        let extent = self.max - self.min;
+        let local = p - self.min;
+        let (fx, fy, fz) = (local.x / extent.x, local.y / extent.y, local.z / extent.z);
+        if !(0.0..=1.0).contains(&fx) || !(0.0..=1.0).contains(&fy) || !(0.0..=1.0).contains(&fz) {
+            return 0.0;
+        }
+
+        // samples sit at voxel centres
+        let axis = |f: f64, n: usize| {
+            let c = (f * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let i = (c as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), c - i as f64)
+        };
+        let (x0, x1, tx) = axis(fx, self.size.0);
+        let (y0, y1, ty) = axis(fy, self.size.1);
+        let (z0, z1, tz) = axis(fz, self.size.2);
+
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let plane = |z: usize| {
+            lerp(
+                lerp(self.value(x0, y0, z), self.value(x1, y0, z), tx),
+                lerp(self.value(x0, y1, z), self.value(x1, y1, z), tx),
+                ty,
+            )
+        };
+        lerp(plane(z0), plane(z1), tz)
+    }
+
+    /// Distances along the ray from `origin` along `dir` where it enters and leaves the box
+    pub fn clip(&self, origin: Vector, dir: Vector) -> Option<(f64, f64)> {
+        let mut near = 0.0f64;
+        let mut far = f64::INFINITY;
+        for (o, d, lo, hi) in [
+            (origin.x, dir.x, self.min.x, self.max.x),
+            (origin.y, dir.y, self.min.y, self.max.y),
+            (origin.z, dir.z, self.min.z, self.max.z),
+        ] {
+            if d == 0.0 {
+                if o < lo || o > hi {
+                    return None;
+                }
+                continue;
+            }
+            let (t0, t1) = ((lo - o) / d, (hi - o) / d);
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+        if near < far {
+            Some((near, far))
+        } else {
+            None
+        }
+    }
+}
+
+fn decode(data: &[u8]) -> io::Result<((usize, usize, usize), Vec<f64>)> {
+    let invalid =
+        |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("VOXELS: {}", msg));
+
+    let mut lines = data.splitn(3, |&b| b == b'\n');
+    if lines.next().map(|l| l.trim_ascii()) != Some(b"VOXELS".as_slice()) {
+        return Err(invalid("not a voxel grid"));
+    }
+    let dims = lines
+        .next()
+        .and_then(|l| std::str::from_utf8(l).ok())
+        .ok_or_else(|| invalid("truncated header"))?;
+    let dims: Vec<usize> = dims
+        .split_whitespace()
+        .map(|t| t.parse::<usize>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid("bad header"))?;
+    if dims.len() != 3 || dims.contains(&0) {
+        return Err(invalid("bad header"));
+    }
+
+    let count = dims[0] * dims[1] * dims[2];
+    let bytes = lines
+        .next()
+        .and_then(|b| b.get(..count * 4))
+        .ok_or_else(|| invalid("truncated data"))?;
+    let values = bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+        .collect();
+    Ok(((dims[0], dims[1], dims[2]), values))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::geometry::vector::Vector;
+
+    use super::{decode, VoxelGrid};
+
+    fn ramp() -> VoxelGrid {
+        // density equal to x, sampled at voxel centres of the unit box
+        VoxelGrid::from_fn(Vector::zero(), Vector::one(), (4, 2, 2), |p| p.x)
+    }
+
+    #[test]
+    fn interpolation() {
+        let grid = ramp();
+        assert_delta!(grid.max_value(), 0.875, 1e-12);
+        assert_delta!(grid.density(Vector::new(0.5, 0.3, 0.7)), 0.5, 1e-12);
+        assert_delta!(grid.density(Vector::new(0.375, 0.5, 0.5)), 0.375, 1e-12);
+        // clamped to the outer samples near the faces, zero outside
+        assert_delta!(grid.density(Vector::new(0.0, 0.5, 0.5)), 0.125, 1e-12);
+        assert_eq!(grid.density(Vector::new(1.5, 0.5, 0.5)), 0.0);
+    }
+
+    #[test]
+    fn clip() {
+        let grid = ramp();
+        let (near, far) = grid
+            .clip(Vector::new(0.5, 0.5, -2.0), Vector::one_z())
+            .unwrap();
+        assert_delta!(near, 2.0, 1e-12);
+        assert_delta!(far, 3.0, 1e-12);
+        let (near, far) = grid
+            .clip(Vector::new(0.5, 0.5, 0.5), Vector::one_x())
+            .unwrap();
+        assert_eq!(near, 0.0);
+        assert_delta!(far, 0.5, 1e-12);
+        assert!(grid
+            .clip(Vector::new(2.0, 0.5, -2.0), Vector::one_z())
+            .is_none());
+    }
+
+    #[test]
+    fn cloud_fades_out() {
+        let grid = VoxelGrid::cloud(-Vector::one(), Vector::one(), 16, 3);
+        assert!(grid.density(Vector::new(0.0, 0.0, 0.0)) > 0.5);
+        assert_eq!(grid.density(Vector::new(0.97, 0.97, 0.97)), 0.0);
+    }
+
+    #[test]
+    fn raw_format() {
+        let mut data = b"VOXELS\n2 1 1\n".to_vec();
+        data.extend_from_slice(&0.25f32.to_le_bytes());
+        data.extend_from_slice(&2.0f32.to_le_bytes());
+        let (size, values) = decode(&data).unwrap();
+        assert_eq!(size, (2, 1, 1));
+        assert_eq!(values, vec![0.25, 2.0]);
+
+        assert!(decode(b"VOXELS\n2 1 1\n\0\0").is_err());
+        assert!(decode(b"VOXELS\n2 x 1\n").is_err());
+        assert!(decode(b"PF\n2 1\n").is_err());
+    }
+}
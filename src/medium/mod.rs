@@ -0,0 +1,356 @@
+pub mod grid;
+
+use std::f64::consts::PI;
+
+use crate::geometry::{
+    shape::{surface_epsilon, Shape},
+    vector::Vector,
+};
+use crate::random::Rng;
+
+use grid::VoxelGrid;
+
+/// Henyey-Greenstein phase function: `g` above zero scatters light mostly
+/// forward, below zero mostly back, zero sends it equally everywhere.
+#[derive(Clone, Copy, Debug)]
+pub struct HenyeyGreenstein {
+    pub g: f64,
+}
+
+impl HenyeyGreenstein {
+    /// Density of light turning by an angle with cosine `cos_theta`, per unit solid angle
+    #[inline]
+    pub fn eval(&self, cos_theta: f64) -> f64 {
+        let g = self.g;
+        let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+}
+
+/// Homogeneous participating medium. Coefficients are per unit distance and
+/// per colour channel, light is lost to `absorption` and redirected by `scattering`.
+#[derive(Clone, Copy, Debug)]
+pub struct Medium {
+    pub absorption: Vector,
+    pub scattering: Vector,
+    pub phase: HenyeyGreenstein,
+}
+
+impl Medium {
+    pub fn new(absorption: Vector, scattering: Vector, g: f64) -> Medium {
+        Medium {
+            absorption,
+            scattering,
+            phase: HenyeyGreenstein { g },
+        }
+    }
+
+    #[inline]
+    pub fn extinction(&self) -> Vector {
+        self.absorption + self.scattering
+    }
+
+    /// Share of light left after travelling `dist` through the medium (Beer-Lambert)
+    pub fn transmittance(&self, dist: f64) -> Vector {
+        let e = self.extinction();
+        let channel = |sigma: f64| {
+            if sigma == 0.0 {
+                1.0
+            } else {
+                (-sigma * dist).exp()
+            }
+        };
+        Vector::new(channel(e.x), channel(e.y), channel(e.z))
+    }
+}
+
+/// Region filled with a medium: the inside of a closed convex `boundary`, or
+/// all of space when there is none. Unbounded fog swallows everything at
+/// infinity, including the environment and directional lights.
+///
+/// With a `density` grid the medium coefficients are scaled by it and the volume
+/// ends at the grid box. Such heterogeneous media are delta tracked against the
+/// average of the extinction channels, so their colour comes from the albedo.
+pub struct Volume {
+    pub medium: Medium,
+    pub boundary: Option<Box<dyn Shape>>,
+    pub density: Option<VoxelGrid>,
+}
+
+impl Volume {
+    pub fn new(medium: Medium, boundary: Option<Box<dyn Shape>>) -> Volume {
+        Volume {
+            medium,
+            boundary,
+            density: None,
+        }
+    }
+
+    pub fn with_density(mut self, grid: VoxelGrid) -> Volume {
+        self.density = Some(grid);
+        self
+    }
+
+    /// Part of the ray from `origin` along `dir`, up to distance `max_t`, that
+    /// lies inside the volume
+    pub fn segment(
+        &self,
+        origin: Vector,
+        dir: Vector,
+        max_t: f64,
+        time: f64,
+    ) -> Option<(f64, f64)> {
+        let (mut start, mut end) = match &self.boundary {
+            Some(boundary) => {
+                let t_first = boundary.intersect(origin, dir, 0.0, time)?;
+                let first = origin + dir * t_first;
+                if boundary.normal(first, time).dot(&dir) > 0.0 {
+                    // leaving, so the ray started inside
+                    (0.0, t_first)
+                } else {
+                    let t_exit =
+                        boundary.intersect(origin, dir, t_first + surface_epsilon(first), time)?;
+                    (t_first, t_exit)
+                }
+            }
+            None => (0.0, max_t),
+        };
+        if let Some(grid) = &self.density {
+            let (near, far) = grid.clip(origin, dir)?;
+            start = start.max(near);
+            end = end.min(far);
+        }
+
+        let end = end.min(max_t);
+        if start < end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+
+    /// Share of light surviving the first `dist` units of the ray, exact for a
+    /// homogeneous medium and a ratio tracking estimate through a grid
+    pub fn transmittance(
+        &self,
+        origin: Vector,
+        dir: Vector,
+        dist: f64,
+        time: f64,
+        rng: &mut Rng,
+    ) -> Vector {
+        let (start, end) = match self.segment(origin, dir, dist, time) {
+            Some(segment) => segment,
+            None => return Vector::one(),
+        };
+        let grid = match &self.density {
+            Some(grid) => grid,
+            None => return self.medium.transmittance(end - start),
+        };
+
+        let extinction = self.medium.extinction();
+        let majorant = extinction.x.max(extinction.y).max(extinction.z) * grid.max_value();
+        if majorant <= 0.0 {
+            return Vector::one();
+        }
+        let mut total = Vector::one();
+        let mut t = start;
+        loop {
+            t -= (1.0 - rng.next_f64()).ln() / majorant;
+            if t >= end {
+                return total;
+            }
+            let sigma = extinction * grid.density(origin + dir * t);
+            total = total.scale(&(Vector::one() - sigma / majorant));
+        }
+    }
+
+    /// Picks a point to scatter at within the first `max_t` units of the ray,
+    /// returning its distance and the weight of the light scattered there
+    /// towards the ray origin: scattering coefficient and transmittance up to
+    /// it over the density of the pick. `None` when the ray passes through.
+    pub fn sample_scatter(
+        &self,
+        origin: Vector,
+        dir: Vector,
+        max_t: f64,
+        time: f64,
+        rng: &mut Rng,
+    ) -> Option<(f64, Vector)> {
+        if self.medium.scattering == Vector::zero() {
+            return None;
+        }
+        let (start, end) = self.segment(origin, dir, max_t, time)?;
+        let extinction = self.medium.extinction();
+        let sigma = (extinction.x + extinction.y + extinction.z) / 3.0;
+
+        let grid = match &self.density {
+            Some(grid) => grid,
+            None => {
+                // exponential distance sampling truncated to the segment
+                let (t, pdf) = if sigma > 0.0 {
+                    let reach = 1.0 - (-sigma * (end - start)).exp();
+                    let offset = -(1.0 - rng.next_f64() * reach).ln() / sigma;
+                    (start + offset, sigma * (-sigma * offset).exp() / reach)
+                } else if end.is_finite() {
+                    (start + rng.next_f64() * (end - start), 1.0 / (end - start))
+                } else {
+                    return None;
+                };
+                if pdf <= 0.0 || !t.is_finite() {
+                    return None;
+                }
+                let weight = self
+                    .medium
+                    .transmittance(t - start)
+                    .scale(&self.medium.scattering);
+                return Some((t, weight / pdf));
+            }
+        };
+
+        // delta tracking: tentative collisions against the majorant, kept in
+        // proportion to the real density, leave the albedo as the weight
+        let majorant = sigma * grid.max_value();
+        if majorant <= 0.0 {
+            return None;
+        }
+        let mut t = start;
+        loop {
+            t -= (1.0 - rng.next_f64()).ln() / majorant;
+            if t >= end {
+                return None;
+            }
+            if rng.next_f64() * grid.max_value() < grid.density(origin + dir * t) {
+                return Some((t, self.medium.scattering / sigma));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::assert_delta;
+    use crate::geometry::{sphere::Sphere, vector::Vector};
+    use crate::material::Material;
+
+    use crate::random::Rng;
+
+    use super::{grid::VoxelGrid, HenyeyGreenstein, Medium, Volume};
+
+    #[test]
+    fn phase_normalized() {
+        for g in [-0.6, 0.0, 0.3, 0.8] {
+            let phase = HenyeyGreenstein { g };
+            // integrate over the sphere in cos theta, the phase has no azimuthal dependence
+            let n = 20000;
+            let total: f64 = (0..n)
+                .map(|i| {
+                    let cos = -1.0 + 2.0 * (i as f64 + 0.5) / n as f64;
+                    phase.eval(cos) * 2.0 * PI * 2.0 / n as f64
+                })
+                .sum();
+            assert_delta!(total, 1.0, 1e-3);
+        }
+        assert_delta!(
+            HenyeyGreenstein { g: 0.0 }.eval(0.3),
+            1.0 / (4.0 * PI),
+            1e-12
+        );
+        let forward = HenyeyGreenstein { g: 0.5 };
+        assert!(forward.eval(1.0) > forward.eval(-1.0));
+    }
+
+    #[test]
+    fn beer_lambert() {
+        let medium = Medium::new(Vector::new(0.5, 0.0, 0.0), Vector::new(0.5, 1.0, 0.0), 0.0);
+        let tr = medium.transmittance(2.0);
+        assert_delta!(tr.x, (-2.0f64).exp(), 1e-12);
+        assert_delta!(tr.y, (-2.0f64).exp(), 1e-12);
+        assert_eq!(tr.z, 1.0);
+    }
+
+    #[test]
+    fn segments() {
+        let medium = Medium::new(Vector::zero(), Vector::one(), 0.0);
+        let ball = Volume::new(
+            medium,
+            Some(Box::new(Sphere::new(
+                Vector::new(0.0, 0.0, 5.0),
+                1.0,
+                Material::default(),
+            ))),
+        );
+
+        let (start, end) = ball
+            .segment(Vector::zero(), Vector::one_z(), f64::INFINITY, 0.0)
+            .unwrap();
+        assert_delta!(start, 4.0, 1e-9);
+        assert_delta!(end, 6.0, 1e-9);
+
+        // a surface in front of the exit cuts the segment short
+        let (_, end) = ball
+            .segment(Vector::zero(), Vector::one_z(), 5.5, 0.0)
+            .unwrap();
+        assert_eq!(end, 5.5);
+        assert!(ball
+            .segment(Vector::zero(), Vector::one_z(), 3.0, 0.0)
+            .is_none());
+
+        // starting inside
+        let (start, end) = ball
+            .segment(
+                Vector::new(0.0, 0.0, 5.0),
+                Vector::one_x(),
+                f64::INFINITY,
+                0.0,
+            )
+            .unwrap();
+        assert_eq!(start, 0.0);
+        assert_delta!(end, 1.0, 1e-9);
+
+        assert!(ball
+            .segment(Vector::zero(), Vector::one_x(), f64::INFINITY, 0.0)
+            .is_none());
+
+        let fog = Volume::new(medium, None);
+        assert_eq!(
+            fog.segment(Vector::zero(), Vector::one_x(), 3.0, 0.0),
+            Some((0.0, 3.0))
+        );
+    }
+
+    #[test]
+    fn tracking_matches_homogeneous() {
+        // a constant grid has to agree with the closed form on average
+        let medium = Medium::new(Vector::one() * 0.2, Vector::new(0.6, 0.6, 0.3), 0.0);
+        let grid = VoxelGrid::from_fn(Vector::zero(), Vector::one() * 4.0, (2, 2, 2), |_| 0.5);
+        let cloud = Volume::new(medium, None).with_density(grid);
+        let half = Medium::new(medium.absorption * 0.5, medium.scattering * 0.5, 0.0);
+        let (origin, dir) = (Vector::new(2.0, 2.0, -1.0), Vector::one_z());
+
+        let mut rng = Rng::new(11);
+        let n = 20000;
+        let mut transmittance = Vector::zero();
+        let mut scattered = 0;
+        for _ in 0..n {
+            transmittance += cloud.transmittance(origin, dir, f64::INFINITY, 0.0, &mut rng);
+            if let Some((t, weight)) = cloud.sample_scatter(origin, dir, 3.0, 0.0, &mut rng) {
+                assert!((1.0..3.0).contains(&t));
+                assert_delta!(weight.x, 0.6 / 0.7, 1e-9);
+                scattered += 1;
+            }
+        }
+        let expected = half.transmittance(4.0);
+        assert_delta!(transmittance.x / n as f64, expected.x, 0.01);
+        assert_delta!(transmittance.z / n as f64, expected.z, 0.01);
+        // collisions happen at the grey extinction rate over the two units inside
+        let grey = 0.7 * 0.5;
+        assert_delta!(
+            scattered as f64 / n as f64,
+            1.0 - (-grey * 2.0f64).exp(),
+            0.02
+        );
+    }
+}
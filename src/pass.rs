@@ -0,0 +1,112 @@
+use crate::geometry::vector::Vector;
+
+/// What the tracer shows for each pixel: the final colour, or one of the
+/// debug passes describing what the ray through the pixel centre hit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pass {
+    Beauty,
+    /// Distance to the hit, false coloured from near (blue) to `DEPTH_RANGE` (red)
+    Depth,
+    /// Shading normal, XYZ in -1..1 mapped onto RGB
+    Normal,
+    /// World position, `POSITION_RANGE` either side of the origin mapped onto RGB
+    Position,
+    /// Surface colour without lighting
+    Albedo,
+    /// Index of the shape hit, each in its own colour
+    ShapeIndex,
+    /// Secondary rays traced along the path, false coloured up to `BOUNCE_RANGE`
+    Bounces,
+    /// Share of the point and directional lights the hit can see, false coloured
+    ShadowVisibility,
+    /// Samples traced for the pixel, false coloured up to the most any pixel can get
+    SampleCount,
+}
+
+pub const DEPTH_RANGE: f64 = 12.0;
+pub const POSITION_RANGE: f64 = 5.0;
+pub const BOUNCE_RANGE: f64 = 4.0;
+
+impl Pass {
+    pub const ALL: [Pass; 9] = [
+        Pass::Beauty,
+        Pass::Depth,
+        Pass::Normal,
+        Pass::Position,
+        Pass::Albedo,
+        Pass::ShapeIndex,
+        Pass::Bounces,
+        Pass::ShadowVisibility,
+        Pass::SampleCount,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::Beauty => "beauty",
+            Pass::Depth => "depth",
+            Pass::Normal => "normal",
+            Pass::Position => "position",
+            Pass::Albedo => "albedo",
+            Pass::ShapeIndex => "shape index",
+            Pass::Bounces => "bounce count",
+            Pass::ShadowVisibility => "shadow visibility",
+            Pass::SampleCount => "sample count",
+        }
+    }
+}
+
+/// Scalar in 0..1 as a blue - cyan - green - yellow - red ramp, clamped outside
+pub fn false_color(t: f64) -> Vector {
+    let stops = [
+        Vector::new(0.0, 0.0, 1.0),
+        Vector::new(0.0, 1.0, 1.0),
+        Vector::new(0.0, 1.0, 0.0),
+        Vector::new(1.0, 1.0, 0.0),
+        Vector::new(1.0, 0.0, 0.0),
+    ];
+    let pos = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let i = (pos as usize).min(stops.len() - 2);
+    let f = pos - i as f64;
+    stops[i] * (1.0 - f) + stops[i + 1] * f
+}
+
+/// Distinct colour for category `index`, hues spread by the golden ratio
+pub fn category_color(index: usize) -> Vector {
+    let hue = (index as f64 * 0.618_033_988_75).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    match hue as u32 {
+        0 => Vector::new(1.0, x, 0.0),
+        1 => Vector::new(x, 1.0, 0.0),
+        2 => Vector::new(0.0, 1.0, x),
+        3 => Vector::new(0.0, x, 1.0),
+        4 => Vector::new(x, 0.0, 1.0),
+        _ => Vector::new(1.0, 0.0, x),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::vector::Vector;
+
+    use super::{category_color, false_color};
+
+    #[test]
+    fn ramp() {
+        assert_eq!(false_color(0.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(false_color(0.5), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(false_color(1.0), Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(false_color(7.0), false_color(1.0));
+        assert_eq!(false_color(-1.0), false_color(0.0));
+        assert_eq!(false_color(0.125), Vector::new(0.0, 0.5, 1.0));
+    }
+
+    #[test]
+    fn categories_differ() {
+        for i in 0..8 {
+            for j in i + 1..8 {
+                assert!((category_color(i) - category_color(j)).len() > 0.1);
+            }
+        }
+        assert_eq!(category_color(0), Vector::new(1.0, 0.0, 0.0));
+    }
+}
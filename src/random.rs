@@ -0,0 +1,67 @@
+/// Small, seedable pseudo-random generator (SplitMix64). Good enough for
+/// sampling and fully deterministic for a given seed.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /// Generator for a pixel of a frame, so renders don't depend on pixel order
+    pub fn for_pixel(seed: u64, x: u32, y: u32, frame: u64) -> Rng {
+        let mut rng = Rng::new(seed ^ ((x as u64) << 32 | y as u64));
+        rng.state ^= rng.next_u64() ^ frame.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    #[inline]
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn unit_range() {
+        let mut rng = Rng::new(7);
+        let mut sum = 0.0;
+        for _ in 0..10000 {
+            let v = rng.next_f64();
+            assert!((0.0..1.0).contains(&v));
+            sum += v;
+        }
+        assert!((sum / 10000.0 - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn per_pixel_streams_differ() {
+        let a = Rng::for_pixel(0, 1, 2, 0).next_u64();
+        assert_ne!(a, Rng::for_pixel(0, 2, 1, 0).next_u64());
+        assert_ne!(a, Rng::for_pixel(0, 1, 2, 1).next_u64());
+        assert_eq!(a, Rng::for_pixel(0, 1, 2, 0).next_u64());
+    }
+}
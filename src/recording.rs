@@ -0,0 +1,161 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::framebuffer::Framebuffer;
+use crate::image::{
+    png, ppm,
+    y4m::{Y4mOptions, Y4mWriter},
+};
+
+/// 8-bit frames stored one after another
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SequenceFormat {
+    /// Numbered files, `frame_00000.ppm`, ...
+    Ppm,
+    /// Numbered files, `frame_00000.png`, ...
+    Png,
+    /// A single uncompressed YUV4MPEG2 video file
+    Y4m(Y4mOptions),
+}
+
+/// Destination of an image sequence or video stream
+pub enum FrameSink {
+    Images {
+        dir: PathBuf,
+        png: bool,
+        next: usize,
+    },
+    Y4m(Y4mWriter<BufWriter<File>>),
+}
+
+impl FrameSink {
+    /// Numbered images go into the directory `path`, created if needed;
+    /// a Y4M stream is written to the file `path`.
+    pub fn create(
+        path: &Path,
+        format: SequenceFormat,
+        (width, height): (u32, u32),
+        fps: f64,
+    ) -> io::Result<FrameSink> {
+        match format {
+            SequenceFormat::Ppm | SequenceFormat::Png => {
+                fs::create_dir_all(path)?;
+                Ok(FrameSink::Images {
+                    dir: path.to_path_buf(),
+                    png: format == SequenceFormat::Png,
+                    next: 0,
+                })
+            }
+            SequenceFormat::Y4m(options) => {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                let out = BufWriter::new(File::create(path)?);
+                Ok(FrameSink::Y4m(Y4mWriter::new(
+                    out, width, height, fps, options,
+                )?))
+            }
+        }
+    }
+
+    /// Appends a frame of tightly packed 8-bit RGB pixels.
+    pub fn write_frame(&mut self, (width, height): (u32, u32), rgb: &[u8]) -> io::Result<()> {
+        match self {
+            FrameSink::Images { dir, png, next } => {
+                let extension = if *png { "png" } else { "ppm" };
+                let file = dir.join(format!("frame_{:05}.{}", next, extension));
+                *next += 1;
+                if *png {
+                    png::write(&file, width, height, rgb)
+                } else {
+                    ppm::write(&file, width, height, rgb)
+                }
+            }
+            FrameSink::Y4m(writer) => writer.write_frame(rgb),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            FrameSink::Images { .. } => Ok(()),
+            FrameSink::Y4m(writer) => writer.finish().map(|_| ()),
+        }
+    }
+}
+
+/// Captures the interactive window at a steady frame rate: each frame on
+/// screen is repeated until the next one arrives, so the recording plays
+/// back in step with the wall clock however long frames took to render.
+pub struct Recording {
+    pub path: PathBuf,
+    sink: FrameSink,
+    size: (u32, u32),
+    fps: f64,
+    started: Instant,
+    frames: usize,
+    /// RGB of the frame on screen, written once its time on screen is known
+    shown: Option<Vec<u8>>,
+}
+
+impl Recording {
+    pub fn start(
+        path: &Path,
+        format: SequenceFormat,
+        size: (u32, u32),
+        fps: f64,
+    ) -> io::Result<Recording> {
+        Ok(Recording {
+            path: path.to_path_buf(),
+            sink: FrameSink::create(path, format, size, fps)?,
+            size,
+            fps,
+            started: Instant::now(),
+            frames: 0,
+            shown: None,
+        })
+    }
+
+    /// Adds a new frame going on screen, of the size the recording started with
+    pub fn record(&mut self, frame: &Framebuffer) -> io::Result<()> {
+        assert_eq!(frame.size(), self.size);
+        let rgb = frame.to_rgb();
+        let slot = frame_slot(self.started.elapsed().as_secs_f64(), self.fps);
+        // the slots since the last frame showed that frame
+        let previous = self.shown.take().unwrap_or_else(|| rgb.clone());
+        while self.frames < slot {
+            self.sink.write_frame(self.size, &previous)?;
+            self.frames += 1;
+        }
+        self.shown = Some(rgb);
+        Ok(())
+    }
+
+    /// Closes the recording, returning the number of frames written
+    pub fn finish(mut self) -> io::Result<usize> {
+        if let Some(rgb) = self.shown.take() {
+            self.sink.write_frame(self.size, &rgb)?;
+            self.frames += 1;
+        }
+        self.sink.finish()?;
+        Ok(self.frames)
+    }
+}
+
+/// Index of the frame interval `elapsed` seconds into a recording falls in
+fn frame_slot(elapsed: f64, fps: f64) -> usize {
+    (elapsed * fps).floor() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::frame_slot;
+
+    #[test]
+    fn pacing() {
+        assert_eq!(frame_slot(0.0, 30.0), 0);
+        assert_eq!(frame_slot(0.05, 30.0), 1);
+        assert_eq!(frame_slot(1.0, 24.0), 24);
+    }
+}
@@ -1,106 +1,71 @@
-use sdl2::render::Canvas;
-
 use crate::camera::Camera;
+use crate::display::Display;
+use crate::framebuffer::{Framebuffer, RenderJob};
 use crate::geometry::scene::Scene;
 use crate::tracer::Tracer;
 
-pub struct Renderer {
-    canvas: Canvas<sdl2::video::Window>,
-    pub event_pump: sdl2::EventPump,
+/// Traces frames at a fixed resolution and hands them to a display.
+pub struct Renderer<D: Display> {
+    pub display: D,
+    /// Last frame drawn
+    frame: Framebuffer,
 }
 
-impl Renderer {
-    pub fn initialize(
-        window_width: u32,
-        window_height: u32,
-        render_width: u32,
-        render_height: u32,
-    ) -> Renderer {
-        let sdl_context = sdl2::init().unwrap_or_else(|err| {
-            println!("Cannot initialize SDL! {}", err);
-            std::process::exit(1);
-        });
-
-        let video_subsystem = sdl_context
-            .video()
-            .expect("Cannot initialize video for SDL!");
-
-        let mut window = video_subsystem
-            .window("ray tracer", window_width, window_height)
-            .resizable()
-            .position_centered()
-            .build()
-            .expect("Cannot initialize video mode for SDL! {}");
-
-        window
-            .set_title("Ray Tracer")
-            .expect("Failed to set window title!");
-
-        let event_pump = sdl_context
-            .event_pump()
-            .expect("Cannot initialize event pump for SDL!");
-
-        let mut canvas = window
-            .into_canvas()
-            .present_vsync()
-            .build()
-            .expect("Failed to get canvas from window!");
-
-        canvas
-            .set_integer_scale(true)
-            .expect("Failed to set integer scale!");
-        canvas
-            .set_logical_size(render_width, render_height)
-            .expect("Failed to set logical size for the canvas!");
-
-        Renderer { canvas, event_pump }
+impl<D: Display> Renderer<D> {
+    pub fn new(display: D, render_width: u32, render_height: u32) -> Renderer<D> {
+        Renderer {
+            display,
+            frame: Framebuffer::new(render_width, render_height),
+        }
     }
 
-    pub fn draw_frame(&mut self, tracer: &Tracer, camera: &Camera, scene: &Scene) {
-        let (w, h) = self.canvas.logical_size();
-
-        let tex_creator = self.canvas.texture_creator();
-        let mut tex = tex_creator
-            .create_texture_streaming(None, w, h)
-            .expect("Cannot create texture for rendering!");
-
-        tex.with_lock(None, |buf, stride| {
-            self.render(buf, stride, tracer, camera, scene)
-        })
-        .expect("Cannot render frame into texture!");
-
-        self.canvas
-            .copy(&tex, None, None)
-            .expect("Cannot copy texture to framebuffer!");
-        self.canvas.present();
+    pub fn draw_frame(&mut self, tracer: &Tracer, camera: &Camera, scene: &Scene, time: f64) {
+        let job = RenderJob {
+            tracer,
+            camera,
+            scene,
+            time,
+        };
+        job.render(&mut self.frame);
+        self.display.present(&self.frame);
     }
 
-    fn render(
-        &self,
-        buf: &mut [u8],
-        stride: usize,
-        tracer: &Tracer,
-        camera: &Camera,
-        scene: &Scene,
-    ) {
-        let (w, h) = self.canvas.logical_size();
-
-        for y in 0..h as u32 {
-            for x in 0..w as u32 {
-                let pos = (y * stride as u32 + x * 4) as usize;
-                let xp = (x as f64 + 0.5) / (w as f64 / 2.0) - 1.0;
-                let yp = (y as f64 + 0.5) / (h as f64 / 2.0) - 1.0;
-                let (r, g, b) = tracer.trace(xp, -yp, camera, scene, 1).spread(); // vertical axis is inverted on screen
-
-                let r = (r * 256.0).clamp(0.0, 255.0) as u8; // TODO: Fix hue shifting issue
-                let g = (g * 256.0).clamp(0.0, 255.0) as u8;
-                let b = (b * 256.0).clamp(0.0, 255.0) as u8;
+    /// The frame on screen
+    pub fn frame(&self) -> &Framebuffer {
+        &self.frame
+    }
+}
 
-                buf[pos + 0] = b; // b
-                buf[pos + 1] = g; // g
-                buf[pos + 2] = r; // r
-                buf[pos + 3] = 255; // a?
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::camera::Camera;
+    use crate::display::memory::MemoryDisplay;
+    use crate::geometry::{scene::Scene, vector::Vector};
+    use crate::tracer::Tracer;
+
+    use super::Renderer;
+
+    #[test]
+    fn draws_into_memory() {
+        let mut renderer = Renderer::new(MemoryDisplay::new(), 16, 12);
+        let camera = Camera::new(
+            Vector::zero(),
+            Vector::one_z(),
+            Vector::one_y(),
+            PI / 3.0,
+            4.0 / 3.0,
+        );
+        renderer.draw_frame(&Tracer::new(), &camera, &Scene::new(), 0.0);
+        renderer.draw_frame(&Tracer::new(), &camera, &Scene::new(), 0.5);
+
+        let display = &renderer.display;
+        assert_eq!(display.frames_presented, 2);
+        let last = display.last_frame.as_ref().unwrap();
+        assert_eq!(last.size(), (16, 12));
+        assert_eq!(last, renderer.frame());
+        // the demo room is lit, so some pixel is not black
+        assert!(last.to_rgb().iter().any(|&c| c > 0));
     }
 }
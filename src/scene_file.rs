@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::bookmarks::Bookmark;
+use crate::bsdf::{conductor::Conductor, dielectric::Dielectric};
+use crate::geometry::{
+    directional_light::DirectionalLight,
+    environment::Environment,
+    planes::{PlaneXY, PlaneXZ, PlaneYZ},
+    point_light::PointLight,
+    scene::Scene,
+    shape::Shape,
+    sky::Sky,
+    sphere::Sphere,
+    triangle::Triangle,
+    vector::Vector,
+};
+use crate::material::Material;
+use crate::texture::procedural::Checker;
+use crate::tracer::MAX_DEPTH;
+
+/// Scene described in a text file, one item per line, a keyword followed by
+/// its values, and anything after a `#` is a comment. Materials are named
+/// before the shapes that use them; any of them can end in `depth N` to follow
+/// at most N bounces from its surfaces, N being at most `MAX_DEPTH`.
+///
+/// ```text
+/// # position, forward direction, vertical field of view in degrees and
+/// # optionally the lens radius and focus distance
+/// camera 0 1 -3  0 0 1  60  0.05 4
+/// ambient 0.05 0.05 0.05
+/// # sun elevation and azimuth in radians, turbidity
+/// sky 0.6 3.9 3
+/// # equirectangular HDR image next to the scene file, optional intensity
+/// environment studio.hdr 1.5
+/// # point light: position, colour, power
+/// light 0 4 2  1 1 1  3
+/// # directional light: direction towards the light, colour
+/// sun -1 2 -1  1 0.9 0.8
+/// material red diffuse 0.9 0.1 0.1 0.2      # colour, optional reflectivity
+/// material mirror diffuse 1 1 1 0.95 depth 12
+/// material floor checker 1 1 1  0.2 0.2 0.2  1
+/// material glass glass 1.5 0                # index of refraction, optional roughness
+/// material copper metal copper 0.2          # gold, copper or aluminium, optional roughness
+/// material lamp emissive 1 0.9 0.8 5        # colour, strength
+/// sphere 0 0.5 4  1  red                    # centre, radius
+/// plane +y -1  -10 10  -10 10  floor        # facing, offset, ranges of the other two axes
+/// # corners, the front faces where (v1 - v0) x (v2 - v0) points
+/// triangle -1 0 5  0 2 5  1 0 5  glass
+/// ```
+pub struct SceneFile {
+    pub scene: Scene,
+    /// Viewpoint to start from, if the file has one
+    pub camera: Option<Bookmark>,
+}
+
+/// Named material, built again for every shape that uses it
+#[derive(Clone, Copy, Debug)]
+enum MaterialSpec {
+    Diffuse(Vector, f64),
+    Checker(Vector, Vector, f64),
+    Glass(f64, f64),
+    Metal(&'static str, f64),
+    Emissive(Vector, f64),
+}
+
+impl MaterialSpec {
+    fn build(&self) -> Material {
+        match *self {
+            MaterialSpec::Diffuse(color, reflectivity) => Material {
+                color,
+                refletivity_index: reflectivity,
+                ..Default::default()
+            },
+            MaterialSpec::Checker(even, odd, size) => Material {
+                texture: Some(Box::new(Checker::new(even, odd, size))),
+                ..Default::default()
+            },
+            MaterialSpec::Glass(eta, roughness) => Material {
+                bsdf: Some(Box::new(Dielectric::new(eta, roughness))),
+                ..Default::default()
+            },
+            MaterialSpec::Metal(name, roughness) => {
+                let conductor = match name {
+                    "gold" => Conductor::gold(roughness),
+                    "copper" => Conductor::copper(roughness),
+                    _ => Conductor::aluminium(roughness),
+                };
+                Material {
+                    bsdf: Some(Box::new(conductor)),
+                    ..Default::default()
+                }
+            }
+            MaterialSpec::Emissive(color, strength) => Material {
+                color: Vector::zero(),
+                emission: color,
+                emission_strength: strength,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl SceneFile {
+    /// Reads the scene at `path`; environment maps are found next to it.
+    pub fn load(path: &Path) -> io::Result<SceneFile> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&fs::read_to_string(path)?, dir)
+    }
+
+    pub fn parse(text: &str, dir: &Path) -> io::Result<SceneFile> {
+        let mut scene = Scene::empty();
+        let mut camera = None;
+        let mut materials: HashMap<&str, (MaterialSpec, Option<u32>)> = HashMap::new();
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let invalid = |what: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid {} on line {}", what, line_no + 1),
+                )
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (key, rest) = (fields[0], &fields[1..]);
+            // numbers, `optional` of them may be left out at the end
+            let numbers =
+                |fields: &[&str], count: usize, optional: usize| -> io::Result<Vec<f64>> {
+                    if fields.len() > count || fields.len() + optional < count {
+                        return Err(invalid(key));
+                    }
+                    fields
+                        .iter()
+                        .map(|field| field.parse().map_err(|_| invalid(key)))
+                        .collect()
+                };
+            let vector = |v: &[f64]| Vector::new(v[0], v[1], v[2]);
+            let material = |name: &str| {
+                materials
+                    .get(name)
+                    .map(|(spec, max_depth)| Material {
+                        max_depth: *max_depth,
+                        ..spec.build()
+                    })
+                    .ok_or_else(|| invalid("material name"))
+            };
+
+            match key {
+                "camera" => {
+                    let v = numbers(rest, 9, 2)?;
+                    let (aperture, focus_distance) = match v[7..] {
+                        [] => (0.0, 1.0),
+                        [aperture, focus] if aperture >= 0.0 && focus > 0.0 => (aperture, focus),
+                        _ => return Err(invalid(key)),
+                    };
+                    camera = Some(Bookmark {
+                        pos: vector(&v[0..3]),
+                        forward: vector(&v[3..6]),
+                        up: Vector::one_y(),
+                        vfov: v[6].to_radians(),
+                        aperture,
+                        focus_distance,
+                    });
+                }
+                "ambient" => scene.ambient_light = vector(&numbers(rest, 3, 0)?),
+                "sky" => {
+                    let v = numbers(rest, 3, 0)?;
+                    scene.sky = Some(Sky::new(v[0], v[1], v[2]));
+                }
+                "environment" => {
+                    let (file, intensity) = match rest {
+                        [file] => (file, 1.0),
+                        [file, intensity] => (file, numbers(&[intensity], 1, 0)?[0]),
+                        _ => return Err(invalid(key)),
+                    };
+                    let mut environment = Environment::load(&dir.join(file))?;
+                    environment.intensity = intensity;
+                    scene.environment = Some(environment);
+                }
+                "light" => {
+                    let v = numbers(rest, 7, 0)?;
+                    scene.point_lights.push(PointLight::new(
+                        vector(&v[0..3]),
+                        vector(&v[3..6]),
+                        v[6],
+                    ));
+                }
+                "sun" => {
+                    let v = numbers(rest, 6, 0)?;
+                    scene
+                        .directional_lights
+                        .push(DirectionalLight::new(vector(&v[0..3]), vector(&v[3..6])));
+                }
+                "material" => {
+                    let (name, kind, values) = match rest {
+                        [name, kind, values @ ..] => (*name, *kind, values),
+                        _ => return Err(invalid(key)),
+                    };
+                    let (values, max_depth) = match values {
+                        [values @ .., "depth", depth] => (
+                            values,
+                            Some(
+                                depth
+                                    .parse()
+                                    .ok()
+                                    .filter(|&depth| depth <= MAX_DEPTH)
+                                    .ok_or_else(|| invalid("material depth"))?,
+                            ),
+                        ),
+                        _ => (values, None),
+                    };
+                    let spec = match kind {
+                        "diffuse" => {
+                            let v = numbers(values, 4, 1)?;
+                            MaterialSpec::Diffuse(vector(&v), v.get(3).copied().unwrap_or(0.0))
+                        }
+                        "checker" => {
+                            let v = numbers(values, 7, 0)?;
+                            MaterialSpec::Checker(vector(&v[0..3]), vector(&v[3..6]), v[6])
+                        }
+                        "glass" => {
+                            let v = numbers(values, 2, 1)?;
+                            MaterialSpec::Glass(v[0], v.get(1).copied().unwrap_or(0.0))
+                        }
+                        "metal" => {
+                            let (metal, roughness) = match values {
+                                [metal, roughness @ ..] => (*metal, numbers(roughness, 1, 1)?),
+                                _ => return Err(invalid(key)),
+                            };
+                            let metal = ["gold", "copper", "aluminium"]
+                                .into_iter()
+                                .find(|name| *name == metal)
+                                .ok_or_else(|| invalid("metal"))?;
+                            MaterialSpec::Metal(metal, roughness.first().copied().unwrap_or(0.0))
+                        }
+                        "emissive" => {
+                            let v = numbers(values, 4, 0)?;
+                            MaterialSpec::Emissive(vector(&v), v[3])
+                        }
+                        _ => return Err(invalid("material kind")),
+                    };
+                    materials.insert(name, (spec, max_depth));
+                }
+                "sphere" => {
+                    let (values, name) = split_last(rest).ok_or_else(|| invalid(key))?;
+                    let v = numbers(values, 4, 0)?;
+                    let sphere = Sphere::new(vector(&v), v[3], material(name)?);
+                    scene.shapes.push(Box::new(sphere));
+                }
+                "plane" => {
+                    let (facing, values, name) = match rest {
+                        [facing, values @ .., name] => (*facing, values, *name),
+                        _ => return Err(invalid(key)),
+                    };
+                    let v = numbers(values, 5, 0)?;
+                    let (offset, a, b) = (v[0], (v[1], v[2]), (v[3], v[4]));
+                    let negative = facing.starts_with('-');
+                    let shape: Box<dyn Shape> = match facing {
+                        "+x" | "-x" => {
+                            Box::new(PlaneYZ::new(offset, negative, a, b, material(name)?))
+                        }
+                        "+y" | "-y" => {
+                            Box::new(PlaneXZ::new(offset, negative, a, b, material(name)?))
+                        }
+                        "+z" | "-z" => {
+                            Box::new(PlaneXY::new(offset, negative, a, b, material(name)?))
+                        }
+                        _ => return Err(invalid("plane facing")),
+                    };
+                    scene.shapes.push(shape);
+                }
+                "triangle" => {
+                    let (values, name) = split_last(rest).ok_or_else(|| invalid(key))?;
+                    let v = numbers(values, 9, 0)?;
+                    let triangle = Triangle::new(
+                        [vector(&v[0..3]), vector(&v[3..6]), vector(&v[6..9])],
+                        [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+                        material(name)?,
+                    );
+                    scene.shapes.push(Box::new(triangle));
+                }
+                _ => return Err(invalid("scene item")),
+            }
+        }
+
+        Ok(SceneFile { scene, camera })
+    }
+}
+
+/// Values and the material name closing a shape line
+fn split_last<'a>(fields: &'a [&'a str]) -> Option<(&'a [&'a str], &'a str)> {
+    fields.split_last().map(|(name, values)| (values, *name))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::geometry::vector::Vector;
+
+    use super::SceneFile;
+
+    #[test]
+    fn parses_items() {
+        let text = "# test scene\n\
+                    camera 0 1 -3  0 0 1  60  0.05 4\n\
+                    ambient 0.1 0.1 0.1\n\
+                    light 0 4 2  1 1 1  3\n\
+                    sun 0 1 0  1 1 1\n\
+                    material red diffuse 0.9 0.1 0.1  # colour\n\
+                    material mirror diffuse 1 1 1 0.8 depth 12\n\
+                    material glass glass 1.5\n\
+                    material copper metal copper 0.2\n\
+                    material lamp emissive 1 1 1 5\n\
+                    sphere 0 0.5 4  1  red\n\
+                    sphere 2 0.5 4  1  glass\n\
+                    plane +y -1  -10 10  -10 10  mirror\n\
+                    triangle -1 0 5  0 2 5  1 0 5  copper\n\
+                    sphere 0 5 4 0.5 lamp\n";
+        let file = SceneFile::parse(text, Path::new("")).unwrap();
+        let scene = &file.scene;
+        assert_eq!(scene.shapes.len(), 5);
+        assert_eq!(scene.point_lights.len(), 1);
+        assert_eq!(scene.directional_lights.len(), 1);
+        assert_eq!(scene.ambient_light, Vector::one() * 0.1);
+        assert_eq!(scene.emitters().count(), 1);
+        assert_eq!(
+            scene.shapes[0].get_material().color,
+            Vector::new(0.9, 0.1, 0.1)
+        );
+        assert_eq!(scene.shapes[2].get_material().refletivity_index, 0.8);
+        assert_eq!(scene.shapes[2].get_material().max_depth, Some(12));
+        assert_eq!(scene.shapes[0].get_material().max_depth, None);
+        assert!(scene.shapes[1].get_material().bsdf.is_some());
+
+        let camera = file.camera.unwrap();
+        assert_eq!(camera.pos, Vector::new(0.0, 1.0, -3.0));
+        assert_eq!(camera.vfov, 60f64.to_radians());
+        assert_eq!((camera.aperture, camera.focus_distance), (0.05, 4.0));
+
+        // the floor faces up: rays from above hit it, rays from below don't
+        let floor = &scene.shapes[2];
+        assert!(floor
+            .intersect(Vector::zero(), -Vector::one_y(), 0.0, 0.0)
+            .is_some());
+        assert!(floor
+            .intersect(Vector::new(0.0, -2.0, 0.0), Vector::one_y(), 0.0, 0.0)
+            .is_none());
+
+        // the triangle faces the camera of the file
+        let centre = Vector::new(0.0, 2.0 / 3.0, 5.0);
+        assert!(scene.shapes[3]
+            .intersect(camera.pos, (centre - camera.pos).normalized(), 0.0, 0.0)
+            .is_some());
+    }
+
+    #[test]
+    fn invalid_items() {
+        let parse = |text: &str| SceneFile::parse(text, Path::new(""));
+        assert!(parse("sphere 0 0 0 1 undefined\n").is_err());
+        assert!(parse("material red diffuse 1 0\n").is_err());
+        assert!(parse("material red paint 1 0 0\n").is_err());
+        assert!(parse("material m metal silver\n").is_err());
+        assert!(parse("material m glass 1.5 depth many\n").is_err());
+        assert!(parse("material m glass 1.5 depth 100000\n").is_err());
+        assert!(parse("material red diffuse 1 0 0\nplane +w 0 -1 1 -1 1 red\n").is_err());
+        assert!(parse("light 0 0 0 1 1 1\n").is_err());
+        assert!(parse("camera 0 0 0 0 0 1 60 0.05\n").is_err());
+        assert!(parse("teapot\n").is_err());
+        assert!(parse("").unwrap().scene.shapes.is_empty());
+    }
+}
@@ -0,0 +1,424 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::bookmarks::Bookmark;
+use crate::camera::Camera;
+use crate::framebuffer::{Framebuffer, RenderJob};
+use crate::geometry::{scene::Scene, vector::Vector};
+use crate::image::png;
+use crate::pass::Pass;
+use crate::tracer::{Integrator, Tracer};
+
+/// Everything needed to render a captured frame again: the scene and the
+/// viewpoint, the animation time, the render settings and the lighting and
+/// volume toggles.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Screenshot {
+    /// Scene file the frame shows, the demo scene when absent
+    pub scene: Option<PathBuf>,
+    pub camera: Bookmark,
+    pub time: f64,
+    pub size: (u32, u32),
+    pub samples_per_pixel: u32,
+    pub seed: u64,
+    pub pass: Pass,
+    pub integrator: Integrator,
+    pub max_depth: u32,
+    pub min_throughput: f64,
+    /// Noise threshold and sample cap of adaptive sampling, when it is on
+    pub adaptive: Option<(f64, u32)>,
+    /// Elevation, azimuth, turbidity and intensity of the sky, when it is on
+    pub sky: Option<(f64, f64, f64, f64)>,
+    /// Rotation and intensity of the environment map, when one is loaded
+    pub environment: Option<(f64, f64)>,
+    /// Homogeneous fog volumes in the scene
+    pub fog: bool,
+    /// Voxel grid volumes in the scene
+    pub smoke: bool,
+}
+
+impl Screenshot {
+    pub fn capture(
+        camera: &Camera,
+        time: f64,
+        size: (u32, u32),
+        tracer: &Tracer,
+        scene: &Scene,
+    ) -> Screenshot {
+        Screenshot {
+            scene: None,
+            camera: Bookmark::from_camera(camera),
+            time,
+            size,
+            samples_per_pixel: tracer.samples_per_pixel,
+            seed: tracer.seed,
+            pass: tracer.pass,
+            integrator: tracer.integrator,
+            max_depth: tracer.max_depth,
+            min_throughput: tracer.min_throughput,
+            adaptive: (tracer.noise_threshold > 0.0)
+                .then_some((tracer.noise_threshold, tracer.max_samples_per_pixel)),
+            sky: scene
+                .sky
+                .as_ref()
+                .map(|sky| (sky.elevation, sky.azimuth, sky.turbidity, sky.intensity)),
+            environment: scene
+                .environment
+                .as_ref()
+                .map(|env| (env.rotation, env.intensity)),
+            fog: scene.volumes.iter().any(|v| v.density.is_none()),
+            smoke: scene.volumes.iter().any(|v| v.density.is_some()),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Screenshot> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+
+    /// One setting per line, a keyword followed by its values; `fog` and
+    /// `smoke` appear only when enabled, `lens` only with an open aperture and
+    /// `scene`, `noise`, `sky` and `environment` only when present.
+    pub fn serialize(&self) -> String {
+        let c = &self.camera;
+        let mut out = format!(
+            "size {} {}\ntime {}\nsamples {}\nseed {}\npass {}\n\
+             integrator {}\ndepth {}\ncutoff {}\n\
+             camera {} {} {} {} {} {} {} {} {} {}\n",
+            self.size.0,
+            self.size.1,
+            self.time,
+            self.samples_per_pixel,
+            self.seed,
+            self.pass.name(),
+            self.integrator.name(),
+            self.max_depth,
+            self.min_throughput,
+            c.pos.x,
+            c.pos.y,
+            c.pos.z,
+            c.forward.x,
+            c.forward.y,
+            c.forward.z,
+            c.up.x,
+            c.up.y,
+            c.up.z,
+            c.vfov
+        );
+        if c.aperture > 0.0 {
+            out += &format!("lens {} {}\n", c.aperture, c.focus_distance);
+        }
+        if let Some(scene) = &self.scene {
+            out += &format!("scene {}\n", scene.display());
+        }
+        if let Some((threshold, max_samples)) = self.adaptive {
+            out += &format!("noise {} {}\n", threshold, max_samples);
+        }
+        if let Some((elevation, azimuth, turbidity, intensity)) = self.sky {
+            out += &format!(
+                "sky {} {} {} {}\n",
+                elevation, azimuth, turbidity, intensity
+            );
+        }
+        if let Some((rotation, intensity)) = self.environment {
+            out += &format!("environment {} {}\n", rotation, intensity);
+        }
+        if self.fog {
+            out += "fog\n";
+        }
+        if self.smoke {
+            out += "smoke\n";
+        }
+        out
+    }
+
+    pub fn parse(text: &str) -> io::Result<Screenshot> {
+        let mut shot = Screenshot {
+            scene: None,
+            camera: Bookmark {
+                pos: Vector::zero(),
+                forward: Vector::one_z(),
+                up: Vector::one_y(),
+                vfov: 0.0,
+                aperture: 0.0,
+                focus_distance: 1.0,
+            },
+            time: 0.0,
+            size: (0, 0),
+            samples_per_pixel: 1,
+            seed: 0,
+            pass: Pass::Beauty,
+            integrator: Integrator::Path,
+            // as traced before these settings were saved
+            max_depth: 1,
+            min_throughput: 0.0,
+            adaptive: None,
+            sky: None,
+            environment: None,
+            fog: false,
+            smoke: false,
+        };
+        let mut has_camera = false;
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid screenshot setting on line {}", line_no + 1),
+                )
+            };
+            let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let numbers = |count: usize| -> io::Result<Vec<f64>> {
+                let v: Vec<f64> = rest
+                    .split_whitespace()
+                    .map(|field| field.parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| invalid())?;
+                if v.len() == count {
+                    Ok(v)
+                } else {
+                    Err(invalid())
+                }
+            };
+
+            match key {
+                "size" => {
+                    let v = numbers(2)?;
+                    if v[0] < 1.0 || v[1] < 1.0 {
+                        return Err(invalid());
+                    }
+                    shot.size = (v[0] as u32, v[1] as u32);
+                }
+                "time" => shot.time = numbers(1)?[0],
+                "samples" => {
+                    shot.samples_per_pixel = rest.parse().map_err(|_| invalid())?;
+                    if shot.samples_per_pixel == 0 {
+                        return Err(invalid());
+                    }
+                }
+                "seed" => shot.seed = rest.parse().map_err(|_| invalid())?,
+                "pass" => {
+                    shot.pass = *Pass::ALL
+                        .iter()
+                        .find(|pass| pass.name() == rest.trim())
+                        .ok_or_else(invalid)?
+                }
+                "integrator" => {
+                    shot.integrator = *Integrator::ALL
+                        .iter()
+                        .find(|integrator| integrator.name() == rest.trim())
+                        .ok_or_else(invalid)?
+                }
+                "depth" => shot.max_depth = rest.parse().map_err(|_| invalid())?,
+                "cutoff" => shot.min_throughput = numbers(1)?[0],
+                "camera" => {
+                    let v = numbers(10)?;
+                    shot.camera = Bookmark {
+                        pos: Vector::new(v[0], v[1], v[2]),
+                        forward: Vector::new(v[3], v[4], v[5]),
+                        up: Vector::new(v[6], v[7], v[8]),
+                        vfov: v[9],
+                        ..shot.camera
+                    };
+                    has_camera = true;
+                }
+                "lens" => {
+                    let v = numbers(2)?;
+                    if v[0] < 0.0 || v[1] <= 0.0 {
+                        return Err(invalid());
+                    }
+                    shot.camera.aperture = v[0];
+                    shot.camera.focus_distance = v[1];
+                }
+                "noise" => {
+                    let v = numbers(2)?;
+                    if v[0] <= 0.0 || v[1] < 1.0 {
+                        return Err(invalid());
+                    }
+                    shot.adaptive = Some((v[0], v[1] as u32));
+                }
+                "scene" if !rest.is_empty() => shot.scene = Some(PathBuf::from(rest.trim())),
+                "sky" => {
+                    let v = numbers(4)?;
+                    shot.sky = Some((v[0], v[1], v[2], v[3]));
+                }
+                "environment" => {
+                    let v = numbers(2)?;
+                    shot.environment = Some((v[0], v[1]));
+                }
+                "fog" => shot.fog = true,
+                "smoke" => shot.smoke = true,
+                _ => return Err(invalid()),
+            }
+        }
+
+        if !has_camera || shot.size.0 == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Screenshot settings need a camera and a size",
+            ));
+        }
+        Ok(shot)
+    }
+
+    /// Camera with the aspect ratio of the captured frame
+    pub fn camera(&self) -> Camera {
+        let mut camera = Camera::new(Vector::zero(), Vector::one_z(), Vector::one_y(), 0.0, 1.0);
+        camera.ar = self.size.0 as f64 / self.size.1 as f64;
+        self.camera.apply(&mut camera);
+        camera
+    }
+
+    /// Tracer set up with the captured render settings
+    pub fn tracer(&self) -> Tracer {
+        let mut tracer = Tracer::new();
+        tracer.samples_per_pixel = self.samples_per_pixel;
+        tracer.seed = self.seed;
+        tracer.pass = self.pass;
+        tracer.integrator = self.integrator;
+        tracer.max_depth = self.max_depth;
+        tracer.min_throughput = self.min_throughput;
+        if let Some((threshold, max_samples)) = self.adaptive {
+            tracer.noise_threshold = threshold;
+            tracer.max_samples_per_pixel = max_samples;
+        }
+        tracer
+    }
+
+    /// Traces the captured view of `scene` at the captured size and settings
+    pub fn render(&self, scene: &Scene) -> Framebuffer {
+        let mut frame = Framebuffer::new(self.size.0, self.size.1);
+        RenderJob {
+            tracer: &self.tracer(),
+            camera: &self.camera(),
+            scene,
+            time: self.time,
+        }
+        .render(&mut frame);
+        frame
+    }
+}
+
+/// Free path in `dir` named after the current UTC time, such as
+/// `screenshot_20240131_235959.png`; a counter is added when that name is
+/// already taken. An empty `extension` names a directory.
+pub fn timestamped_path(dir: &Path, prefix: &str, extension: &str) -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let stem = format!("{}_{}", prefix, timestamp(secs));
+    let mut path = dir.join(&stem).with_extension(extension);
+    let mut n = 2;
+    while path.exists() {
+        path = dir
+            .join(format!("{}_{}", stem, n))
+            .with_extension(extension);
+        n += 1;
+    }
+    path
+}
+
+/// `YYYYMMDD_HHMMSS` for seconds since the Unix epoch, UTC
+fn timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // civil date from a day count, with years starting in March
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}_{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+pub fn save_png(path: &Path, frame: &Framebuffer) -> io::Result<()> {
+    let (width, height) = frame.size();
+    png::write(path, width, height, &frame.to_rgb())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::geometry::vector::Vector;
+    use crate::pass::Pass;
+    use crate::tracer::Integrator;
+
+    use super::{timestamp, Screenshot};
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(timestamp(0), "19700101_000000");
+        assert_eq!(timestamp(1_700_000_000), "20231114_221320");
+        assert_eq!(timestamp(951_825_600), "20000229_120000");
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let text = "size 1280 960\ntime 2.5\nsamples 16\nseed 3\npass shape index\n\
+                    integrator ao\ndepth 4\ncutoff 0.05\n\
+                    camera 1 2 3 0 0 1 0 1 0 1.0471975511965976\n\
+                    lens 0.05 3.5\n\
+                    scene scenes/room.txt\n\
+                    noise 0.02 64\n\
+                    sky 0.6 3.9 3 1\nsmoke\n";
+        let shot = Screenshot::parse(text).unwrap();
+        assert_eq!(shot.size, (1280, 960));
+        assert_eq!(shot.samples_per_pixel, 16);
+        assert_eq!(shot.pass, Pass::ShapeIndex);
+        assert_eq!(shot.integrator, Integrator::AmbientOcclusion);
+        assert_eq!(shot.max_depth, 4);
+        assert_eq!(shot.min_throughput, 0.05);
+        assert_eq!(shot.adaptive, Some((0.02, 64)));
+        assert_eq!(shot.tracer().max_samples_per_pixel, 64);
+        assert_eq!(shot.camera.pos, Vector::new(1.0, 2.0, 3.0));
+        assert_eq!(shot.camera.aperture, 0.05);
+        assert_eq!(shot.camera().focus_distance, 3.5);
+        assert_eq!(shot.sky, Some((0.6, 3.9, 3.0, 1.0)));
+        assert_eq!(shot.environment, None);
+        assert_eq!(shot.scene, Some(PathBuf::from("scenes/room.txt")));
+        assert!(shot.smoke && !shot.fog);
+        assert_eq!(shot.serialize(), text);
+        assert_eq!(Screenshot::parse(&shot.serialize()).unwrap(), shot);
+
+        let camera = shot.camera();
+        assert_eq!(camera.ar, 1280.0 / 960.0);
+        assert_eq!(camera.forward, Vector::one_z());
+    }
+
+    #[test]
+    fn invalid_settings() {
+        assert!(Screenshot::parse("size 640 480\n").is_err());
+        assert!(Screenshot::parse("camera 0 0 0 0 0 1 0 1 0 1\n").is_err());
+        assert!(Screenshot::parse("size 640 480\ncamera 0 0 0 0 0 1 0 1\n").is_err());
+        assert!(Screenshot::parse("size 640 480\ncamera 0 0 0 0 0 1 0 1 0 1\npass x\n").is_err());
+        assert!(Screenshot::parse("size 640 480\ncamera 0 0 0 0 0 1 0 1 0 1\nzoom 2\n").is_err());
+        assert!(
+            Screenshot::parse("size 640 480\ncamera 0 0 0 0 0 1 0 1 0 1\nnoise 0 64\n").is_err()
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::io;
+use std::path::Path;
+
+use crate::geometry::vector::Vector;
+use crate::image::Image;
+
+pub use super::mipmap::Wrap;
+use super::{mipmap::MipMap, TexCoord, Texture};
+
+/// Image mapped onto the surface UVs. Lookups are trilinear over a mip-map
+/// when the hit carries a footprint, bilinear from the full image otherwise.
+/// One image repeat spans `scale` UV units. Surfaces without UVs sample the image origin.
+pub struct ImageTexture {
+    pub mipmap: MipMap,
+    pub scale: (f64, f64),
+}
+
+impl ImageTexture {
+    pub fn new(image: Image, wrap: Wrap) -> ImageTexture {
+        ImageTexture {
+            mipmap: MipMap::new(image, wrap),
+            scale: (1.0, 1.0),
+        }
+    }
+
+    pub fn load(path: &Path, wrap: Wrap) -> io::Result<ImageTexture> {
+        Ok(ImageTexture::new(Image::load(path)?, wrap))
+    }
+
+    pub fn sample(&self, u: f64, v: f64) -> Vector {
+        self.mipmap.bilinear(0, u, v)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, coord: &TexCoord) -> Vector {
+        let (u, v) = coord.uv.unwrap_or((0.0, 0.0));
+        let (u, v) = (u / self.scale.0, v / self.scale.1);
+
+        match coord.footprint {
+            Some(footprint) => {
+                let (du, dv) = footprint.uv_width();
+                self.mipmap
+                    .trilinear(u, v, (du / self.scale.0).max(dv / self.scale.1))
+            }
+            None => self.sample(u, v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::vector::Vector;
+    use crate::image::Image;
+    use crate::texture::{Footprint, TexCoord, Texture};
+
+    use super::{ImageTexture, Wrap};
+
+    fn image() -> Image {
+        // 2x1: black, white
+        let mut image = Image::new(2, 1);
+        image.set(1, 0, Vector::one());
+        image
+    }
+
+    #[test]
+    fn texel_centres() {
+        let tex = ImageTexture::new(image(), Wrap::Clamp);
+        assert_eq!(tex.sample(0.25, 0.5), Vector::zero());
+        assert_eq!(tex.sample(0.75, 0.5), Vector::one());
+    }
+
+    #[test]
+    fn bilinear() {
+        let tex = ImageTexture::new(image(), Wrap::Clamp);
+        assert_eq!(tex.sample(0.5, 0.5), Vector::one() * 0.5);
+    }
+
+    #[test]
+    fn wrap_modes() {
+        let repeat = ImageTexture::new(image(), Wrap::Repeat);
+        let clamp = ImageTexture::new(image(), Wrap::Clamp);
+
+        // halfway between the last and the first texel when repeating
+        assert_eq!(repeat.sample(1.0, 0.5), Vector::one() * 0.5);
+        assert_eq!(clamp.sample(1.0, 0.5), Vector::one());
+        assert_eq!(repeat.sample(1.25, 0.5), Vector::zero());
+    }
+
+    #[test]
+    fn footprint_blurs() {
+        let tex = ImageTexture::new(image(), Wrap::Repeat);
+        let mut coord = TexCoord::new(Vector::zero(), Some((0.75, 0.5)), 0.0);
+        assert_eq!(tex.value(&coord), Vector::one());
+
+        coord.footprint = Some(Footprint {
+            dp_dx: Vector::zero(),
+            dp_dy: Vector::zero(),
+            duv_dx: (2.0, 0.0),
+            duv_dy: (0.0, 2.0),
+        });
+        assert_eq!(tex.value(&coord), Vector::one() * 0.5);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::geometry::vector::Vector;
+use crate::image::Image;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wrap {
+    Repeat,
+    Clamp,
+}
+
+/// Image pyramid, each level half the size of the previous one down to 1x1.
+pub struct MipMap {
+    levels: Vec<Image>,
+    pub wrap: Wrap,
+}
+
+impl MipMap {
+    pub fn new(image: Image, wrap: Wrap) -> MipMap {
+        let mut levels = vec![image];
+        loop {
+            let prev = levels.last().unwrap();
+            if prev.width == 1 && prev.height == 1 {
+                break;
+            }
+            let next = downsample(prev);
+            levels.push(next);
+        }
+        MipMap { levels, wrap }
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn level(&self, level: usize) -> &Image {
+        &self.levels[level]
+    }
+
+    #[inline]
+    fn texel(&self, image: &Image, x: i64, y: i64) -> Vector {
+        let (w, h) = (image.width as i64, image.height as i64);
+        let (x, y) = match self.wrap {
+            Wrap::Repeat => (x.rem_euclid(w), y.rem_euclid(h)),
+            Wrap::Clamp => (x.clamp(0, w - 1), y.clamp(0, h - 1)),
+        };
+        image.get(x as u32, y as u32)
+    }
+
+    /// Bilinear lookup in one level, `u` grows to the right and `v` upwards from the bottom row.
+    pub fn bilinear(&self, level: usize, u: f64, v: f64) -> Vector {
+        let image = &self.levels[level.min(self.levels.len() - 1)];
+        let x = u * image.width as f64 - 0.5;
+        let y = (1.0 - v) * image.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = self.texel(image, x0, y0) * (1.0 - fx) + self.texel(image, x0 + 1, y0) * fx;
+        let bottom =
+            self.texel(image, x0, y0 + 1) * (1.0 - fx) + self.texel(image, x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    /// Filtered lookup for a footprint `width` UV units wide, blending the two
+    /// levels whose texel size brackets it.
+    pub fn trilinear(&self, u: f64, v: f64, width: f64) -> Vector {
+        let base = &self.levels[0];
+        let texels = width * base.width.max(base.height) as f64;
+        let lod = texels
+            .max(1e-8)
+            .log2()
+            .clamp(0.0, (self.levels.len() - 1) as f64);
+
+        let lower = lod.floor() as usize;
+        let frac = lod - lower as f64;
+        if frac == 0.0 {
+            return self.bilinear(lower, u, v);
+        }
+        self.bilinear(lower, u, v) * (1.0 - frac) + self.bilinear(lower + 1, u, v) * frac
+    }
+}
+
+/// Box filters the image down to half its size. Each texel of the next level
+/// averages the area it covers, so texels of an odd sized image that straddle
+/// two of them count half into each and the last row and column still show.
+fn downsample(image: &Image) -> Image {
+    let (w, h) = ((image.width / 2).max(1), (image.height / 2).max(1));
+    let (scale_x, scale_y) = (
+        image.width as f64 / w as f64,
+        image.height as f64 / h as f64,
+    );
+    let mut next = Image::new(w, h);
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = Vector::zero();
+            for (sy, wy) in covered(y, scale_y, image.height) {
+                for (sx, wx) in covered(x, scale_x, image.width) {
+                    sum += image.get(sx, sy) * (wx * wy);
+                }
+            }
+            next.set(x, y, sum / (scale_x * scale_y));
+        }
+    }
+    next
+}
+
+/// Texels of a row or column `len` long that texel `i` of the level `scale`
+/// times smaller covers, with the part of each inside it
+fn covered(i: u32, scale: f64, len: u32) -> impl Iterator<Item = (u32, f64)> {
+    let (start, end) = (i as f64 * scale, (i + 1) as f64 * scale);
+    let last = (end.ceil() as u32).min(len);
+    (start.floor() as u32..last).map(move |t| (t, end.min(t as f64 + 1.0) - start.max(t as f64)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::geometry::vector::Vector;
+    use crate::image::Image;
+
+    use super::{MipMap, Wrap};
+
+    fn checker(size: u32) -> Image {
+        let mut image = Image::new(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                if (x + y) % 2 == 1 {
+                    image.set(x, y, Vector::one());
+                }
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn pyramid() {
+        let mip = MipMap::new(Image::new(8, 2), Wrap::Repeat);
+        let sizes: Vec<(u32, u32)> = (0..mip.level_count())
+            .map(|l| (mip.level(l).width, mip.level(l).height))
+            .collect();
+        assert_eq!(sizes, vec![(8, 2), (4, 1), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn averages_down() {
+        let mip = MipMap::new(checker(4), Wrap::Repeat);
+        assert_eq!(mip.level(1).get(0, 0), Vector::one() * 0.5);
+        assert_eq!(mip.level(2).get(0, 0), Vector::one() * 0.5);
+    }
+
+    #[test]
+    fn odd_sizes_keep_every_texel() {
+        // a bright last row and column the 2x2 footprint used to skip
+        let mut image = Image::new(5, 3);
+        for x in 0..5 {
+            image.set(x, 2, Vector::one() * 15.0);
+        }
+        for y in 0..3 {
+            image.set(4, y, Vector::one() * 15.0);
+        }
+        let mean = |image: &Image| {
+            image.pixels.iter().fold(Vector::zero(), |sum, &p| sum + p) / image.pixels.len() as f64
+        };
+
+        let mip = MipMap::new(image.clone(), Wrap::Clamp);
+        assert_eq!(mip.level(1).width, 2);
+        for level in 1..mip.level_count() {
+            let (got, want) = (mean(mip.level(level)), mean(&image));
+            assert_delta!(got.x, want.x, 1e-9);
+        }
+        // the bright last column lands in the right texel of the next level
+        assert!(mip.level(1).get(1, 0).x > mip.level(1).get(0, 0).x);
+    }
+
+    #[test]
+    fn trilinear_picks_level_by_footprint() {
+        let mip = MipMap::new(checker(8), Wrap::Repeat);
+        let (u, v) = (1.5 / 8.0, 1.0 - 0.5 / 8.0);
+
+        // a texel wide footprint keeps full detail, a wide one sees the average
+        assert_eq!(mip.trilinear(u, v, 1.0 / 8.0), Vector::one());
+        assert_eq!(mip.trilinear(u, v, 1.0), Vector::one() * 0.5);
+    }
+}
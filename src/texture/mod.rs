@@ -0,0 +1,67 @@
+pub mod image;
+pub mod mipmap;
+pub mod noise;
+pub mod procedural;
+
+use crate::geometry::vector::Vector;
+
+/// Area of the surface covered by one pixel, from ray differentials: how the
+/// hit point and its UVs change when moving one pixel right (`dx`) or down (`dy`).
+#[derive(Clone, Copy, Debug)]
+pub struct Footprint {
+    pub dp_dx: Vector,
+    pub dp_dy: Vector,
+    pub duv_dx: (f64, f64),
+    pub duv_dy: (f64, f64),
+}
+
+impl Footprint {
+    /// Filter width along `u` and `v`
+    #[inline]
+    pub fn uv_width(&self) -> (f64, f64) {
+        (
+            self.duv_dx.0.abs().max(self.duv_dy.0.abs()),
+            self.duv_dx.1.abs().max(self.duv_dy.1.abs()),
+        )
+    }
+}
+
+/// Where a texture is looked up: the hit point in world space, surface UV
+/// coordinates when the shape provides them, the scene time and, for camera
+/// rays and their reflections, the pixel footprint used for filtering.
+#[derive(Clone, Copy, Debug)]
+pub struct TexCoord {
+    pub point: Vector,
+    pub uv: Option<(f64, f64)>,
+    pub time: f64,
+    pub footprint: Option<Footprint>,
+}
+
+impl TexCoord {
+    #[inline]
+    pub fn new(point: Vector, uv: Option<(f64, f64)>, time: f64) -> TexCoord {
+        TexCoord {
+            point,
+            uv,
+            time,
+            footprint: None,
+        }
+    }
+}
+
+pub trait Texture: Send + Sync {
+    fn value(&self, coord: &TexCoord) -> Vector;
+
+    /// Single-channel lookup for scalar parameters such as reflectivity
+    fn scalar(&self, coord: &TexCoord) -> f64 {
+        let v = self.value(coord);
+        (v.x + v.y + v.z) / 3.0
+    }
+}
+
+impl Texture for Vector {
+    #[inline]
+    fn value(&self, _: &TexCoord) -> Vector {
+        *self
+    }
+}
@@ -0,0 +1,152 @@
+use crate::geometry::vector::Vector;
+use crate::random::Rng;
+
+/// Improved Perlin gradient noise with a seeded permutation table.
+pub struct Perlin {
+    perm: [u8; 512],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Perlin {
+        let mut table: [u8; 256] = [0; 256];
+        for (i, v) in table.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+
+        let mut rng = Rng::new(seed);
+        for i in (1..256).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            table.swap(i, j);
+        }
+
+        let mut perm = [0; 512];
+        for i in 0..512 {
+            perm[i] = table[i & 255];
+        }
+        Perlin { perm }
+    }
+
+    /// Noise value in roughly [-1, 1], zero at integer lattice points
+    pub fn noise(&self, p: Vector) -> f64 {
+        let (xf, yf, zf) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (xi, yi, zi) = (
+            (xf as i64 & 255) as usize,
+            (yf as i64 & 255) as usize,
+            (zf as i64 & 255) as usize,
+        );
+        let (x, y, z) = (p.x - xf, p.y - yf, p.z - zf);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let perm = &self.perm;
+        let a = perm[xi] as usize + yi;
+        let aa = perm[a] as usize + zi;
+        let ab = perm[a + 1] as usize + zi;
+        let b = perm[xi + 1] as usize + yi;
+        let ba = perm[b] as usize + zi;
+        let bb = perm[b + 1] as usize + zi;
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(perm[aa], x, y, z), grad(perm[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(perm[ab], x, y - 1.0, z),
+                    grad(perm[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(perm[aa + 1], x, y, z - 1.0),
+                    grad(perm[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(perm[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(perm[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+
+    /// Sum of absolute noise over `octaves`, each at double frequency and half weight
+    pub fn turbulence(&self, p: Vector, octaves: u32) -> f64 {
+        let mut sum = 0.0;
+        let mut p = p;
+        let mut weight = 1.0;
+        for _ in 0..octaves {
+            sum += weight * self.noise(p).abs();
+            weight *= 0.5;
+            p = p * 2.0;
+        }
+        sum
+    }
+}
+
+#[inline]
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[inline]
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+#[inline]
+fn grad(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::geometry::vector::Vector;
+
+    use super::Perlin;
+
+    #[test]
+    fn zero_on_lattice() {
+        let perlin = Perlin::new(1);
+        assert_eq!(perlin.noise(Vector::new(1.0, 2.0, -3.0)), 0.0);
+    }
+
+    #[test]
+    fn bounded_and_continuous() {
+        let perlin = Perlin::new(3);
+        for i in 0..1000 {
+            let p = Vector::new(i as f64 * 0.137, i as f64 * 0.071, -(i as f64) * 0.053);
+            let n = perlin.noise(p);
+            assert!((-1.5..=1.5).contains(&n));
+            assert_delta!(perlin.noise(p + Vector::one() * 1e-6), n, 1e-4);
+        }
+    }
+
+    #[test]
+    fn seeded() {
+        let p = Vector::new(0.3, 0.7, 0.1);
+        assert_eq!(Perlin::new(5).noise(p), Perlin::new(5).noise(p));
+        assert_ne!(Perlin::new(5).noise(p), Perlin::new(6).noise(p));
+    }
+
+    #[test]
+    fn turbulence_non_negative() {
+        let perlin = Perlin::new(0);
+        for i in 0..100 {
+            let p = Vector::new(i as f64 * 0.31, 0.5, i as f64 * 0.17);
+            assert!(perlin.turbulence(p, 5) >= 0.0);
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use crate::geometry::{animation::Lerp, vector::Vector};
+
+use super::{noise::Perlin, TexCoord, Texture};
+
+/// Alternating cells of `size`; uses UVs when the surface has them, solid 3D cells otherwise.
+/// Cells are box filtered over the pixel footprint so distant checkers fade to grey
+/// instead of aliasing.
+pub struct Checker {
+    pub even: Vector,
+    pub odd: Vector,
+    pub size: f64,
+}
+
+impl Checker {
+    pub fn new(even: Vector, odd: Vector, size: f64) -> Checker {
+        Checker { even, odd, size }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, coord: &TexCoord) -> Vector {
+        // cell coordinates and the footprint width along each of them
+        let (cells, widths) = match (coord.uv, coord.footprint) {
+            (Some((u, v)), footprint) => {
+                let (du, dv) = footprint.map_or((0.0, 0.0), |f| f.uv_width());
+                (vec![u, v], vec![du, dv])
+            }
+            (None, footprint) => {
+                let p = coord.point;
+                let (dx, dy) =
+                    footprint.map_or((Vector::zero(), Vector::zero()), |f| (f.dp_dx, f.dp_dy));
+                let width = |a: f64, b: f64| a.abs().max(b.abs());
+                (
+                    vec![p.x, p.y, p.z],
+                    vec![width(dx.x, dy.x), width(dx.y, dy.y), width(dx.z, dy.z)],
+                )
+            }
+        };
+
+        // box filter each axis; a cell is odd where an odd number of its coordinates are
+        let odd = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(c, w)| {
+                let (c, w) = (c / self.size, w / self.size);
+                odd_share(c - w / 2.0, c + w / 2.0)
+            })
+            .fold(0.0, |a, b| a + b - 2.0 * a * b);
+        self.even.lerp(&self.odd, odd)
+    }
+}
+
+/// Footprints narrower than this many cells are point sampled
+const FILTER_MIN_WIDTH: f64 = 1e-6;
+
+/// Portion of `x0..x1` falling into odd unit cells
+fn odd_share(x0: f64, x1: f64) -> f64 {
+    if x1 - x0 < FILTER_MIN_WIDTH {
+        return if x0.floor().rem_euclid(2.0) < 1.0 {
+            0.0
+        } else {
+            1.0
+        };
+    }
+    // integral of the odd cell indicator from 0 to x
+    let integral = |x: f64| (x / 2.0).floor() + (x / 2.0 - (x / 2.0).floor() - 0.5).max(0.0) * 2.0;
+    (integral(x1) - integral(x0)) / (x1 - x0)
+}
+
+/// Parallel bands of equal `width` across `axis`.
+pub struct Stripes {
+    pub a: Vector,
+    pub b: Vector,
+    pub axis: Vector,
+    pub width: f64,
+}
+
+impl Stripes {
+    pub fn new(a: Vector, b: Vector, axis: Vector, width: f64) -> Stripes {
+        Stripes {
+            a,
+            b,
+            axis: axis.normalized(),
+            width,
+        }
+    }
+}
+
+impl Texture for Stripes {
+    fn value(&self, coord: &TexCoord) -> Vector {
+        if (coord.point.dot(&self.axis) / self.width)
+            .floor()
+            .rem_euclid(2.0)
+            < 1.0
+        {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+/// Linear blend from `from` to `to` along `axis`, between distances `start` and `end`.
+pub struct Gradient {
+    pub from: Vector,
+    pub to: Vector,
+    pub axis: Vector,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Gradient {
+    pub fn new(from: Vector, to: Vector, axis: Vector, start: f64, end: f64) -> Gradient {
+        Gradient {
+            from,
+            to,
+            axis: axis.normalized(),
+            start,
+            end,
+        }
+    }
+}
+
+impl Texture for Gradient {
+    fn value(&self, coord: &TexCoord) -> Vector {
+        let t = (coord.point.dot(&self.axis) - self.start) / (self.end - self.start);
+        self.from.lerp(&self.to, t.clamp(0.0, 1.0))
+    }
+}
+
+/// Smooth Perlin noise blending between two colours.
+pub struct Noise {
+    pub a: Vector,
+    pub b: Vector,
+    pub scale: f64,
+    perlin: Perlin,
+}
+
+impl Noise {
+    pub fn new(a: Vector, b: Vector, scale: f64, seed: u64) -> Noise {
+        Noise {
+            a,
+            b,
+            scale,
+            perlin: Perlin::new(seed),
+        }
+    }
+}
+
+impl Texture for Noise {
+    fn value(&self, coord: &TexCoord) -> Vector {
+        let n = self.perlin.noise(coord.point * self.scale);
+        self.a.lerp(&self.b, (0.5 * (n + 1.0)).clamp(0.0, 1.0))
+    }
+}
+
+/// Fractal turbulence blending between two colours.
+pub struct Turbulence {
+    pub a: Vector,
+    pub b: Vector,
+    pub scale: f64,
+    pub octaves: u32,
+    perlin: Perlin,
+}
+
+impl Turbulence {
+    pub fn new(a: Vector, b: Vector, scale: f64, octaves: u32, seed: u64) -> Turbulence {
+        Turbulence {
+            a,
+            b,
+            scale,
+            octaves,
+            perlin: Perlin::new(seed),
+        }
+    }
+}
+
+impl Texture for Turbulence {
+    fn value(&self, coord: &TexCoord) -> Vector {
+        let t = self
+            .perlin
+            .turbulence(coord.point * self.scale, self.octaves);
+        self.a.lerp(&self.b, t.clamp(0.0, 1.0))
+    }
+}
+
+/// Veins along `axis` distorted by turbulence.
+pub struct Marble {
+    pub base: Vector,
+    pub vein: Vector,
+    pub axis: Vector,
+    pub frequency: f64,
+    pub distortion: f64,
+    pub octaves: u32,
+    perlin: Perlin,
+}
+
+impl Marble {
+    pub fn new(base: Vector, vein: Vector, axis: Vector, frequency: f64, seed: u64) -> Marble {
+        Marble {
+            base,
+            vein,
+            axis: axis.normalized(),
+            frequency,
+            distortion: 2.0,
+            octaves: 6,
+            perlin: Perlin::new(seed),
+        }
+    }
+}
+
+impl Texture for Marble {
+    fn value(&self, coord: &TexCoord) -> Vector {
+        let p = coord.point * self.frequency;
+        let phase = p.dot(&self.axis) + self.distortion * self.perlin.turbulence(p, self.octaves);
+        self.base.lerp(&self.vein, 0.5 * (1.0 - phase.sin()))
+    }
+}
+
+/// Concentric growth rings around `axis` through `center`, wobbled by noise.
+pub struct Wood {
+    pub light: Vector,
+    pub dark: Vector,
+    pub center: Vector,
+    pub axis: Vector,
+    pub rings_per_unit: f64,
+    pub distortion: f64,
+    perlin: Perlin,
+}
+
+impl Wood {
+    pub fn new(
+        light: Vector,
+        dark: Vector,
+        center: Vector,
+        axis: Vector,
+        rings_per_unit: f64,
+        seed: u64,
+    ) -> Wood {
+        Wood {
+            light,
+            dark,
+            center,
+            axis: axis.normalized(),
+            rings_per_unit,
+            distortion: 0.15,
+            perlin: Perlin::new(seed),
+        }
+    }
+}
+
+impl Texture for Wood {
+    fn value(&self, coord: &TexCoord) -> Vector {
+        let rel = coord.point - self.center;
+        let radial = rel - self.axis * rel.dot(&self.axis);
+        let r = radial.len() + self.distortion * self.perlin.noise(coord.point * 2.0);
+        let ring = (r * self.rings_per_unit).rem_euclid(1.0);
+        // sharp dark edge at the end of each ring, like latewood
+        self.light.lerp(&self.dark, ring.powi(3))
+    }
+}
+
+/// Running bond brickwork. Colour blends from `mortar` to `brick` over
+/// `mortar_width` so it also works as a bump map with bevelled grooves.
+/// Uses UVs when available, the XY plane otherwise.
+pub struct Bricks {
+    pub brick: Vector,
+    pub mortar: Vector,
+    pub size: (f64, f64),
+    pub mortar_width: f64,
+}
+
+impl Bricks {
+    pub fn new(brick: Vector, mortar: Vector, size: (f64, f64), mortar_width: f64) -> Bricks {
+        Bricks {
+            brick,
+            mortar,
+            size,
+            mortar_width,
+        }
+    }
+}
+
+impl Texture for Bricks {
+    fn value(&self, coord: &TexCoord) -> Vector {
+        let (u, v) = coord.uv.unwrap_or((coord.point.x, coord.point.y));
+        let row = (v / self.size.1).floor();
+        // every other row is shifted by half a brick
+        let u = u + if row.rem_euclid(2.0) < 1.0 {
+            0.0
+        } else {
+            self.size.0 / 2.0
+        };
+
+        let du = u.rem_euclid(self.size.0);
+        let dv = v.rem_euclid(self.size.1);
+        let edge = du.min(self.size.0 - du).min(dv).min(self.size.1 - dv);
+
+        let t = (edge / self.mortar_width).clamp(0.0, 1.0);
+        self.mortar.lerp(&self.brick, t * t * (3.0 - 2.0 * t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_delta;
+    use crate::geometry::vector::Vector;
+    use crate::texture::{Footprint, TexCoord, Texture};
+
+    use super::{Bricks, Checker, Gradient, Marble, Stripes, Wood};
+
+    fn at(x: f64, y: f64, z: f64) -> TexCoord {
+        TexCoord::new(Vector::new(x, y, z), None, 0.0)
+    }
+
+    #[test]
+    fn checker_solid() {
+        let c = Checker::new(Vector::zero(), Vector::one(), 1.0);
+        assert_eq!(c.value(&at(0.5, 0.5, 0.5)), Vector::zero());
+        assert_eq!(c.value(&at(1.5, 0.5, 0.5)), Vector::one());
+        assert_eq!(c.value(&at(-0.5, 0.5, 0.5)), Vector::one());
+        assert_eq!(c.value(&at(1.5, 1.5, 0.5)), Vector::zero());
+    }
+
+    #[test]
+    fn checker_uv() {
+        let c = Checker::new(Vector::zero(), Vector::one(), 0.5);
+        let mut coord = at(0.25, 0.25, 0.25);
+        coord.uv = Some((0.75, 0.25));
+        assert_eq!(c.value(&coord), Vector::one());
+    }
+
+    #[test]
+    fn checker_filtered() {
+        let c = Checker::new(Vector::zero(), Vector::one(), 0.5);
+        let mut coord = at(0.0, 0.0, 0.0);
+        coord.uv = Some((0.75, 0.25));
+        coord.footprint = Some(Footprint {
+            dp_dx: Vector::zero(),
+            dp_dy: Vector::zero(),
+            duv_dx: (0.01, 0.0),
+            duv_dy: (0.0, 0.01),
+        });
+        assert_eq!(c.value(&coord), Vector::one());
+
+        // a footprint spanning many cells averages out
+        coord.footprint = Some(Footprint {
+            dp_dx: Vector::zero(),
+            dp_dy: Vector::zero(),
+            duv_dx: (10.0, 0.0),
+            duv_dy: (0.0, 10.0),
+        });
+        assert_delta!(c.value(&coord).x, 0.5, 1e-9);
+
+        // solid cells filter over the world space footprint
+        let mut coord = at(0.5, 0.5, 0.5);
+        coord.footprint = Some(Footprint {
+            dp_dx: Vector::new(8.0, 0.0, 0.0),
+            dp_dy: Vector::zero(),
+            duv_dx: (0.0, 0.0),
+            duv_dy: (0.0, 0.0),
+        });
+        assert_delta!(c.value(&coord).x, 0.5, 1e-9);
+    }
+
+    #[test]
+    fn stripes() {
+        let s = Stripes::new(Vector::zero(), Vector::one(), Vector::one_x(), 2.0);
+        assert_eq!(s.value(&at(1.0, 5.0, 5.0)), Vector::zero());
+        assert_eq!(s.value(&at(3.0, -5.0, 5.0)), Vector::one());
+    }
+
+    #[test]
+    fn gradient() {
+        let g = Gradient::new(Vector::zero(), Vector::one(), Vector::one_y(), 0.0, 2.0);
+        assert_eq!(g.value(&at(0.0, -1.0, 0.0)), Vector::zero());
+        assert_eq!(g.value(&at(0.0, 1.0, 0.0)), Vector::one() * 0.5);
+        assert_eq!(g.value(&at(0.0, 3.0, 0.0)), Vector::one());
+        assert_eq!(g.scalar(&at(0.0, 1.0, 0.0)), 0.5);
+    }
+
+    #[test]
+    fn bricks() {
+        let b = Bricks::new(Vector::one(), Vector::zero(), (2.0, 1.0), 0.1);
+        assert_eq!(b.value(&at(1.0, 0.5, 0.0)), Vector::one());
+        assert_eq!(b.value(&at(2.0, 0.5, 0.0)), Vector::zero());
+        // the next row is offset by half a brick
+        assert_eq!(b.value(&at(2.0, 1.5, 0.0)), Vector::one());
+        assert_eq!(b.value(&at(1.0, 1.5, 0.0)), Vector::zero());
+    }
+
+    #[test]
+    fn marble_and_wood_stay_between_colours() {
+        let m = Marble::new(Vector::zero(), Vector::one(), Vector::one_x(), 3.0, 1);
+        let w = Wood::new(
+            Vector::zero(),
+            Vector::one(),
+            Vector::zero(),
+            Vector::one_y(),
+            4.0,
+            1,
+        );
+        for i in 0..100 {
+            let coord = at(i as f64 * 0.13, i as f64 * 0.07, i as f64 * -0.11);
+            for v in [m.value(&coord), w.value(&coord)] {
+                assert!((0.0..=1.0).contains(&v.x));
+            }
+        }
+    }
+}
@@ -1,54 +1,714 @@
+use std::f64::consts::PI;
+use std::thread;
+
+use crate::bsdf::Frame;
 use crate::camera::Camera;
 use crate::geometry::scene::Scene;
-use crate::geometry::shape::Shape;
+use crate::geometry::shape::{surface_epsilon, Shape};
 use crate::geometry::vector::Vector;
+use crate::medium::Medium;
+use crate::pass::{self, Pass};
+use crate::random::Rng;
+use crate::texture::{Footprint, TexCoord};
+
+/// Reach of the occlusion rays of `Integrator::AmbientOcclusion`
+const OCCLUSION_DISTANCE: f64 = 2.0;
+/// Samples adaptive sampling takes before it trusts the variance of a pixel
+const MIN_ADAPTIVE_SAMPLES: u32 = 8;
+/// Brightness the noise of darker pixels is measured against, so near black
+/// pixels aren't sampled up to the cap chasing noise too dark to see
+const NOISE_FLOOR: f64 = 0.05;
+/// Most bounces a path follows, each one is a level of recursion in `trace_color`
+pub const MAX_DEPTH: u32 = 256;
+
+/// Rays through the neighbouring pixels to the right (`dx`) and below (`dy`),
+/// traced alongside a camera ray to estimate its footprint on the surfaces.
+#[derive(Clone, Copy, Debug)]
+struct RayDifferential {
+    origin_dx: Vector,
+    origin_dy: Vector,
+    dir_dx: Vector,
+    dir_dy: Vector,
+}
+
+/// Ray being traced, with the state the tracer carries along it.
+#[derive(Clone, Copy, Debug)]
+struct Ray {
+    origin: Vector,
+    direction: Vector,
+    time: f64,
+    differential: Option<RayDifferential>,
+    /// Density a BSDF picked the direction with; emission the ray hits is then
+    /// weighted against the same light being sampled directly
+    bsdf_pdf: Option<f64>,
+    /// Share of what the ray brings back that reaches the camera, the product
+    /// of the reflectances it bounced off
+    throughput: Vector,
+}
+
+/// Light arriving along a ray, split by whether it came straight from the
+/// lights to the first thing the ray met or bounced off something else on the way.
+#[derive(Clone, Copy, Debug)]
+struct Radiance {
+    direct: Vector,
+    indirect: Vector,
+    /// Secondary rays traced after the first hit
+    bounces: u32,
+}
+
+impl Radiance {
+    #[inline]
+    fn total(&self) -> Vector {
+        self.direct + self.indirect
+    }
+}
+
+/// Render of one pixel with the extra layers (AOVs) written next to the colour.
+/// `depth` is the distance along the centre ray, infinite where it hits nothing;
+/// `object_id` indexes `Scene::shapes`.
+#[derive(Clone, Copy, Debug)]
+pub struct PixelAovs {
+    pub color: Vector,
+    pub direct: Vector,
+    pub indirect: Vector,
+    pub depth: f64,
+    pub normal: Vector,
+    pub albedo: Vector,
+    pub object_id: Option<usize>,
+}
 
-pub struct Tracer {}
+/// What the ray through a pixel centre hit, for the layers describing the surface
+struct CentreHit {
+    shape_index: usize,
+    t: f64,
+    point: Vector,
+    /// Geometric normal of the shape, before bump and normal maps
+    face_normal: Vector,
+    shading_normal: Vector,
+    albedo: Vector,
+}
+
+/// How the beauty pass gathers light
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Integrator {
+    /// Lights, emitters and the environment at every hit, plus reflected and
+    /// scattered light up to `Tracer::max_depth` bounces
+    Path,
+    /// Only the light reaching the first hit straight from the lights
+    Direct,
+    /// Share of the hemisphere above the first hit that is open within
+    /// `OCCLUSION_DISTANCE`, in grey
+    AmbientOcclusion,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 3] = [
+        Integrator::Path,
+        Integrator::Direct,
+        Integrator::AmbientOcclusion,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integrator::Path => "path",
+            Integrator::Direct => "direct",
+            Integrator::AmbientOcclusion => "ao",
+        }
+    }
+}
+
+pub struct Tracer {
+    pub samples_per_pixel: u32,
+    pub seed: u64,
+    /// Shown by `trace_pixel`
+    pub pass: Pass,
+    pub integrator: Integrator,
+    /// Reflections and BSDF bounces followed after the first hit, materials can lower it,
+    /// at most `MAX_DEPTH`
+    pub max_depth: u32,
+    /// Paths stop bouncing once the throughput of every channel falls below this,
+    /// their further light wouldn't show
+    pub min_throughput: f64,
+    /// Rows `RenderJob` traces at once, one runs on the calling thread
+    pub threads: usize,
+    /// With adaptive sampling pixels get samples beyond `samples_per_pixel`
+    /// until the standard error of their brightness drops below this share of
+    /// it; zero traces exactly `samples_per_pixel` everywhere
+    pub noise_threshold: f64,
+    /// Most samples adaptive sampling spends on a pixel
+    pub max_samples_per_pixel: u32,
+}
+
+impl Default for Tracer {
+    fn default() -> Tracer {
+        Tracer::new()
+    }
+}
 
 impl Tracer {
     pub fn new() -> Tracer {
-        Tracer {}
+        Tracer {
+            samples_per_pixel: 1,
+            seed: 0,
+            pass: Pass::Beauty,
+            integrator: Integrator::Path,
+            max_depth: 5,
+            min_throughput: 0.02,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            noise_threshold: 0.0,
+            max_samples_per_pixel: 256,
+        }
+    }
+
+    /// Averages `samples_per_pixel` rays through pixel (`x`, `y`) of a `w` x `h`
+    /// frame, or with a `noise_threshold` as many as the pixel needs. With several
+    /// samples the rays are jittered across the pixel area; each ray also gets
+    /// its own time within the camera shutter interval.
+    /// Debug passes show their false colour value instead.
+    pub fn trace_pixel(
+        &self,
+        x: u32,
+        y: u32,
+        (w, h): (u32, u32),
+        camera: &Camera,
+        scene: &Scene,
+        time: f64,
+    ) -> Vector {
+        match self.pass {
+            Pass::Beauty => {
+                return self
+                    .sample_pixel(x, y, (w, h), camera, scene, time)
+                    .0
+                    .total()
+            }
+            Pass::Bounces => {
+                let bounces = self
+                    .sample_pixel(x, y, (w, h), camera, scene, time)
+                    .0
+                    .bounces;
+                return pass::false_color(bounces as f64 / pass::BOUNCE_RANGE);
+            }
+            Pass::SampleCount => {
+                let samples = self.sample_pixel(x, y, (w, h), camera, scene, time).1;
+                let (_, max_samples) = self.sample_range();
+                return pass::false_color(samples as f64 / max_samples as f64);
+            }
+            _ => {}
+        }
+
+        let hit = match Self::centre_hit(x, y, (w, h), camera, scene, time) {
+            Some(hit) => hit,
+            None => return Vector::zero(),
+        };
+        match self.pass {
+            Pass::Depth => pass::false_color(hit.t / pass::DEPTH_RANGE),
+            Pass::Normal => (hit.shading_normal + Vector::one()) / 2.0,
+            Pass::Position => (hit.point / pass::POSITION_RANGE + Vector::one()) / 2.0,
+            Pass::Albedo => hit.albedo,
+            Pass::ShapeIndex => pass::category_color(hit.shape_index),
+            _ => pass::false_color(Self::light_visibility(
+                hit.point,
+                hit.face_normal,
+                scene,
+                time,
+            )),
+        }
+    }
+
+    /// Like `trace_pixel`, with the render split into the layers compositing
+    /// works with. Geometry layers come from the ray through the pixel centre.
+    pub fn trace_pixel_aovs(
+        &self,
+        x: u32,
+        y: u32,
+        (w, h): (u32, u32),
+        camera: &Camera,
+        scene: &Scene,
+        time: f64,
+    ) -> PixelAovs {
+        let (radiance, _) = self.sample_pixel(x, y, (w, h), camera, scene, time);
+        let hit = Self::centre_hit(x, y, (w, h), camera, scene, time);
+        PixelAovs {
+            color: radiance.total(),
+            direct: radiance.direct,
+            indirect: radiance.indirect,
+            depth: hit.as_ref().map_or(f64::INFINITY, |hit| hit.t),
+            normal: hit
+                .as_ref()
+                .map_or(Vector::zero(), |hit| hit.shading_normal),
+            albedo: hit.as_ref().map_or(Vector::zero(), |hit| hit.albedo),
+            object_id: hit.map(|hit| hit.shape_index),
+        }
+    }
+
+    fn centre_hit(
+        x: u32,
+        y: u32,
+        (w, h): (u32, u32),
+        camera: &Camera,
+        scene: &Scene,
+        time: f64,
+    ) -> Option<CentreHit> {
+        let xp = (x as f64 + 0.5) / (w as f64 / 2.0) - 1.0;
+        let yp = (y as f64 + 0.5) / (h as f64 / 2.0) - 1.0;
+        let dir = Self::camera_direction(camera, xp, -yp);
+
+        let mut closest: Option<(usize, f64)> = None;
+        for (i, shape) in scene.shapes.iter().enumerate() {
+            if let Some(t) = shape.intersect(camera.pos, dir, surface_epsilon(camera.pos), time) {
+                if closest.is_none_or(|(_, best)| t < best) {
+                    closest = Some((i, t));
+                }
+            }
+        }
+        let (shape_index, t) = closest?;
+
+        let shape = scene.shapes[shape_index].as_ref();
+        let hit = shape.hit(camera.pos, dir, t, time);
+        let material = shape.get_material();
+        let coord = TexCoord::new(hit.point, hit.uv, time);
+        Some(CentreHit {
+            shape_index,
+            t,
+            point: hit.point,
+            face_normal: hit.normal,
+            shading_normal: material.shading_normal(&hit, &coord),
+            albedo: material.color_at(&coord),
+        })
+    }
+
+    /// Share of the point and directional lights in front of the surface at
+    /// `ip` that shadow rays reach, one when there are none
+    fn light_visibility(ip: Vector, normal: Vector, scene: &Scene, time: f64) -> f64 {
+        let mut directions: Vec<(Vector, f64)> = scene
+            .point_lights
+            .iter()
+            .map(|light| {
+                let to_light = light.pos_at(time) - ip;
+                (to_light.normalized(), to_light.len())
+            })
+            .collect();
+        directions.extend(
+            scene
+                .directional_lights()
+                .map(|light| (light.direction, f64::INFINITY)),
+        );
+
+        let (mut visible, mut total) = (0, 0);
+        for (dir, dist) in directions {
+            if dir.dot(&normal) <= 0.0 {
+                continue;
+            }
+            total += 1;
+            let origin = Self::offset_origin(ip, normal, dir);
+            match Self::closest_intersect(origin, dir, &scene.shapes, time) {
+                Some((_, t)) if t < dist => {}
+                _ => visible += 1,
+            }
+        }
+        if total == 0 {
+            1.0
+        } else {
+            visible as f64 / total as f64
+        }
+    }
+
+    /// Fewest and most samples a pixel gets, the same without adaptive sampling
+    fn sample_range(&self) -> (u32, u32) {
+        let spp = self.samples_per_pixel.max(1);
+        if self.noise_threshold <= 0.0 {
+            return (spp, spp);
+        }
+        let max_spp = self.max_samples_per_pixel.max(spp);
+        (spp.max(MIN_ADAPTIVE_SAMPLES).min(max_spp), max_spp)
     }
 
-    pub fn trace(&self, x: f64, y: f64, camera: &Camera, scene: &Scene, refl_idx: i32) -> Vector {
+    /// Radiance through the pixel and the number of samples it took
+    fn sample_pixel(
+        &self,
+        x: u32,
+        y: u32,
+        (w, h): (u32, u32),
+        camera: &Camera,
+        scene: &Scene,
+        time: f64,
+    ) -> (Radiance, u32) {
+        let (min_spp, max_spp) = self.sample_range();
+        // samples spread over the pixel, each covering a smaller area of it
+        let spacing = 1.0 / (min_spp as f64).sqrt();
+        let (step_x, step_y) = (2.0 / w as f64 * spacing, 2.0 / h as f64 * spacing);
+        let mut rng = Rng::for_pixel(self.seed, x, y, time.to_bits());
+        let mut direct = Vector::zero();
+        let mut indirect = Vector::zero();
+        let mut bounces = 0;
+        let depth = self.max_depth.min(MAX_DEPTH) as i32;
+        let cutoff = self.min_throughput;
+        // running sums of the brightness of the samples, for their variance
+        let (mut sum, mut sum_sq) = (0.0, 0.0);
+        let mut spp = 0;
+
+        while spp < max_spp {
+            // a single sample stays in the pixel centre so the image doesn't flicker
+            let (jx, jy) = if max_spp > 1 {
+                (rng.next_f64(), rng.next_f64())
+            } else {
+                (0.5, 0.5)
+            };
+            let xp = (x as f64 + jx) / (w as f64 / 2.0) - 1.0;
+            let yp = (y as f64 + jy) / (h as f64 / 2.0) - 1.0;
+
+            let shutter = if camera.shutter > 0.0 {
+                rng.next_f64()
+            } else {
+                0.0
+            };
+            let ray_camera = camera.at_shutter(shutter);
+            let ray_time = time + shutter * camera.shutter;
+
+            // vertical axis is inverted on screen
+            let dir = Self::camera_direction(&ray_camera, xp, -yp);
+            let (origin, dir) = if ray_camera.aperture > 0.0 {
+                ray_camera.lens_ray(dir, rng.next_f64(), rng.next_f64())
+            } else {
+                (ray_camera.pos, dir)
+            };
+            let differential = RayDifferential {
+                origin_dx: origin,
+                origin_dy: origin,
+                dir_dx: Self::camera_direction(&ray_camera, xp + step_x, -yp),
+                dir_dy: Self::camera_direction(&ray_camera, xp, -yp - step_y),
+            };
+            let ray = Ray {
+                origin,
+                direction: dir,
+                time: ray_time,
+                differential: Some(differential),
+                bsdf_pdf: None,
+                throughput: Vector::one(),
+            };
+            let radiance = match self.integrator {
+                Integrator::Path => Self::trace_color(&ray, scene, depth, cutoff, &mut rng),
+                Integrator::Direct => Self::trace_color(&ray, scene, 0, cutoff, &mut rng),
+                Integrator::AmbientOcclusion => Radiance {
+                    direct: Vector::one() * Self::ambient_occlusion(&ray, scene, &mut rng),
+                    indirect: Vector::zero(),
+                    bounces: 0,
+                },
+            };
+            direct += radiance.direct;
+            indirect += radiance.indirect;
+            bounces = bounces.max(radiance.bounces);
+
+            let brightness = radiance.total().luminance();
+            sum += brightness;
+            sum_sq += brightness * brightness;
+            spp += 1;
+            if spp >= min_spp && self.converged(sum, sum_sq, spp) {
+                break;
+            }
+        }
+
+        let radiance = Radiance {
+            direct: direct / spp as f64,
+            indirect: indirect / spp as f64,
+            bounces,
+        };
+        (radiance, spp)
+    }
+
+    /// Whether the mean of `n` samples with brightness sums `sum` and `sum_sq`
+    /// is within the noise threshold
+    fn converged(&self, sum: f64, sum_sq: f64, n: u32) -> bool {
+        if n < 2 {
+            return false;
+        }
+        let n = n as f64;
+        let mean = sum / n;
+        let variance = ((sum_sq - sum * mean) / (n - 1.0)).max(0.0);
+        let error = (variance / n).sqrt();
+        error <= self.noise_threshold * mean.max(NOISE_FLOOR)
+    }
+
+    /// One if a cosine weighted ray from the first hit of `ray` escapes
+    /// `OCCLUSION_DISTANCE`, zero if it is blocked or `ray` hits nothing
+    fn ambient_occlusion(ray: &Ray, scene: &Scene, rng: &mut Rng) -> f64 {
+        let (shape, t) =
+            match Self::closest_intersect(ray.origin, ray.direction, &scene.shapes, ray.time) {
+                Some(hit) => hit,
+                None => return 0.0,
+            };
+        let hit = shape.hit(ray.origin, ray.direction, t, ray.time);
+        // the side the ray arrived from
+        let normal = if hit.normal.dot(&ray.direction) > 0.0 {
+            -hit.normal
+        } else {
+            hit.normal
+        };
+
+        let r = rng.next_f64().sqrt();
+        let phi = 2.0 * PI * rng.next_f64();
+        let z = (1.0 - r * r).max(0.0).sqrt();
+        let local = Vector::new(r * phi.cos(), r * phi.sin(), z);
+        let dir = Frame::new(normal, hit.tangent).to_world(local);
+
+        let origin = Self::offset_origin(hit.point, normal, dir);
+        match Self::closest_intersect(origin, dir, &scene.shapes, ray.time) {
+            Some((_, t)) if t < OCCLUSION_DISTANCE => 0.0,
+            _ => 1.0,
+        }
+    }
+
+    /// Direction of the camera ray through viewport point (`x`, `y`) in -1..1
+    fn camera_direction(camera: &Camera, x: f64, y: f64) -> Vector {
         let vp_h = camera.up * camera.vfov2_tg;
         let vp_w = camera.right * camera.vfov2_tg * camera.ar;
-        let dir = (camera.forward + x * vp_w + y * vp_h).normalized();
+        (camera.forward + x * vp_w + y * vp_h).normalized()
+    }
 
-        Tracer::trace_color(camera.pos, dir, scene, refl_idx)
+    /// Light arriving along `ray`, following at most `refl_idx` more bounces
+    /// and none once the throughput falls below `cutoff`
+    fn trace_color(
+        ray: &Ray,
+        scene: &Scene,
+        refl_idx: i32,
+        cutoff: f64,
+        rng: &mut Rng,
+    ) -> Radiance {
+        let closest_intersect =
+            Self::closest_intersect(ray.origin, ray.direction, &scene.shapes, ray.time);
+        if scene.volumes.is_empty() {
+            return Self::shade(ray, closest_intersect, scene, refl_idx, cutoff, rng);
+        }
+
+        // whatever is behind the media is seen through them, plus the light they scatter
+        let max_t = closest_intersect.map_or(f64::INFINITY, |(_, t)| t);
+        let transmittance =
+            Self::transmittance(ray.origin, ray.direction, max_t, scene, ray.time, rng);
+        // single scattering comes straight from the lights
+        let mut color = Radiance {
+            direct: Self::scatter_from_media(ray, max_t, scene, rng),
+            indirect: Vector::zero(),
+            bounces: 0,
+        };
+        if transmittance != Vector::zero() {
+            let behind = Self::shade(ray, closest_intersect, scene, refl_idx, cutoff, rng);
+            color.direct += transmittance.scale(&behind.direct);
+            color.indirect += transmittance.scale(&behind.indirect);
+            color.bounces = behind.bounces;
+        }
+        color
     }
 
-    fn trace_color(source: Vector, direction: Vector, scene: &Scene, refl_idx: i32) -> Vector {
-        let closest_intersect = Self::closest_intersect(source, direction, &scene.shapes);
+    /// Light leaving the surface `ray` hit, or arriving from the background when it hit nothing
+    fn shade(
+        ray: &Ray,
+        closest_intersect: Option<(&dyn Shape, f64)>,
+        scene: &Scene,
+        refl_idx: i32,
+        cutoff: f64,
+        rng: &mut Rng,
+    ) -> Radiance {
+        let (source, direction, time) = (ray.origin, ray.direction, ray.time);
 
-        if let None = closest_intersect {
-            return Vector::zero();
+        if closest_intersect.is_none() {
+            let background = scene.background().map_or(Vector::zero(), |env| {
+                // the environment was also sampled directly from the previous hit
+                let weight = ray.bsdf_pdf.map_or(1.0, |bsdf_pdf| {
+                    power_heuristic(bsdf_pdf, env.pdf(direction))
+                });
+                env.radiance(direction) * weight
+            });
+            return Radiance {
+                direct: background,
+                indirect: Vector::zero(),
+                bounces: 0,
+            };
         }
 
-        let mut result_color = scene.ambient_light.clone();
+        let mut result_color = scene.ambient_light;
+        let mut indirect_color = Vector::zero();
+        let mut bounces = 0;
 
         let (shape, t) = closest_intersect.unwrap();
         let material = shape.get_material();
+        let refl_idx = material.max_depth.map_or(refl_idx, |max_depth| {
+            refl_idx.min(max_depth.min(MAX_DEPTH) as i32)
+        });
+
+        let hit = shape.hit(source, direction, t, time);
+        let ip = hit.point;
+
+        let emitted = material.emitted();
+        let cos_light = -direction.dot(&hit.normal);
+        if emitted != Vector::zero() && cos_light > 0.0 {
+            // the light was also sampled directly from the previous hit
+            let weight = ray.bsdf_pdf.map_or(1.0, |bsdf_pdf| {
+                power_heuristic(bsdf_pdf, t * t / (shape.area() * cos_light))
+            });
+            result_color += emitted * weight;
+        }
 
-        let ip = source + direction * t;
-        let normal = shape.normal(ip);
-        let diff_color = Self::trace_to_lights(ip, normal, scene);
-        result_color += diff_color.scale(&material.color) * (1.0 - material.refletivity_index);
+        let mut coord = TexCoord::new(ip, hit.uv, time);
+        let offsets = ray
+            .differential
+            .and_then(|d| Self::offset_hits(&d, ip, hit.normal));
+        coord.footprint = offsets.map(|(p_dx, p_dy)| Footprint {
+            dp_dx: p_dx - ip,
+            dp_dy: p_dy - ip,
+            duv_dx: Self::uv_delta(shape, ip, p_dx - ip, hit.uv, time),
+            duv_dy: Self::uv_delta(shape, ip, p_dy - ip, hit.uv, time),
+        });
+        let color = material.color_at(&coord);
+        let normal = material.shading_normal(&hit, &coord);
+
+        if let Some(bsdf) = &material.bsdf {
+            let frame = Frame::new(normal, hit.tangent);
+            let wo = frame.to_local(-direction);
+
+            // light powers are calibrated for the diffuse model below, where a white
+            // surface sends back all of its irradiance; pi makes a white Lambertian match
+            let direct = Self::trace_to_lights(ip, normal, scene, time, rng, |to_light| {
+                bsdf.eval(wo, frame.to_local(to_light), color) * PI
+            });
+            result_color += direct;
+            let response = |to_light| {
+                let wi = frame.to_local(to_light);
+                (bsdf.eval(wo, wi, color), Some(bsdf.pdf(wo, wi)))
+            };
+            result_color += Self::trace_to_emitters(ip, normal, scene, time, rng, response);
+            result_color += Self::trace_to_environment(ip, normal, scene, time, rng, response);
+
+            if refl_idx > 0 {
+                let sample = bsdf.sample(wo, color, rng).filter(|sample| {
+                    ray.throughput.scale(&sample.weight()).max_component() >= cutoff
+                });
+                if let Some(sample) = sample {
+                    let wi = frame.to_world(sample.wi);
+                    let bounce = Ray {
+                        origin: Self::offset_origin(ip, hit.normal, wi),
+                        direction: wi,
+                        time,
+                        differential: None,
+                        bsdf_pdf: Some(sample.pdf),
+                        throughput: ray.throughput.scale(&sample.weight()),
+                    };
+                    let indirect = Self::trace_color(&bounce, scene, refl_idx - 1, cutoff, rng);
+                    indirect_color += indirect.total().scale(&sample.weight());
+                    bounces = indirect.bounces + 1;
+                }
+            }
+            return Radiance {
+                direct: result_color,
+                indirect: indirect_color,
+                bounces,
+            };
+        }
 
-        if refl_idx > 0 {
+        let reflectivity = material.reflectivity_at(&coord);
+        let mut diff_color = Self::trace_to_lights(ip, normal, scene, time, rng, |_| Vector::one());
+        let response = |_| (Vector::one() / PI, None);
+        diff_color += Self::trace_to_emitters(ip, normal, scene, time, rng, response);
+        diff_color += Self::trace_to_environment(ip, normal, scene, time, rng, response);
+        result_color += diff_color.scale(&color) * (1.0 - reflectivity);
+
+        let throughput = ray.throughput.scale(&color) * reflectivity;
+        if refl_idx > 0 && throughput.max_component() >= cutoff {
             let refl_direction = direction.reflect(&normal);
-            let refl_color = Self::trace_color(ip, refl_direction, scene, refl_idx - 1);
-            result_color += refl_color.scale(&material.color) * material.refletivity_index;
+            // offset rays bounce off the surface curvature at their own hit points
+            let refl_differential =
+                ray.differential
+                    .zip(offsets)
+                    .map(|(d, (p_dx, p_dy))| RayDifferential {
+                        origin_dx: p_dx,
+                        origin_dy: p_dy,
+                        dir_dx: d.dir_dx.reflect(&shape.normal(p_dx, time)),
+                        dir_dy: d.dir_dy.reflect(&shape.normal(p_dy, time)),
+                    });
+            let reflected = Ray {
+                origin: Self::offset_origin(ip, hit.normal, refl_direction),
+                direction: refl_direction,
+                time,
+                differential: refl_differential,
+                bsdf_pdf: None,
+                throughput,
+            };
+            let refl_color = Self::trace_color(&reflected, scene, refl_idx - 1, cutoff, rng);
+            indirect_color += refl_color.total().scale(&color) * reflectivity;
+            bounces = refl_color.bounces + 1;
+        }
+
+        Radiance {
+            direct: result_color,
+            indirect: indirect_color,
+            bounces,
         }
+    }
+
+    /// Where the offset rays meet the tangent plane at hit point `ip`
+    fn offset_hits(
+        differential: &RayDifferential,
+        ip: Vector,
+        normal: Vector,
+    ) -> Option<(Vector, Vector)> {
+        let plane_hit = |origin: Vector, dir: Vector| {
+            let cos = dir.dot(&normal);
+            if cos.abs() < 1e-9 {
+                return None;
+            }
+            Some(origin + dir * ((ip - origin).dot(&normal) / cos))
+        };
+        Some((
+            plane_hit(differential.origin_dx, differential.dir_dx)?,
+            plane_hit(differential.origin_dy, differential.dir_dy)?,
+        ))
+    }
+
+    /// Change in UV over surface offset `dp`. Differences are taken on both sides of
+    /// the hit and the smaller one kept, so seams where UVs wrap don't blow up the footprint.
+    fn uv_delta(
+        shape: &dyn Shape,
+        ip: Vector,
+        dp: Vector,
+        uv: Option<(f64, f64)>,
+        time: f64,
+    ) -> (f64, f64) {
+        let (u, v) = match uv {
+            Some(uv) => uv,
+            None => return (0.0, 0.0),
+        };
+        let ahead = shape.uv(ip + dp, time).unwrap_or((u, v));
+        let behind = shape.uv(ip - dp, time).unwrap_or((u, v));
+        let smaller = |a: f64, b: f64| if a.abs() < b.abs() { a } else { b };
+        (
+            smaller(ahead.0 - u, u - behind.0),
+            smaller(ahead.1 - v, v - behind.1),
+        )
+    }
 
-        result_color
+    /// Start of a ray leaving the surface at `ip` towards `dir`, nudged along
+    /// `normal` past the rounding error `ip` may carry, so it doesn't hit the surface again
+    fn offset_origin(ip: Vector, normal: Vector, dir: Vector) -> Vector {
+        if dir.dot(&normal) < 0.0 {
+            ip - normal * surface_epsilon(ip)
+        } else {
+            ip + normal * surface_epsilon(ip)
+        }
     }
 
-    fn trace_to_lights(ip: Vector, normal: Vector, scene: &Scene) -> Vector {
+    /// Sum of the unoccluded point and directional lights reaching `ip`, each
+    /// weighted by `response` to its direction and by the cosine of incidence.
+    fn trace_to_lights(
+        ip: Vector,
+        normal: Vector,
+        scene: &Scene,
+        time: f64,
+        rng: &mut Rng,
+        response: impl Fn(Vector) -> Vector,
+    ) -> Vector {
         let mut total_color = Vector::zero();
 
         for light in &scene.point_lights {
-            let to_light = light.pos - ip;
+            let to_light = light.pos_at(time) - ip;
             let dist_to_light_sq = to_light.len_sq();
             let to_light = to_light.normalized();
 
@@ -58,7 +718,8 @@ impl Tracer {
                 continue;
             }
 
-            let ip2 = Self::closest_intersect(ip, to_light, &scene.shapes);
+            let origin = Self::offset_origin(ip, normal, to_light);
+            let ip2 = Self::closest_intersect(origin, to_light, &scene.shapes, time);
             if let Some((_, ip2t)) = ip2 {
                 if ip2t.powi(2) < dist_to_light_sq {
                     // path to light is occluded by geometry
@@ -66,22 +727,241 @@ impl Tracer {
                 }
             }
 
-            total_color += light.color * (light.power.powi(2) / dist_to_light_sq) * incidence_coeff;
+            let transmittance =
+                Self::transmittance(origin, to_light, dist_to_light_sq.sqrt(), scene, time, rng);
+            total_color += light
+                .color_at(time)
+                .scale(&response(to_light))
+                .scale(&transmittance)
+                * (light.power.powi(2) / dist_to_light_sq)
+                * incidence_coeff;
+        }
+
+        for light in scene.directional_lights() {
+            let incidence_coeff = light.direction.dot(&normal);
+            if incidence_coeff <= 0.0 {
+                continue;
+            }
+
+            let origin = Self::offset_origin(ip, normal, light.direction);
+            if Self::closest_intersect(origin, light.direction, &scene.shapes, time).is_some() {
+                continue;
+            }
+
+            let transmittance =
+                Self::transmittance(origin, light.direction, f64::INFINITY, scene, time, rng);
+            total_color += light
+                .color
+                .scale(&response(light.direction))
+                .scale(&transmittance)
+                * incidence_coeff;
+        }
+
+        total_color
+    }
+
+    /// Light reaching `ip` from one sample on each emissive shape. `response`
+    /// gives the BSDF value towards the light and, for weighting against BSDF
+    /// sampling, the density the BSDF would pick that direction with.
+    fn trace_to_emitters(
+        ip: Vector,
+        normal: Vector,
+        scene: &Scene,
+        time: f64,
+        rng: &mut Rng,
+        response: impl Fn(Vector) -> (Vector, Option<f64>),
+    ) -> Vector {
+        let mut total_color = Vector::zero();
+
+        for emitter in scene.emitters() {
+            let (point, light_normal) =
+                match emitter.sample_point(rng.next_f64(), rng.next_f64(), time) {
+                    Some(sample) => sample,
+                    None => continue,
+                };
+            let to_light = point - ip;
+            let dist = to_light.len();
+            let to_light = to_light / dist;
+
+            let incidence_coeff = to_light.dot(&normal);
+            let cos_light = -to_light.dot(&light_normal);
+            if incidence_coeff <= 0.0 || cos_light <= 0.0 {
+                continue;
+            }
+
+            let origin = Self::offset_origin(ip, normal, to_light);
+            if let Some((_, t)) = Self::closest_intersect(origin, to_light, &scene.shapes, time) {
+                if t < dist - 2.0 * surface_epsilon(point) {
+                    continue;
+                }
+            }
+
+            // area density turned into solid angle as seen from ip
+            let light_pdf = dist * dist / (emitter.area() * cos_light);
+            let (f, bsdf_pdf) = response(to_light);
+            let weight = bsdf_pdf.map_or(1.0, |bsdf_pdf| power_heuristic(light_pdf, bsdf_pdf));
+            let transmittance = Self::transmittance(origin, to_light, dist, scene, time, rng);
+            total_color += emitter
+                .get_material()
+                .emitted()
+                .scale(&f)
+                .scale(&transmittance)
+                * (incidence_coeff * weight / light_pdf);
+        }
+
+        total_color
+    }
+
+    /// Light reaching `ip` from one sample of the environment, `response` as in `trace_to_emitters`
+    fn trace_to_environment(
+        ip: Vector,
+        normal: Vector,
+        scene: &Scene,
+        time: f64,
+        rng: &mut Rng,
+        response: impl Fn(Vector) -> (Vector, Option<f64>),
+    ) -> Vector {
+        let env = match scene.background() {
+            Some(env) => env,
+            None => return Vector::zero(),
+        };
+        let (to_light, radiance, light_pdf) = match env.sample(rng.next_f64(), rng.next_f64()) {
+            Some(sample) => sample,
+            None => return Vector::zero(),
+        };
+
+        let incidence_coeff = to_light.dot(&normal);
+        if incidence_coeff <= 0.0 {
+            return Vector::zero();
+        }
+        let origin = Self::offset_origin(ip, normal, to_light);
+        if Self::closest_intersect(origin, to_light, &scene.shapes, time).is_some() {
+            return Vector::zero();
+        }
+
+        let (f, bsdf_pdf) = response(to_light);
+        let weight = bsdf_pdf.map_or(1.0, |bsdf_pdf| power_heuristic(light_pdf, bsdf_pdf));
+        let transmittance = Self::transmittance(origin, to_light, f64::INFINITY, scene, time, rng);
+        radiance.scale(&f).scale(&transmittance) * (incidence_coeff * weight / light_pdf)
+    }
+
+    /// Share of light surviving the media between `origin` and distance `dist` along `dir`
+    fn transmittance(
+        origin: Vector,
+        dir: Vector,
+        dist: f64,
+        scene: &Scene,
+        time: f64,
+        rng: &mut Rng,
+    ) -> Vector {
+        let mut total = Vector::one();
+        for volume in &scene.volumes {
+            total = total.scale(&volume.transmittance(origin, dir, dist, time, rng));
+        }
+        total
+    }
+
+    /// Light the media scatter towards the start of `ray` from its first `max_t`
+    /// units, from one point picked in each volume.
+    fn scatter_from_media(ray: &Ray, max_t: f64, scene: &Scene, rng: &mut Rng) -> Vector {
+        let mut total_color = Vector::zero();
+
+        for (i, volume) in scene.volumes.iter().enumerate() {
+            let (t, weight) =
+                match volume.sample_scatter(ray.origin, ray.direction, max_t, ray.time, rng) {
+                    Some(sample) => sample,
+                    None => continue,
+                };
+
+            // the pick already accounts for the volume's own transmittance
+            let mut transmittance = weight;
+            for (j, other) in scene.volumes.iter().enumerate() {
+                if j != i {
+                    transmittance = transmittance.scale(&other.transmittance(
+                        ray.origin,
+                        ray.direction,
+                        t,
+                        ray.time,
+                        rng,
+                    ));
+                }
+            }
+            let point = ray.origin + ray.direction * t;
+            let in_scattered = Self::scatter_from_lights(
+                point,
+                ray.direction,
+                &volume.medium,
+                scene,
+                ray.time,
+                rng,
+            );
+            total_color += transmittance.scale(&in_scattered);
         }
 
         total_color
     }
 
+    /// Light from the point and directional lights scattered at `point` into the
+    /// ray travelling along `dir`, per unit of scattering coefficient.
+    fn scatter_from_lights(
+        point: Vector,
+        dir: Vector,
+        medium: &Medium,
+        scene: &Scene,
+        time: f64,
+        rng: &mut Rng,
+    ) -> Vector {
+        let mut total_color = Vector::zero();
+
+        // pi as in the BSDF path, light powers are calibrated as diffuse irradiance
+        for light in &scene.point_lights {
+            let to_light = light.pos_at(time) - point;
+            let dist = to_light.len();
+            let to_light = to_light / dist;
+
+            if let Some((_, t)) = Self::closest_intersect(point, to_light, &scene.shapes, time) {
+                if t < dist {
+                    continue;
+                }
+            }
+
+            let transmittance = Self::transmittance(point, to_light, dist, scene, time, rng);
+            total_color += light.color_at(time).scale(&transmittance)
+                * (light.power.powi(2) / (dist * dist)
+                    * medium.phase.eval(dir.dot(&to_light))
+                    * PI);
+        }
+
+        for light in scene.directional_lights() {
+            let to_light = light.direction;
+            if Self::closest_intersect(point, to_light, &scene.shapes, time).is_some() {
+                continue;
+            }
+
+            let transmittance =
+                Self::transmittance(point, to_light, f64::INFINITY, scene, time, rng);
+            total_color +=
+                light.color.scale(&transmittance) * (medium.phase.eval(dir.dot(&to_light)) * PI);
+        }
+
+        total_color
+    }
+
+    /// Nearest shape along the ray and the distance to it. Hits within the
+    /// surface epsilon of `pos` are skipped: rays leaving a surface start just
+    /// off it, and this keeps them from finding it again if the nudge falls short.
     fn closest_intersect(
         pos: Vector,
         dir: Vector,
         shapes: &Vec<Box<dyn Shape>>,
+        time: f64,
     ) -> Option<(&dyn Shape, f64)> {
         let mut closest_intersect = None;
+        let t_min = surface_epsilon(pos);
 
         for sph in shapes {
-            let t = sph.intersect(pos, dir);
-            if let None = t {
+            let t = sph.intersect(pos, dir, t_min, time);
+            if t.is_none() {
                 continue;
             }
             if let Some((_, x)) = closest_intersect {
@@ -95,3 +975,230 @@ impl Tracer {
         closest_intersect
     }
 }
+
+/// Multiple importance sampling weight of a strategy with density `a` against one with `b`
+#[inline]
+fn power_heuristic(a: f64, b: f64) -> f64 {
+    if a.is_infinite() {
+        return 1.0;
+    }
+    a * a / (a * a + b * b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::camera::Camera;
+    use crate::geometry::{
+        planes::{PlaneXY, PlaneXZ},
+        scene::Scene,
+        shape::Shape,
+        sphere::Sphere,
+        vector::Vector,
+    };
+    use crate::material::Material;
+    use crate::random::Rng;
+
+    use super::{Tracer, MAX_DEPTH, MIN_ADAPTIVE_SAMPLES};
+
+    /// Mirrors facing each other across the camera, sending its centre ray back and forth
+    fn facing_mirrors(reflectivity: f64, max_depth: Option<u32>) -> Scene {
+        let mirror = || Material {
+            refletivity_index: reflectivity,
+            max_depth,
+            ..Default::default()
+        };
+        let mut scene = Scene::empty();
+        scene.ambient_light = Vector::one() * 0.1;
+        scene.shapes.push(Box::new(PlaneXY::new(
+            5.0,
+            true,
+            (-10.0, 10.0),
+            (-10.0, 10.0),
+            mirror(),
+        )));
+        scene.shapes.push(Box::new(PlaneXY::new(
+            -5.0,
+            false,
+            (-10.0, 10.0),
+            (-10.0, 10.0),
+            mirror(),
+        )));
+        scene
+    }
+
+    fn centre_bounces(tracer: &Tracer, scene: &Scene) -> u32 {
+        let camera = Camera::new(
+            Vector::zero(),
+            Vector::one_z(),
+            Vector::one_y(),
+            PI / 3.0,
+            1.0,
+        );
+        tracer
+            .sample_pixel(2, 2, (5, 5), &camera, scene, 0.0)
+            .0
+            .bounces
+    }
+
+    #[test]
+    fn depth_limits_bounces() {
+        let mut tracer = Tracer::new();
+        tracer.min_throughput = 0.0;
+        let scene = facing_mirrors(0.9, None);
+        for depth in [0, 1, 6] {
+            tracer.max_depth = depth;
+            assert_eq!(centre_bounces(&tracer, &scene), depth);
+        }
+
+        // the material caps the render depth, never raises it
+        tracer.max_depth = 6;
+        assert_eq!(centre_bounces(&tracer, &facing_mirrors(0.9, Some(2))), 2);
+        tracer.max_depth = 1;
+        assert_eq!(centre_bounces(&tracer, &facing_mirrors(0.9, Some(4))), 1);
+
+        // deeper paths are cut at the maximum, which a thread's stack still holds
+        tracer.max_depth = u32::MAX;
+        assert_eq!(
+            centre_bounces(&tracer, &facing_mirrors(1.0, None)),
+            MAX_DEPTH
+        );
+    }
+
+    #[test]
+    fn throughput_cutoff() {
+        let mut tracer = Tracer::new();
+        tracer.max_depth = 50;
+        tracer.min_throughput = 0.1;
+        // 0.5^3 still shows, 0.5^4 is under the cutoff
+        assert_eq!(centre_bounces(&tracer, &facing_mirrors(0.5, None)), 3);
+        // nothing is reflected off a matte surface
+        assert_eq!(centre_bounces(&tracer, &facing_mirrors(0.0, None)), 0);
+
+        // the light cut off is what the cutoff says at most
+        let camera = Camera::new(
+            Vector::zero(),
+            Vector::one_z(),
+            Vector::one_y(),
+            PI / 3.0,
+            1.0,
+        );
+        let scene = facing_mirrors(0.5, None);
+        let cut = tracer.trace_pixel(2, 2, (5, 5), &camera, &scene, 0.0);
+        tracer.min_throughput = 0.0;
+        let full = tracer.trace_pixel(2, 2, (5, 5), &camera, &scene, 0.0);
+        // ambient 0.1 at every hit plus half the next one adds up to 0.2
+        assert!((full.x - 0.2).abs() < 1e-6);
+        assert!(full.x - cut.x > 0.0 && full.x - cut.x < 0.1 * full.x);
+    }
+
+    /// Rays leaving the points `eye` sees of `shape` around `target` for all of
+    /// the hemisphere above them, counting those that hit the shape they left.
+    /// Without `offset` the rays start right on the hit points.
+    fn self_hits(
+        shape: Box<dyn Shape>,
+        eye: Vector,
+        target: Vector,
+        spread: f64,
+        offset: bool,
+    ) -> usize {
+        let shapes = vec![shape];
+        let mut rng = Rng::new(7);
+        // uniform in -1..1 on each axis
+        let mut random =
+            || Vector::new(rng.next_f64(), rng.next_f64(), rng.next_f64()) * 2.0 - Vector::one();
+        let mut hits = 0;
+        for _ in 0..200 {
+            let dir = (target + random() * spread - eye).normalized();
+            let (shape, t) = match Tracer::closest_intersect(eye, dir, &shapes, 0.0) {
+                Some(hit) => hit,
+                None => continue,
+            };
+            let hit = shape.hit(eye, dir, t, 0.0);
+            for _ in 0..20 {
+                let mut out = random().normalized();
+                if out.dot(&hit.normal) < 0.0 {
+                    out = -out;
+                }
+                let origin = if offset {
+                    Tracer::offset_origin(hit.point, hit.normal, out)
+                } else {
+                    hit.point
+                };
+                if Tracer::closest_intersect(origin, out, &shapes, 0.0).is_some() {
+                    hits += 1;
+                }
+            }
+        }
+        hits
+    }
+
+    #[test]
+    fn no_acne_far_from_the_origin() {
+        // the offset origin and the t_min of the shadow and bounce rays each
+        // keep them off the surface they leave
+        for offset in [true, false] {
+            // seen from far away, the sphere equation loses precision
+            let sphere = Sphere::new(Vector::zero(), 1.0, Material::default());
+            let eye = Vector::new(0.0, 0.0, -1e6);
+            assert_eq!(
+                self_hits(Box::new(sphere), eye, Vector::zero(), 0.5, offset),
+                0
+            );
+
+            // large coordinates round hit points further off the surface
+            let centre = Vector::new(3e10, -3e10, 3e10);
+            let sphere = Sphere::new(centre, 1e3, Material::default());
+            let eye = centre + Vector::new(1e3, 2e3, -4e3);
+            assert_eq!(self_hits(Box::new(sphere), eye, centre, 500.0, offset), 0);
+
+            let floor = PlaneXZ::new(3e10, false, (0.0, 1e11), (0.0, 1e11), Material::default());
+            let eye = Vector::new(5e10, 3e10 + 10.0, 5e10);
+            let target = Vector::new(5e10 + 50.0, 3e10, 5e10 + 50.0);
+            assert_eq!(self_hits(Box::new(floor), eye, target, 40.0, offset), 0);
+        }
+    }
+
+    #[test]
+    fn adaptive_sampling() {
+        // a lit wall covering the left half of the view, nothing on the right
+        let mut scene = Scene::empty();
+        scene.ambient_light = Vector::one() * 0.5;
+        scene.shapes.push(Box::new(PlaneXY::new(
+            5.0,
+            true,
+            (-10.0, 0.0),
+            (-10.0, 10.0),
+            Material::default(),
+        )));
+        let camera = Camera::new(
+            Vector::zero(),
+            Vector::one_z(),
+            Vector::one_y(),
+            PI / 3.0,
+            1.0,
+        );
+        let samples =
+            |tracer: &Tracer, x| tracer.sample_pixel(x, 2, (5, 5), &camera, &scene, 0.0).1;
+
+        let mut tracer = Tracer::new();
+        tracer.samples_per_pixel = 4;
+        assert_eq!(samples(&tracer, 2), 4);
+
+        tracer.noise_threshold = 0.05;
+        tracer.max_samples_per_pixel = 64;
+        // flat wall and empty background settle as soon as the variance is trusted
+        assert_eq!(samples(&tracer, 0), MIN_ADAPTIVE_SAMPLES);
+        assert_eq!(samples(&tracer, 4), MIN_ADAPTIVE_SAMPLES);
+        // the edge of the wall through the middle pixel keeps sampling up to the cap
+        assert_eq!(samples(&tracer, 2), 64);
+        tracer.noise_threshold = 0.25;
+        let relaxed = samples(&tracer, 2);
+        assert!(relaxed > MIN_ADAPTIVE_SAMPLES && relaxed < 64);
+
+        // more base samples than the cap are all taken
+        tracer.samples_per_pixel = 100;
+        assert_eq!(samples(&tracer, 0), 100);
+    }
+}